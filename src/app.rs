@@ -0,0 +1,237 @@
+use std::io::Read;
+use std::path::Path;
+use better_term::{Color, flush_styles, Style};
+use crate::{cli, formatter, lint, lsp, matador_std, parser, repl};
+use crate::cli::{Command, FmtOptions, LintOptions, RunOptions};
+use crate::debug::{timed, ScriptError};
+use crate::interpreter::Interpreter;
+use crate::lint::{LintConfig, Severity};
+use crate::lexer::Lexer;
+use crate::logging::{Logger, StderrSink};
+use crate::module::FileResolver;
+use crate::node::Node;
+use crate::sexpr::ToSexpr;
+
+// the matador command line
+pub fn main() {
+    let command = cli::parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}Error: {}{}", Color::BrightRed, Color::Red, e);
+        flush_styles();
+        eprintln!("{}", cli::USAGE);
+        std::process::exit(2);
+    });
+    match command {
+        Command::Run(options) => {
+            let logger = logger(options.log.as_deref());
+            run(options, logger);
+        }
+        Command::Repl(options) => {
+            let mut repl = repl::Repl::new(options.args);
+            if let Some(logger) = logger(options.log.as_deref()) {
+                repl.set_logger(logger);
+            }
+            repl.run();
+        }
+        Command::Lsp => {
+            let code = lsp::serve(std::io::stdin().lock(), std::io::stdout().lock());
+            std::process::exit(code);
+        }
+        Command::Fmt(options) => std::process::exit(fmt(options)),
+        Command::Lint(options) => std::process::exit(lint(options)),
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Version => {
+            println!("{}Matador {}v0.1ALPHA",
+                     Style::new().fg(Color::Cyan).bold(),
+                     Style::new().overwrite().fg(Color::BrightCyan));
+            flush_styles();
+        }
+    }
+}
+
+// logs to stderr by the MATADOR_LOG environment variable and the --log flag
+fn logger(filter: Option<&str>) -> Option<Logger> {
+    let env = std::env::var("MATADOR_LOG").ok();
+    if env.is_none() && filter.is_none() {
+        return None;
+    }
+    let mut logger = Logger::new(Box::new(StderrSink));
+    for filter in env.iter().map(|f| f.as_str()).chain(filter) {
+        if let Err(e) = logger.apply_filter(filter) {
+            eprintln!("{}Error: {}{}", Color::BrightRed, Color::Red, e);
+            flush_styles();
+            std::process::exit(2);
+        }
+    }
+    Some(logger)
+}
+
+fn run(options: RunOptions, logger: Option<Logger>) {
+    let code = read_script(&options);
+    let mut interpreter = Interpreter::new();
+    // imports are relative to the script, or to the working directory for stdin
+    if !options.is_stdin() {
+        let dir = Path::new(&options.script).parent().unwrap_or(Path::new("."));
+        interpreter.set_resolver(Box::new(FileResolver::new(dir)));
+    }
+    interpreter.set_args(&options.args);
+    if let Some(logger) = logger {
+        interpreter.set_logger(logger);
+    }
+    matador_std::attach_std(&mut interpreter);
+    interpreter.with_logger(|interpreter| run_script(&options, &code, interpreter));
+}
+
+fn run_script(options: &RunOptions, code: &str, interpreter: &mut Interpreter) {
+    let (tokens, lex_time) = timed(|| {
+        let mut lexer = Lexer::new(code);
+        lexer.lex()
+    });
+    let tokens = tokens.unwrap_or_else(|e| script_failed(&e));
+    let token_length = tokens.len();
+    if options.dump_tokens {
+        for token in tokens.iter() {
+            println!("{}", token.to_sexpr());
+        }
+        // the tokens of source that doesn't parse can still be dumped
+        if !options.dump_ast {
+            return;
+        }
+    }
+    let (nodes, parse_time) = timed(|| {
+        let mut parser = parser::Parser::new(tokens);
+        parser.parse()
+    });
+    let nodes = nodes.unwrap_or_else(|e| script_failed(&e));
+    if options.dump_ast {
+        for node in nodes.iter() {
+            println!("{}", node.to_sexpr().pretty(100));
+        }
+    }
+    // dumps are for other tools, so the script isn't run
+    if options.dump_tokens || options.dump_ast {
+        return;
+    }
+    if options.ast {
+        Node::prgm_display(&nodes);
+    }
+    let (result, interpret_time) = timed(|| interpreter.interpret(nodes));
+    if let Err(e) = result {
+        script_failed(&e);
+    }
+    if options.time {
+        eprintln!("{gb}Ran code in {y}{:?} {gb}with {y}{} {gb}tokens.",
+                  interpret_time + parse_time + lex_time, token_length,
+                  y = Style::new().overwrite().fg(Color::BrightYellow),
+                  gb = Style::new().fg(Color::BrightGreen).bold());
+        flush_styles();
+    }
+}
+
+// reports an error that stopped the script
+fn script_failed(e: &ScriptError) -> ! {
+    eprintln!("{}{}", Color::BrightRed, e);
+    flush_styles();
+    std::process::exit(1);
+}
+
+fn read_script(options: &RunOptions) -> String {
+    let result = if options.is_stdin() {
+        let mut code = String::new();
+        std::io::stdin().read_to_string(&mut code).map(|_| code)
+    } else {
+        std::fs::read_to_string(&options.script)
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("{}Error: {}could not read {}: {}", Color::BrightRed, Color::Red, options.script, e);
+        flush_styles();
+        std::process::exit(1);
+    })
+}
+
+// formats the files in place, returns the exit code
+fn fmt(options: FmtOptions) -> i32 {
+    let mut code = 0;
+    for file in options.files.iter() {
+        let Some(source) = read_source(file) else {
+            code = 1;
+            continue;
+        };
+        let formatted = match formatter::format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                print_source_error(file, &e);
+                code = 1;
+                continue;
+            }
+        };
+        if options.check {
+            if formatted != source {
+                println!("{} is not formatted", file);
+                code = 1;
+            }
+        } else if file == "-" {
+            print!("{}", formatted);
+        } else if formatted != source {
+            if let Err(e) = std::fs::write(file, formatted) {
+                eprintln!("{}Error: {}could not write {}: {}", Color::BrightRed, Color::Red, file, e);
+                flush_styles();
+                code = 1;
+            }
+        }
+    }
+    code
+}
+
+// lints the files, returns 1 when there are errors
+fn lint(options: LintOptions) -> i32 {
+    let mut config = LintConfig::default();
+    for (rule, severity) in options.severities.iter() {
+        config.set(*rule, *severity);
+    }
+    let mut code = 0;
+    for file in options.files.iter() {
+        let Some(source) = read_source(file) else {
+            code = 1;
+            continue;
+        };
+        let lints = match lint::lint_source(&source, &config) {
+            Ok(lints) => lints,
+            Err(e) => {
+                print_source_error(file, &e);
+                code = 1;
+                continue;
+            }
+        };
+        for lint in lints {
+            let color = if lint.severity == Severity::Error { Color::BrightRed } else { Color::BrightYellow };
+            let pos = lint.pos.map_or(String::new(), |(line, char)| format!(":{}:{}", line, char));
+            println!("{}{}{}: {}{}[{}]: {}{}", Color::White, file, pos, color, lint.severity, lint.rule, Color::White, lint.message);
+            flush_styles();
+            if lint.severity == Severity::Error {
+                code = 1;
+            }
+        }
+    }
+    code
+}
+
+// reads a file, or stdin for -, reporting errors
+fn read_source(file: &str) -> Option<String> {
+    let result = if file == "-" {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source).map(|_| source)
+    } else {
+        std::fs::read_to_string(file)
+    };
+    result.map_err(|e| {
+        eprintln!("{}Error: {}could not read {}: {}", Color::BrightRed, Color::Red, file, e);
+        flush_styles();
+    }).ok()
+}
+
+// prints an error in a file that doesn't lex or parse
+fn print_source_error(file: &str, e: &ScriptError) {
+    let pos = e.pos.map_or(String::new(), |(line, char)| format!(":{}:{}", line, char));
+    eprintln!("{}Error: {}{}{}: {}", Color::BrightRed, Color::Red, file, pos, e);
+    flush_styles();
+}
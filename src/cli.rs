@@ -0,0 +1,185 @@
+// command line arguments of the matador binary
+
+use crate::lint::{LintRule, Severity};
+
+pub const USAGE: &str = "\
+usage: matador [options] [--] <script> [args...]
+       matador [--log <filter>] repl [args...]
+       matador lsp
+       matador fmt [--check] <files...>
+       matador lint [--allow <rule>] [--warn <rule>] [--deny <rule>] <files...>
+
+Runs a matador script, use - as the script to read it from stdin. Put -- before scripts
+named like a subcommand or an option, i.e. `matador -- lint`.
+Arguments after the script are passed to it as the `args` array.
+`matador repl` starts an interactive session instead, `matador lsp` starts a language
+server for editors that talks over stdin and stdout. `matador fmt` formats scripts in
+place, or checks that they are formatted with --check, use - to format stdin to stdout.
+`matador lint` reports likely mistakes, the rules are unused-variable, shadowing,
+unreachable-code, undeclared-assignment, arity and constant-condition. --allow turns a
+rule off, --warn and --deny make it a warning or an error. Errors exit with 1.
+
+options:
+  -t, --time      print how long lexing, parsing and running took
+  -a, --ast       print the parsed program before running it
+  --dump-tokens   print the tokens as s-expressions instead of running
+  --dump-ast      print the parsed program as s-expressions instead of running
+  --log <filter>  log the lexer, parser, interpreter or scopes to stderr, i.e. `debug` or
+                  `parser=debug,scope=trace`, overrides the MATADOR_LOG environment variable
+  -h, --help      print this message
+  -v, --version   print the version";
+
+pub enum Command {
+    Run(RunOptions),
+    Repl(ReplOptions),
+    Lsp,
+    Fmt(FmtOptions),
+    Lint(LintOptions),
+    Help,
+    Version,
+}
+
+pub struct RunOptions {
+    // a file path, or - for stdin
+    pub script: String,
+    pub args: Vec<String>,
+    pub time: bool,
+    pub ast: bool,
+    pub dump_tokens: bool,
+    pub dump_ast: bool,
+    // log filter, i.e. `parser=debug`
+    pub log: Option<String>,
+}
+
+pub struct ReplOptions {
+    pub args: Vec<String>,
+    pub log: Option<String>,
+}
+
+pub struct FmtOptions {
+    // file paths, or - for stdin
+    pub files: Vec<String>,
+    // only report files that aren't formatted
+    pub check: bool,
+}
+
+pub struct LintOptions {
+    // file paths, or - for stdin
+    pub files: Vec<String>,
+    // severities set by --allow, --warn and --deny, in order
+    pub severities: Vec<(LintRule, Severity)>,
+}
+
+impl RunOptions {
+    pub fn is_stdin(&self) -> bool {
+        self.script == "-"
+    }
+}
+
+// options come before the script, everything after it belongs to the script. After `--` the
+// next argument is always the script, even when it is named like a subcommand
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut time = false;
+    let mut ast = false;
+    let mut dump_tokens = false;
+    let mut dump_ast = false;
+    let mut log = None;
+    let script = loop {
+        let arg = args.next().ok_or("no script given")?;
+        match arg.as_str() {
+            "--" => break args.next().ok_or("no script given")?,
+            "-t" | "--time" => time = true,
+            "-a" | "--ast" => ast = true,
+            "--dump-tokens" => dump_tokens = true,
+            "--dump-ast" => dump_ast = true,
+            "--log" => log = Some(args.next().ok_or("--log needs a filter")?),
+            "-h" | "--help" => return Ok(Command::Help),
+            "-v" | "--version" => return Ok(Command::Version),
+            "repl" => return Ok(Command::Repl(ReplOptions { args: args.collect(), log })),
+            "lsp" => return Ok(Command::Lsp),
+            "fmt" => return parse_fmt_args(args),
+            "lint" => return parse_lint_args(args),
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option '{}'", arg)),
+            _ => break arg,
+        }
+    };
+    Ok(Command::Run(RunOptions {
+        script,
+        args: args.collect(),
+        time,
+        ast,
+        dump_tokens,
+        dump_ast,
+        log,
+    }))
+}
+
+fn parse_fmt_args<I: Iterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut options = FmtOptions { files: Vec::new(), check: false };
+    for arg in args {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option '{}'", arg)),
+            _ => options.files.push(arg),
+        }
+    }
+    if options.files.is_empty() {
+        return Err("no files to format given".to_string());
+    }
+    Ok(Command::Fmt(options))
+}
+
+fn parse_lint_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let mut options = LintOptions { files: Vec::new(), severities: Vec::new() };
+    while let Some(arg) = args.next() {
+        let severity = match arg.as_str() {
+            "--allow" => Severity::Allow,
+            "--warn" => Severity::Warning,
+            "--deny" => Severity::Error,
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option '{}'", arg)),
+            _ => {
+                options.files.push(arg);
+                continue;
+            }
+        };
+        let rule = args.next().ok_or_else(|| format!("{} needs a rule", arg))?;
+        options.severities.push((rule.parse()?, severity));
+    }
+    if options.files.is_empty() {
+        return Err("no files to lint given".to_string());
+    }
+    Ok(Command::Lint(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    fn script(args: &[&str]) -> (String, Vec<String>) {
+        match parse(args) {
+            Ok(Command::Run(options)) => (options.script, options.args),
+            _ => panic!("{:?} did not parse as a script", args),
+        }
+    }
+
+    #[test]
+    fn subcommands() {
+        assert!(matches!(parse(&["repl"]), Ok(Command::Repl(_))));
+        assert!(matches!(parse(&["lint", "a.mtdr"]), Ok(Command::Lint(_))));
+        assert_eq!(script(&["-t", "repl.mtdr", "lint"]), ("repl.mtdr".to_string(), vec!["lint".to_string()]));
+    }
+
+    #[test]
+    fn double_dash() {
+        // scripts named like a subcommand or an option run after --
+        assert_eq!(script(&["--", "repl", "x"]), ("repl".to_string(), vec!["x".to_string()]));
+        assert_eq!(script(&["-t", "--", "lint"]).0, "lint");
+        assert_eq!(script(&["--", "-t"]).0, "-t");
+        assert!(matches!(parse(&["--"]), Err(e) if e == "no script given"));
+        assert!(matches!(parse(&[]), Err(e) if e == "no script given"));
+    }
+}
@@ -0,0 +1,28 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::debug::timed;
+
+/// The source of time for the time functions of scripts, a host can supply game time or a
+/// fake clock for tests
+pub trait Clock {
+    /// The current time in milliseconds
+    fn now_ms(&self) -> u64;
+
+    /// Waits for `ms` milliseconds and returns how long it actually waited
+    fn sleep(&mut self, ms: u64) -> u64;
+}
+
+/// The wall clock, times are milliseconds since the unix epoch
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+    }
+
+    fn sleep(&mut self, ms: u64) -> u64 {
+        // the os can sleep longer than asked
+        let ((), slept) = timed(|| std::thread::sleep(Duration::from_millis(ms)));
+        slept.as_millis() as u64
+    }
+}
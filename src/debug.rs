@@ -1,7 +1,5 @@
 use std::fmt;
 use std::time::Duration;
-use better_term::{Color, flush_styles};
-use crate::DEBUG_OUTPUT;
 
 pub(crate) fn timed<F: FnOnce() -> R, R>(f: F) -> (R, Duration) {
     let start = std::time::Instant::now();
@@ -10,22 +8,54 @@ pub(crate) fn timed<F: FnOnce() -> R, R>(f: F) -> (R, Duration) {
     (result, elapsed)
 }
 
-pub(crate) fn _debug_print(args: fmt::Arguments) {
-    if !DEBUG_OUTPUT {
-        return;
+/// An error that stopped a script, with where it happened in the source when known
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+    /// line and char, both starting at 1
+    pub pos: Option<(usize, usize)>,
+}
+
+impl ScriptError {
+    // the message is kept without its terminal colors
+    pub(crate) fn new(pos: Option<(usize, usize)>, args: fmt::Arguments) -> Self {
+        ScriptError { message: strip_styles(&args.to_string()), pos }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
     }
-    println!(
-        "{}DBG > {}{}",
-        Color::BrightBlack,
-        Color::White,
-        args,
-    );
-    flush_styles();
 }
 
-#[macro_export]
-macro_rules! debug_print {
+// builds the error that stops a script, returned with `Err` up to where it is reported
+macro_rules! script_error {
+    (at $pos:expr, $($arg:tt)*) => {
+        $crate::debug::ScriptError::new(Some($pos), format_args!($($arg)*))
+    };
     ($($arg:tt)*) => {
-        $crate::debug::_debug_print(format_args!($($arg)*));
+        $crate::debug::ScriptError::new(None, format_args!($($arg)*))
     };
-}
\ No newline at end of file
+}
+
+pub(crate) use script_error;
+
+// removes the terminal color codes from a message
+fn strip_styles(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // codes look like ESC [ ... m
+            for c in chars.by_ref() {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
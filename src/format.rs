@@ -0,0 +1,176 @@
+use std::fmt::{Display, Formatter};
+use crate::variable::Variable;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Align {
+    Left,   // <
+    Right,  // >
+    Center, // ^
+}
+
+// the largest width and precision, so a typo can't ask for gigabytes of padding
+const MAX_WIDTH: usize = 4096;
+
+// format specifier used in string interpolation, i.e. the `>8.2` in "{x:>8.2}"
+// syntax: [align][0][width][.precision]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct FormatSpec {
+    align: Option<Align>,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl FormatSpec {
+    // returns the error message for invalid specifiers
+    pub(crate) fn parse(spec: &str) -> Result<FormatSpec, String> {
+        let invalid = || format!("Invalid format specifier '{}'", spec);
+        let mut rest = spec;
+
+        let align = match rest.chars().next() {
+            Some('<') => Some(Align::Left),
+            Some('>') => Some(Align::Right),
+            Some('^') => Some(Align::Center),
+            _ => None,
+        };
+        if align.is_some() {
+            rest = &rest[1..];
+        }
+
+        let zero_pad = rest.starts_with('0');
+        if zero_pad {
+            rest = &rest[1..];
+        }
+
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision)),
+            None => (rest, None),
+        };
+        let width = if width.is_empty() { 0 } else { width.parse().map_err(|_| invalid())? };
+        let precision = match precision {
+            Some(p) => Some(p.parse().map_err(|_| invalid())?),
+            None => None,
+        };
+        if width > MAX_WIDTH || precision.is_some_and(|p| p > MAX_WIDTH) {
+            return Err(format!("Format specifier '{}' is too wide, widths and precisions go up to {}", spec, MAX_WIDTH));
+        }
+
+        Ok(FormatSpec { align, zero_pad, width, precision })
+    }
+
+    // formats a value, precision sets the number of decimals of floats
+    pub(crate) fn apply(&self, value: &Variable) -> String {
+        let s = match (value, self.precision) {
+            (Variable::Float(f), Some(p)) => format!("{:.*}", p, f),
+            _ => format!("{}", value),
+        };
+
+        let len = s.chars().count();
+        if len >= self.width {
+            return s;
+        }
+        let fill = self.width - len;
+        let numeric = matches!(value, Variable::Int(_) | Variable::Float(_));
+
+        // zeros go between the sign and the digits
+        if self.zero_pad && numeric && self.align.is_none() {
+            let (sign, digits) = match s.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", &s[..]),
+            };
+            return format!("{}{}{}", sign, "0".repeat(fill), digits);
+        }
+
+        // numbers are right aligned by default, everything else left aligned
+        let align = self.align.unwrap_or(if numeric { Align::Right } else { Align::Left });
+        match align {
+            Align::Left => format!("{}{}", s, " ".repeat(fill)),
+            Align::Right => format!("{}{}", " ".repeat(fill), s),
+            Align::Center => format!("{}{}{}", " ".repeat(fill / 2), s, " ".repeat(fill - fill / 2)),
+        }
+    }
+}
+
+// writes the specifier back in its source syntax
+impl Display for FormatSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.align {
+            Some(Align::Left) => write!(f, "<")?,
+            Some(Align::Right) => write!(f, ">")?,
+            Some(Align::Center) => write!(f, "^")?,
+            None => {}
+        }
+        if self.zero_pad {
+            write!(f, "0")?;
+        }
+        if self.width > 0 {
+            write!(f, "{}", self.width)?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{}", precision)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(spec: &str, value: Variable) -> String {
+        FormatSpec::parse(spec).unwrap().apply(&value)
+    }
+
+    #[test]
+    fn padding_and_alignment() {
+        // numbers are right aligned by default, everything else left aligned
+        assert_eq!(format("6", Variable::Int(42)), "    42");
+        assert_eq!(format("6", Variable::String("ab".into())), "ab    ");
+        assert_eq!(format("<6", Variable::Int(42)), "42    ");
+        assert_eq!(format(">6", Variable::Bool(true)), "  true");
+        assert_eq!(format("^7", Variable::String("abc".into())), "  abc  ");
+        assert_eq!(format("^6", Variable::String("abc".into())), " abc  ");
+        // the width counts chars, and values wider than it are left as they are
+        assert_eq!(format("^5", Variable::String("é".into())), "  é  ");
+        assert_eq!(format("2", Variable::Int(12345)), "12345");
+        assert_eq!(format("", Variable::Char('x')), "x");
+    }
+
+    #[test]
+    fn zero_padding() {
+        assert_eq!(format("05", Variable::Int(42)), "00042");
+        assert_eq!(format("05", Variable::Int(-42)), "-0042");
+        assert_eq!(format("08.2", Variable::Float(-1.5)), "-0001.50");
+        // an alignment or a value that isn't a number pads with spaces
+        assert_eq!(format("<05", Variable::Int(42)), "42   ");
+        assert_eq!(format("05", Variable::String("ab".into())), "ab   ");
+    }
+
+    #[test]
+    fn precision() {
+        assert_eq!(format(".2", Variable::Float(1.23456)), "1.23");
+        assert_eq!(format(".0", Variable::Float(2.7)), "3");
+        assert_eq!(format(">8.3", Variable::Float(1.0)), "   1.000");
+        // ints have no decimals to set
+        assert_eq!(format(".2", Variable::Int(3)), "3");
+        assert_eq!(format("04.2", Variable::Int(3)), "0003");
+    }
+
+    #[test]
+    fn invalid_specs() {
+        for spec in ["x", ">>5", "5.", ".x", "-3", "5.2.1", "0x10"] {
+            assert_eq!(FormatSpec::parse(spec), Err(format!("Invalid format specifier '{}'", spec)));
+        }
+        assert!(FormatSpec::parse("4096").is_ok());
+        assert!(FormatSpec::parse("4097").unwrap_err().contains("too wide"));
+        assert!(FormatSpec::parse("99999999999").unwrap_err().contains("too wide"));
+        assert!(FormatSpec::parse(".99999").unwrap_err().contains("too wide"));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for spec in ["", "<8", ">08.2", "^3", "05", ".1"] {
+            assert_eq!(FormatSpec::parse(spec).unwrap().to_string(), spec);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::ops::Range;
+use crate::debug::ScriptError;
+use crate::lexer::{Lexer, Token, TokenType};
+use crate::operator::Operator;
+use crate::parser::Parser;
+use crate::syntax::{lex_tolerant, Lexed};
+
+const INDENT: &str = "    ";
+
+// a token or a comment of the source, with the line breaks before it
+struct Item<'a> {
+    kind: ItemKind,
+    text: &'a str,
+    newlines: usize,
+    // the width of the whitespace before it on its line
+    spaces: usize,
+}
+
+enum ItemKind {
+    Token(TokenType),
+    LineComment,
+    BlockComment,
+}
+
+/// Formats matador source code. Comments and line breaks are kept, while indentation,
+/// spacing around operators, the placement of `else` and blank lines are made canonical.
+/// Source that doesn't lex or parse is returned as an error
+pub fn format_source(source: &str) -> Result<String, ScriptError> {
+    let before = items(source)?;
+    let tokens = Lexer::new(source).lex()?;
+    Parser::new(tokens).parse()?;
+    let formatted = emit(&before);
+
+    // only whitespace may change, anything else is a bug in the formatter
+    let changed = items(&formatted).map_or(true, |after| {
+        after.len() != before.len() || after.iter().zip(before.iter()).any(|(a, b)| a.text != b.text)
+    });
+    if changed {
+        return Err(ScriptError { message: "Formatting changed the meaning of the source".to_string(), pos: None });
+    }
+    Ok(formatted)
+}
+
+fn items(source: &str) -> Result<Vec<Item<'_>>, ScriptError> {
+    let mut items = Vec::new();
+    let mut newlines = 0;
+    let mut spaces = 0;
+    let text = |range: Range<usize>| &source[range];
+    for lexed in lex_tolerant(source) {
+        let (kind, text) = match lexed {
+            Lexed::Trivia(false, range, _) => {
+                let whitespace = text(range);
+                newlines += whitespace.matches('\n').count();
+                spaces = whitespace.len() - whitespace.rfind('\n').map_or(0, |i| i + 1);
+                continue;
+            }
+            Lexed::Trivia(true, range, _) => {
+                let comment = text(range);
+                if comment.starts_with("//") {
+                    (ItemKind::LineComment, comment.trim_end())
+                } else {
+                    (ItemKind::BlockComment, comment)
+                }
+            }
+            Lexed::Token(Token { token_type, range, .. }) => (ItemKind::Token(token_type), text(range)),
+            Lexed::Error(mut e, _, pos) => {
+                e.pos = Some(pos);
+                return Err(e);
+            }
+        };
+        items.push(Item { kind, text, newlines, spaces });
+        newlines = 0;
+        spaces = 0;
+    }
+    Ok(items)
+}
+
+fn emit(items: &[Item]) -> String {
+    let mut out = String::new();
+    // the brackets that are still open, by the line that opened them and whether their
+    // contents start on a new line
+    let mut open: Vec<(usize, bool)> = Vec::new();
+    let mut line = 0;
+    let mut prev: Option<(&ItemKind, bool)> = None;
+
+    for item in items.iter() {
+        let mut newlines = item.newlines.min(2);
+        let token = match &item.kind {
+            ItemKind::Token(token_type) => Some(token_type),
+            _ => None,
+        };
+        let prev_token = match prev {
+            Some((ItemKind::Token(token_type), _)) => Some(token_type),
+            _ => None,
+        };
+        match (prev_token, token) {
+            // `} else` on one line
+            (Some(TokenType::RBrace), Some(TokenType::Else)) => newlines = 0,
+            // no blank lines at the start and end of blocks
+            (Some(TokenType::LBrace), _) | (_, Some(TokenType::RBrace)) => newlines = newlines.min(1),
+            _ => {}
+        }
+        match prev {
+            None => newlines = 0,
+            Some((ItemKind::LineComment, _)) => newlines = newlines.max(1),
+            _ => {}
+        }
+
+        if prev_token.is_some_and(opens) {
+            if let Some(bracket) = open.last_mut() {
+                bracket.1 = newlines > 0;
+            }
+        }
+        if token.is_some_and(closes) {
+            // the bracket of a block over several lines gets a line of its own
+            if open.pop().is_some_and(|(_, multiline)| multiline) {
+                newlines = newlines.max(1);
+            }
+        }
+        // an operator is prefix when it doesn't follow a value on its line
+        let prefix = token.is_some_and(can_be_prefix)
+            && (newlines > 0 || !prev_token.is_some_and(ends_value));
+
+        if newlines > 0 {
+            out.push_str(&"\n".repeat(newlines));
+            line += 1;
+            let mut lines: Vec<usize> = open.iter().map(|(line, _)| *line).collect();
+            lines.dedup();
+            out.push_str(&INDENT.repeat(lines.len()));
+        } else if let Some((prev_kind, prev_prefix)) = prev {
+            // trailing comments keep their alignment
+            if let ItemKind::LineComment = item.kind {
+                out.push_str(&" ".repeat(item.spaces.max(1)));
+            } else if spaced(prev_kind, prev_prefix, &item.kind, prefix) {
+                out.push(' ');
+            }
+        }
+        out.push_str(item.text);
+
+        if token.is_some_and(opens) {
+            open.push((line, false));
+        }
+        prev = Some((&item.kind, prefix));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+// whether there is a space between two items on the same line
+fn spaced(prev: &ItemKind, prev_prefix: bool, next: &ItemKind, next_prefix: bool) -> bool {
+    let (ItemKind::Token(prev), ItemKind::Token(next)) = (prev, next) else {
+        return true;
+    };
+    match (prev, next) {
+        (_, TokenType::Comma | TokenType::Colon | TokenType::Decimal | TokenType::RBracket | TokenType::Op(Operator::RParen)) => false,
+        (TokenType::Decimal | TokenType::LBracket | TokenType::Op(Operator::LParen), _) => false,
+        (TokenType::Op(Operator::Range), _) | (_, TokenType::Op(Operator::Range)) => false,
+        (TokenType::LBrace, TokenType::RBrace) => false,
+        // `-x`, `!x` and `++i`
+        (TokenType::Op(_), _) if prev_prefix => false,
+        // `i++`
+        (_, TokenType::Op(Operator::Inc | Operator::Dec)) if !next_prefix => false,
+        // calls and indexes
+        (TokenType::Ident(_) | TokenType::VariableType(_), TokenType::Op(Operator::LParen)) => false,
+        (prev, TokenType::LBracket) => !ends_value(prev),
+        _ => true,
+    }
+}
+
+fn can_be_prefix(token_type: &TokenType) -> bool {
+    matches!(token_type, TokenType::Op(Operator::Minus | Operator::Not | Operator::Inc | Operator::Dec))
+}
+
+fn ends_value(token_type: &TokenType) -> bool {
+    matches!(token_type, TokenType::Ident(_) | TokenType::Int(_) | TokenType::Float(_) | TokenType::Bool(_)
+        | TokenType::String(_) | TokenType::Char(_) | TokenType::InterpolatedString(_) | TokenType::VariableType(_)
+        | TokenType::RBracket | TokenType::Op(Operator::RParen))
+}
+
+fn opens(token_type: &TokenType) -> bool {
+    matches!(token_type, TokenType::LBrace | TokenType::LBracket | TokenType::Op(Operator::LParen))
+}
+
+fn closes(token_type: &TokenType) -> bool {
+    matches!(token_type, TokenType::RBrace | TokenType::RBracket | TokenType::Op(Operator::RParen))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_source;
+
+    fn format(source: &str) -> String {
+        format_source(source).unwrap()
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(format("let  x=-1+2*(3 -y)\n"), "let x = -1 + 2 * (3 - y)\n");
+        assert_eq!(format("let a=[ 1,2 ,arr [0]]\nprintln( len(a),math.sqrt(4.0) )"), "let a = [1, 2, arr[0]]\nprintln(len(a), math.sqrt(4.0))\n");
+        assert_eq!(format("if ! (a is int)&&b>=0{i ++}\nlet r=0 .. 10"), "if !(a is int) && b >= 0 { i++ }\nlet r = 0..10\n");
+        assert_eq!(format("fn f(a,b){}\nlet n = sizeof f(1,2)\nlet s=n as string"), "fn f(a, b) {}\nlet n = sizeof f(1, 2)\nlet s = n as string\n");
+    }
+
+    #[test]
+    fn indentation_and_braces() {
+        let source = "fn f(x) {\n\n  if x {\n        return 1\n  }\n  else if !x {\n  return 2 }\n\n\n\n  return [\n1,\n2\n]\n\n}\n";
+        let formatted = "fn f(x) {\n    if x {\n        return 1\n    } else if !x {\n        return 2\n    }\n\n    return [\n        1,\n        2\n    ]\n}\n";
+        assert_eq!(format(source), formatted);
+    }
+
+    #[test]
+    fn comments() {
+        let source = "\n\n/* header\n   text */\nlet x = 1  // one   \nwhile x<3 {\n// inside\n x = x + /* two */ 1\n}";
+        let formatted = "/* header\n   text */\nlet x = 1  // one\nwhile x < 3 {\n    // inside\n    x = x + /* two */ 1\n}\n";
+        assert_eq!(format(source), formatted);
+        // a comment between `}` and `else` stays where it is
+        assert_eq!(format("if a {\n}\n// no\nelse {\n}"), "if a {\n}\n// no\nelse {\n}\n");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(format_source("let s = \"open").unwrap_err().pos, Some((1, 9)));
+        assert!(format_source("let = 5").is_err());
+        assert_eq!(format(""), "");
+        // a file that is being edited reports where it stops instead of crashing
+        assert_eq!(format_source("fn f() {\n  let x = 1\n").unwrap_err().pos, Some((3, 1)));
+        assert!(format_source("println(f(1,\n").is_err());
+        assert!(format_source("let a = [1, 2\n").is_err());
+    }
+
+    #[test]
+    fn test_scripts_are_formatted() {
+        let mut dirs = vec![std::path::PathBuf::from("matador_tests")];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "mtdr") {
+                    let source = std::fs::read_to_string(&path).unwrap();
+                    let formatted = format_source(&source).unwrap();
+                    assert_eq!(formatted, source, "{} is not formatted", path.display());
+                    assert_eq!(format_source(&formatted).unwrap(), formatted);
+                }
+            }
+        }
+    }
+}
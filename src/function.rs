@@ -1,13 +1,20 @@
+use crate::debug::ScriptError;
+use crate::interpreter::Interpreter;
 use crate::node::Node;
 use crate::variable::Variable;
 
-pub type NativeFunction = fn(Vec<Variable>) -> Variable;
+pub type NativeFunction = fn(&mut Interpreter, Vec<Variable>) -> Result<Variable, ScriptError>;
 
-#[derive(Debug, PartialEq, Clone)]
+// a native function that changes its first argument, when the argument is a variable
+// the variable itself is changed instead of a copy
+pub type InPlaceFunction = fn(&mut Interpreter, &mut Variable, Vec<Variable>) -> Result<Variable, ScriptError>;
+
+#[derive(Debug, Clone)]
 pub(crate) enum Function {
     // native rust functions
     Native(NativeFunction),
+    InPlace(InPlaceFunction),
 
     // local matador functions
     Local(Vec<String>, Node),
-}
\ No newline at end of file
+}
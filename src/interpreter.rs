@@ -1,21 +1,58 @@
+use std::collections::HashMap;
 use better_term::{Color, flush_styles};
-use crate::function::{Function, NativeFunction};
-use crate::variable::{Variable, VariableType};
+use crate::debug::{script_error, ScriptError};
+use crate::function::{Function, InPlaceFunction, NativeFunction};
+use crate::variable::{Overflow, Variable, VariableType};
 use crate::node::Node;
 use crate::operator::Operator;
 use crate::postfix::ShuntedStackItem;
+use crate::random::Rng;
+use crate::clock::{Clock, SystemClock};
+use crate::lexer::Lexer;
+use crate::module::{FileResolver, ModuleResolver, NativeModule};
+use crate::parser::Parser;
 use crate::scope::ScopeHandler;
+use crate::logging::{self, log, Logger};
 
 #[derive(Debug, PartialEq, Clone)]
 enum InterFlag {
     Break,
     Continue,
     Return(Option<Box<Node>>),
+    // stops the whole program, including the modules and functions it is in
+    Exit,
 }
 
 pub struct Interpreter {
     env: ScopeHandler,
     flag: Option<InterFlag>,
+    overflow: Overflow,
+    rng: Rng,
+    clock: Box<dyn Clock>,
+    // clock time when the interpreter started, for elapsed()
+    start_ms: u64,
+    // native functions and constants are visible from every module
+    natives: HashMap<String, Function>,
+    constants: HashMap<String, Variable>,
+
+    // imported modules by key, each module keeps its own variables and functions
+    modules: HashMap<String, ScopeHandler>,
+    resolver: Box<dyn ModuleResolver>,
+    // the key of the module that is running, None for the main program
+    module: Option<String>,
+    // the modules further up the call stack and their environments, innermost last. a module
+    // can be in it more than once, only its innermost entry holds its environment
+    suspended: Vec<(Option<String>, ScopeHandler)>,
+    // modules that are being loaded, to detect import cycles
+    loading: Vec<String>,
+    // taken out while it is installed by with_logger
+    logger: Option<Logger>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
@@ -23,148 +60,491 @@ impl Interpreter {
         Interpreter {
             env: ScopeHandler::new(),
             flag: None,
+            overflow: Overflow::default(),
+            rng: Rng::from_time(),
+            clock: Box::new(SystemClock),
+            start_ms: SystemClock.now_ms(),
+            natives: HashMap::new(),
+            constants: HashMap::new(),
+            modules: HashMap::new(),
+            resolver: Box::new(FileResolver::default()),
+            module: None,
+            suspended: Vec::new(),
+            loading: Vec::new(),
+            logger: None,
         }
     }
 
-    pub fn interpret(&mut self, nodes: Vec<Node>) {
-        for node in nodes {
-            self.eval(node);
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    pub(crate) fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    // the same state always gives the same random numbers
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.rng = Rng::new(state);
+    }
+
+    pub fn rng_state(&self) -> u64 {
+        self.rng.state()
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    // elapsed time is measured from when the clock is set
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.start_ms = clock.now_ms();
+        self.clock = clock;
+    }
+
+    pub(crate) fn clock(&mut self) -> &mut dyn Clock {
+        self.clock.as_mut()
+    }
+
+    pub(crate) fn start_ms(&self) -> u64 {
+        self.start_ms
+    }
+
+    pub fn set_resolver(&mut self, resolver: Box<dyn ModuleResolver>) {
+        self.resolver = resolver;
+    }
+
+    // logs of everything the interpreter runs go to the logger, including the lexer and
+    // parser of imported modules
+    pub fn set_logger(&mut self, logger: Logger) {
+        self.logger = Some(logger);
+    }
+
+    pub(crate) fn take_logger(&mut self) -> Option<Logger> {
+        self.logger.take()
+    }
+
+    // runs `f` with the logger installed for this thread, so the lexer, parser and scopes
+    // log to it too. the logger that was installed before is back afterwards
+    pub(crate) fn with_logger<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let Some(logger) = self.logger.take() else {
+            // not set, or already installed further up
+            return f(self);
+        };
+        let outer = logging::replace_logger(Some(logger));
+        let result = f(self);
+        self.logger = logging::replace_logger(outer);
+        result
+    }
+
+    pub(crate) fn interpret(&mut self, nodes: Vec<Node>) -> Result<(), ScriptError> {
+        self.with_logger(|this| {
+            for node in nodes {
+                this.eval(node)?;
+            }
+            Ok(())
+        })
+    }
+
+    // runs nodes and returns their values, the interpreter can keep running after an error,
+    // with the changes made before it
+    pub(crate) fn try_interpret(&mut self, nodes: Vec<Node>) -> Result<Vec<Variable>, ScriptError> {
+        let values = self.with_logger(|this| nodes.into_iter().map(|node| this.eval(node)).collect());
+        // a return or break outside of a function or loop ends with the nodes, an exit is
+        // kept for exited()
+        if self.flag != Some(InterFlag::Exit) {
+            self.flag = None;
         }
+        values
+    }
+
+    // whether the program ran `exit`, nothing runs after it
+    pub(crate) fn exited(&self) -> bool {
+        self.flag == Some(InterFlag::Exit)
+    }
+
+    // the variables of the program, sorted by name
+    pub(crate) fn variables(&self) -> Vec<(&String, &Variable)> {
+        self.env.variables()
+    }
+
+    // the functions declared by the program and the names of the native functions, sorted by name
+    pub(crate) fn functions(&self) -> (Vec<(&String, &Function)>, Vec<&String>) {
+        let mut natives: Vec<&String> = self.natives.keys().collect();
+        natives.sort();
+        (self.env.functions(), natives)
     }
 
     pub fn register_native_function<S: Into<String>>(&mut self, name: S, f: NativeFunction) {
-        self.env.push_function(name.into(), Function::Native(f));
+        self.natives.insert(name.into(), Function::Native(f));
+    }
+
+    pub fn register_in_place_function<S: Into<String>>(&mut self, name: S, f: InPlaceFunction) {
+        self.natives.insert(name.into(), Function::InPlace(f));
+    }
+
+    // a constant is visible from every module, variables of the same name hide it
+    pub fn register_constant<S: Into<String>>(&mut self, name: S, value: Variable) {
+        self.constants.insert(name.into(), value);
+    }
+
+    // the arguments passed to the script, as the `args` array of strings
+    pub fn set_args(&mut self, args: &[String]) {
+        let args = args.iter().map(|a| Box::new(Variable::String(a.as_str().into()))).collect();
+        self.register_constant("args", Variable::Array(args));
+    }
+
+    // registers the functions and constants of a native module under its name, i.e. `math.sqrt`
+    pub fn register_module(&mut self, module: &dyn NativeModule) {
+        let prefix = format!("{}.", module.name());
+        self.register_members(module, &prefix);
+    }
+
+    // registers the functions and constants of a native module without its name, i.e. `sqrt`
+    pub fn register_module_globally(&mut self, module: &dyn NativeModule) {
+        self.register_members(module, "");
+    }
+
+    fn register_members(&mut self, module: &dyn NativeModule, prefix: &str) {
+        for (name, f) in module.functions() {
+            self.register_native_function(format!("{}{}", prefix, name), f);
+        }
+        for (name, f) in module.in_place_functions() {
+            self.register_in_place_function(format!("{}{}", prefix, name), f);
+        }
+        for (name, value) in module.constants() {
+            self.register_constant(format!("{}{}", prefix, name), value);
+        }
+    }
+
+    // calls a function value (a reference to a named function) with evaluated arguments
+    pub fn call_value(&mut self, f: &Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        match f {
+            Variable::Function(ident, module) => self.in_module_key(module.clone(), |this| this.call_named(ident, args)),
+            _ => Err(script_error!("{}Not a function: {}{:?}", Color::BrightRed, Color::Red, f)),
+        }
+    }
+
+    // finds a function declared in the current module or a native function
+    fn find_function(&self, ident: &str) -> Option<Function> {
+        self.env.get_function(ident.to_string()).or_else(|| self.natives.get(ident)).cloned()
+    }
+
+    fn get_function(&self, ident: &str) -> Result<Function, ScriptError> {
+        self.find_function(ident).ok_or_else(|| {
+            script_error!("{}Invalid function call: {}{:?}", Color::BrightRed, Color::Red, ident)
+        })
     }
 
-    fn eval(&mut self, node: Node) -> Variable {
+    // calls a function by name, or through a variable holding a reference to it, members of
+    // imported modules run inside their module
+    fn call_named(&mut self, ident: &str, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        log!(Interpreter, Debug, "call {} with {} arguments", ident, args.len());
+        if self.find_function(ident).is_none() {
+            if let Some(f @ Variable::Function(..)) = self.env.get(ident) {
+                let f = f.clone();
+                return self.call_value(&f, args);
+            }
+            if let Some((namespace, member)) = ident.split_once('.') {
+                return self.in_module(namespace, |this| {
+                    let func = this.get_function(member)?;
+                    this.call_function(func, args)
+                });
+            }
+        }
+        let func = self.get_function(ident)?;
+        self.call_function(func, args)
+    }
+
+    // runs `f` with the variables and functions of the module imported as `namespace`
+    fn in_module<R>(&mut self, namespace: &str, f: impl FnOnce(&mut Self) -> Result<R, ScriptError>) -> Result<R, ScriptError> {
+        let key = match self.env.get(namespace) {
+            Some(Variable::Module(key)) => key.clone(),
+            _ => {
+                return Err(script_error!("{}Unknown module: {}{}", Color::BrightRed, Color::Red, namespace));
+            }
+        };
+        self.in_module_key(Some(key), f)
+    }
+
+    // runs `f` in the module with the key, or in the main program for None
+    fn in_module_key<R>(&mut self, key: Option<String>, f: impl FnOnce(&mut Self) -> Result<R, ScriptError>) -> Result<R, ScriptError> {
+        if key == self.module {
+            return f(self);
+        }
+        if let Some(env) = key.as_ref().and_then(|k| self.modules.remove(k)) {
+            let (env, result) = self.run_in(key.clone(), env, f);
+            self.modules.insert(key.unwrap(), env);
+            return result;
+        }
+        // the module is running further up the call stack, its environment is borrowed from there
+        let Some(i) = self.suspended.iter().rposition(|(module, _)| *module == key) else {
+            return Err(script_error!("{}Unknown module: {}{}", Color::BrightRed, Color::Red, key.unwrap_or_default()));
+        };
+        let env = std::mem::replace(&mut self.suspended[i].1, ScopeHandler::new());
+        let (env, result) = self.run_in(key, env, f);
+        self.suspended[i].1 = env;
+        result
+    }
+
+    // runs `f` in a module with its environment and returns the environment afterwards, the
+    // module is left even when `f` fails
+    fn run_in<R>(&mut self, key: Option<String>, env: ScopeHandler, f: impl FnOnce(&mut Self) -> Result<R, ScriptError>)
+                 -> (ScopeHandler, Result<R, ScriptError>) {
+        let outer_env = std::mem::replace(&mut self.env, env);
+        let outer_module = std::mem::replace(&mut self.module, key);
+        self.suspended.push((outer_module, outer_env));
+        let result = f(self);
+        let (outer_module, outer_env) = self.suspended.pop().unwrap();
+        self.module = outer_module;
+        (std::mem::replace(&mut self.env, outer_env), result)
+    }
+
+    // whether a module is loaded, it may be running
+    fn is_loaded(&self, key: &str) -> bool {
+        self.modules.contains_key(key) || self.module.as_deref() == Some(key)
+            || self.suspended.iter().any(|(module, _)| module.as_deref() == Some(key))
+    }
+
+    // resolves, loads and runs a module the first time it is imported, returns its key
+    fn import(&mut self, path: &str) -> Result<String, ScriptError> {
+        let key = self.resolver.resolve(path, self.module.as_deref()).map_err(|e| {
+            script_error!("{}Invalid import: {}{}", Color::BrightRed, Color::Red, e)
+        })?;
+        if self.loading.contains(&key) {
+            let mut cycle = self.loading.clone();
+            cycle.push(key);
+            return Err(script_error!("{}Import cycle: {}{}", Color::BrightRed, Color::Red, cycle.join(" -> ")));
+        }
+        if self.is_loaded(&key) {
+            return Ok(key);
+        }
+
+        let source = self.resolver.load(&key).map_err(|e| {
+            script_error!("{}Invalid import: {}{}", Color::BrightRed, Color::Red, e)
+        })?;
+        let tokens = Lexer::new(&source).lex()?;
+        let nodes = Parser::new(tokens).parse()?;
+
+        // run the module in its own environment, a module that fails isn't kept
+        self.loading.push(key.clone());
+        let (env, result) = self.run_in(Some(key.clone()), ScopeHandler::new(), |this| this.interpret(nodes));
+        self.loading.pop();
+        result?;
+
+        self.modules.insert(key.clone(), env);
+        log!(Interpreter, Info, "imported {} from {}", path, key);
+        Ok(key)
+    }
+
+    fn call_function(&mut self, func: Function, mut args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        match func {
+            Function::Native(f) => f(self, args),
+            Function::InPlace(f) => {
+                if args.is_empty() {
+                    return Err(script_error!("{}Invalid number of arguments: {}0", Color::BrightRed, Color::Red));
+                }
+                let mut target = args.remove(0);
+                f(self, &mut target, args)
+            }
+            Function::Local(params, body) => {
+                // ensure the number of arguments matches the number of parameters
+                if params.len() != args.len() {
+                    return Err(script_error!("{}Invalid number of arguments: {}{:?}", Color::BrightRed, Color::Red, params));
+                }
+                // create a new scope for the function
+                self.env.push_scope();
+                // set the parameters
+                for (param, arg) in params.iter().zip(args) {
+                    self.env.set(param, arg);
+                }
+                // run the body
+                let result = self.eval(body).and_then(|result| match self.flag.clone() {
+                    Some(InterFlag::Return(v)) => {
+                        self.flag = None;
+                        v.map_or(Ok(result), |v| self.eval(*v))
+                    }
+                    _ => Ok(result),
+                });
+                // remove the scope, also when the body failed
+                self.env.pop_scope();
+                result
+            }
+        }
+    }
+
+    // calls an in place function, if the first argument is a variable it is changed directly
+    fn call_in_place(&mut self, f: InPlaceFunction, args: Vec<Box<Node>>) -> Result<Variable, ScriptError> {
+        let mut args = args.into_iter();
+        let Some(target) = args.next() else {
+            return Err(script_error!("{}Invalid number of arguments: {}0", Color::BrightRed, Color::Red));
+        };
+        let rest = args.map(|arg| self.eval(*arg)).collect::<Result<_, _>>()?;
+
+        let ident = match *target {
+            Node::Ident(ref ident) => Some(ident.clone()),
+            Node::ShuntedStack(ref stack) => match stack.single_operand() {
+                Some(Node::Ident(ident)) => Some(ident.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        match ident {
+            Some(ident) if self.env.variable_exists(&ident) => {
+                // the function works on a copy, so callbacks into the interpreter still see the
+                // variable. the copy replaces the variable when the function succeeds, changes the
+                // callbacks made to the variable are lost
+                let mut value = self.env.get(&ident).unwrap().clone();
+                let result = f(self, &mut value, rest)?;
+                self.env.set(&ident, value);
+                Ok(result)
+            }
+            _ => {
+                let mut value = self.eval(*target)?;
+                f(self, &mut value, rest)
+            }
+        }
+    }
+
+    // runs the nodes of a block in the current scope
+    fn eval_block(&mut self, nodes: Vec<Node>) -> Result<Variable, ScriptError> {
+        let mut last = Variable::Int(0);
+        for node in nodes {
+            last = self.eval(node)?;
+            if let Some(f) = &self.flag {
+                match f {
+                    InterFlag::Break => break,
+                    InterFlag::Continue => continue,
+                    InterFlag::Return(_) | InterFlag::Exit => break
+                }
+            }
+        }
+        Ok(last)
+    }
+
+    fn eval(&mut self, node: Node) -> Result<Variable, ScriptError> {
         if self.flag.is_some() {
-            return Variable::Int(0);
+            return Ok(Variable::Int(0));
         }
-        match node.clone() {
+        Ok(match node.clone() {
             Node::Variable(n) => n,
             Node::Block(nodes) => {
-                let mut last = Variable::Int(0);
                 // create a new scope
                 self.env.push_scope();
-                for node in nodes {
-                    last = self.eval(node);
-                    if let Some(f) = &self.flag {
-                        match f {
-                            InterFlag::Break => break,
-                            InterFlag::Continue => continue,
-                            InterFlag::Return(_) => break
-                        }
-                    }
-                }
-                // remove the scope
+                let last = self.eval_block(nodes);
+                // remove the scope, also when the block failed
                 self.env.pop_scope();
-                last
+                last?
             }
-            Node::ShuntedStack(stack) => {
+            Node::ShuntedStack(mut stack) => {
                 flush_styles();
                 // interpret the stack and return the result
                 let mut operand_stack: Vec<Variable> = Vec::new();
-                for item in stack {
+                while let Some(item) = stack.next() {
                     match item {
                         ShuntedStackItem::Operand(node) => {
-                            operand_stack.push(self.eval(node));
+                            operand_stack.push(self.eval(node)?);
+                        },
+                        ShuntedStackItem::ShortCircuit(op, end) => {
+                            // skip the right operand when the left one already decides the result
+                            let decided = matches!((op, operand_stack.last()),
+                                (Operator::And, Some(Variable::Bool(false))) | (Operator::Or, Some(Variable::Bool(true))));
+                            if decided {
+                                stack.jump(end + 1);
+                            }
                         },
                         ShuntedStackItem::Operator(Operator::Minus) => {
                             if operand_stack.len() == 1 {
                                 let right = operand_stack.pop().unwrap();
-                                operand_stack.push(right.neg().unwrap_or_else(|| {
-                                    println!("{}Invalid operation (syom): {}{:?}", Color::BrightRed, Color::Red, right);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                }));
+                                operand_stack.push(right.neg(self.overflow)?.ok_or_else(|| {
+                                    script_error!("{}Invalid operation (syom): {}{:?}", Color::BrightRed, Color::Red, right)
+                                })?);
                             } else {
                                 let right = operand_stack.pop().unwrap();
                                 let left = operand_stack.pop().unwrap();
-                                if let Some(lit) = Operator::Minus.apply_binary(left.clone(), right.clone()) {
+                                if let Some(lit) = Operator::Minus.apply_binary(left.clone(), right.clone(), self.overflow)? {
                                     operand_stack.push(lit);
                                 } else {
-                                    println!("{}Invalid operation (syobm): {}{:?} - {:?}",
-                                         Color::BrightRed, Color::Red, left, right);
-                                    flush_styles();
-                                    std::process::exit(0);
+                                    return Err(script_error!("{}Invalid operation (syobm): {}{:?} - {:?}",
+                                         Color::BrightRed, Color::Red, left, right));
                                 }
                             }
                         },
                         ShuntedStackItem::Operator(op) => {
                             if !op.can_apply() {
-                                println!("{}Invalid operator: {}{:?}", Color::BrightRed, Color::Red, op);
-                                flush_styles();
-                                std::process::exit(0);
+                                return Err(script_error!("{}Invalid operator: {}{:?}", Color::BrightRed, Color::Red, op));
                             }
-                            fn pop_operand(stack: &mut Vec<Variable>) -> Variable {
-                                stack.pop().unwrap_or_else(|| {
-                                    println!("{}Invalid stack (no operands): {}{:?}", Color::BrightRed, Color::Red, stack);
-                                    flush_styles();
-                                    std::process::exit(0);
+                            fn pop_operand(stack: &mut Vec<Variable>) -> Result<Variable, ScriptError> {
+                                stack.pop().ok_or_else(|| {
+                                    script_error!("{}Invalid stack (no operands): {}{:?}", Color::BrightRed, Color::Red, stack)
                                 })
                             }
 
                             if operand_stack.len() == 1 {
                                 // handle unary operators
-                                let right = pop_operand(&mut operand_stack);
-                                if let Some(lit) = op.apply_unary(right.clone()) {
+                                let right = pop_operand(&mut operand_stack)?;
+                                if let Some(lit) = op.apply_unary(right.clone(), self.overflow)? {
                                     operand_stack.push(lit);
                                 } else {
-                                    println!("{}Invalid operation (syops): {}{:?}",
-                                         Color::BrightRed, Color::Red, right);
-                                    flush_styles();
-                                    std::process::exit(0);
+                                    return Err(script_error!("{}Invalid operation (syops): {}{:?}",
+                                         Color::BrightRed, Color::Red, right));
                                 }
                                 continue;
                             }
 
-                            let right = pop_operand(&mut operand_stack);
+                            let right = pop_operand(&mut operand_stack)?;
 
                             // unary check
                             if op.is_unary() {
-                                if let Some(lit) = op.apply_unary(right.clone()) {
+                                if let Some(lit) = op.apply_unary(right.clone(), self.overflow)? {
                                     operand_stack.push(lit);
                                 } else {
-                                    println!("{}Invalid operation (syau): {}{:?} {} ; {:?}",
-                                         Color::BrightRed, Color::Red, op, right, node);
-                                    flush_styles();
-                                    std::process::exit(0);
+                                    return Err(script_error!("{}Invalid operation (syau): {}{:?} {} ; {:?}",
+                                         Color::BrightRed, Color::Red, op, right, node));
                                 }
                                 continue;
                             }
 
-                            let left = pop_operand(&mut operand_stack);
+                            let left = pop_operand(&mut operand_stack)?;
 
-                            if let Some(lit) = op.apply_binary(left.clone(), right.clone()) {
+                            if let Some(lit) = op.apply_binary(left.clone(), right.clone(), self.overflow)? {
                                 operand_stack.push(lit);
                             } else {
-                                println!("{}Invalid operation (syab): {}{:?} {} {:?} ; {:?}",
-                                         Color::BrightRed, Color::Red, left, op, right, node);
-                                flush_styles();
-                                std::process::exit(0);
+                                return Err(script_error!("{}Invalid operation (syab): {}{:?} {} {:?} ; {:?}",
+                                         Color::BrightRed, Color::Red, left, op, right, node));
                             }
                         }
                     }
                 }
 
                 if operand_stack.len() != 1 {
-                    println!("{}Invalid stack (too many operands): {}{:?}", Color::BrightRed, Color::Red, operand_stack);
-                    flush_styles();
-                    std::process::exit(0);
+                    return Err(script_error!("{}Invalid stack (too many operands): {}{:?}", Color::BrightRed, Color::Red, operand_stack));
                 }
 
                 operand_stack.pop().unwrap()
             }
             Node::Ident(ident) => {
-                self.env.get_or_else(&ident).clone()
+                match self.env.get(&ident) {
+                    Some(value) => value.clone(),
+                    None if self.constants.contains_key(&ident) => self.constants[&ident].clone(),
+                    // a bare function name is a reference to the function
+                    None if self.find_function(&ident).is_some() => Variable::Function(ident, self.module.clone()),
+                    None => match ident.split_once('.') {
+                        // a member of an imported module
+                        Some((namespace, member)) => {
+                            // function references keep the module they are declared in
+                            let member = member.to_string();
+                            self.in_module(namespace, |this| this.eval(Node::Ident(member)))?
+                        }
+                        None => self.env.get_or_else(&ident)?,
+                    },
+                }
             },
             Node::Sizeof(node) => {
-                let value = self.eval(*node.clone());
-                value.sizeof().unwrap_or_else(|| {
-                    println!("{}Invalid sizeof: {}{:?}", Color::BrightRed, Color::Red, node);
-                    flush_styles();
-                    std::process::exit(0);
-                })
+                let value = self.eval(*node.clone())?;
+                value.sizeof().ok_or_else(|| {
+                    script_error!("{}Invalid sizeof: {}{:?}", Color::BrightRed, Color::Red, node)
+                })?
             }
             Node::Drop(ident) => {
                 // drop the variable if it is one
@@ -172,267 +552,149 @@ impl Interpreter {
                 Variable::Int(0)
             }
             Node::Exit => {
-                flush_styles();
-                std::process::exit(0);
+                self.flag = Some(InterFlag::Exit);
+                Variable::Int(0)
             }
             Node::VarDecl(ident, typ) => {
-                let value = typ.map_or(Variable::Int(0), |n| self.eval(*n));
+                let value = typ.map_or(Ok(Variable::Int(0)), |n| self.eval(*n))?;
                 self.env.set(&ident, value.clone());
                 value
             }
             Node::VarAssign(ident, value) => {
+                // variables of imported modules
+                if let Some((namespace, member)) = ident.split_once('.') {
+                    let v = self.eval(*value)?;
+                    let member = member.to_string();
+                    return self.in_module(namespace, |this| this.eval(Node::VarAssign(member, Box::new(Node::Variable(v)))));
+                }
+
                 // ensure the variable exists
                 if !self.env.variable_exists(&ident) {
-                    println!("{}Invalid variable assignment: {}{:?}", Color::BrightRed, Color::Red, ident);
-                    flush_styles();
-                    std::process::exit(0);
+                    return Err(script_error!("{}Invalid variable assignment: {}{:?}", Color::BrightRed, Color::Red, ident));
                 }
 
                 // set the variable
-                let v = self.eval(*value);
+                let v = self.eval(*value)?;
                 self.env.set(&ident, v.clone());
                 v
             }
+            Node::Concat(nodes) => {
+                let mut s = String::new();
+                for node in nodes {
+                    s.push_str(&format!("{}", self.eval(node)?));
+                }
+                Variable::String(s.into())
+            }
+            Node::Format(node, spec) => {
+                let value = self.eval(*node)?;
+                Variable::String(spec.apply(&value).into())
+            }
             Node::Array(nodes) => {
                 let mut array = Vec::new();
                 for node in nodes {
-                    array.push(Box::new(self.eval(*node)))
+                    array.push(Box::new(self.eval(*node)?))
                 }
                 Variable::Array(array)
             }
             Node::ArrayMapAccess(ident, index) => {
-                // get the array if it exists from the environment
-                let array = self.env.get_or_else(&ident).to_array().unwrap_or_else(|| {
-                    println!("{}Invalid array access: {}{:?}", Color::BrightRed, Color::Red, ident);
-                    flush_styles();
-                    std::process::exit(0);
-                });
-
                 // get the index
-                let i = self.eval(*index);
+                let i = self.eval(*index)?;
+
+                // members of imported modules are copied out of the module
+                if ident.contains('.') && self.env.get(&ident).is_none() {
+                    let value = self.eval(Node::Ident(ident))?;
+                    return value.access(i.clone())?.ok_or_else(|| {
+                        script_error!("{}Invalid index: {}{:?}[{:?}]", Color::BrightRed, Color::Red, value, i)
+                    });
+                }
 
-                // get the value from the array
-                array.access(i.clone()).unwrap_or_else(|| {
-                    println!("{}Invalid array index: {}{:?}", Color::BrightRed, Color::Red, i);
-                    flush_styles();
-                    std::process::exit(0);
-                })
+                // get the value from the variable or constant, borrowing it so strings aren't copied
+                let value = self.env.get(&ident).or_else(|| self.constants.get(&ident)).ok_or_else(|| {
+                    script_error!("{}Undefined variable: {}{}", Color::BrightRed, Color::Red, ident)
+                })?;
+                value.access(i.clone())?.ok_or_else(|| {
+                    script_error!("{}Invalid index: {}{:?}[{:?}]", Color::BrightRed, Color::Red, value, i)
+                })?
             }
             Node::ArrayMapAssign(ident, index, value) => {
-                // get the array if it exists from the environment
-                let mut array = self.env.get_or_else(&ident).to_array().unwrap_or_else(|| {
-                    println!("{}Invalid array assign: {}{:?}", Color::BrightRed, Color::Red, ident);
-                    flush_styles();
-                    std::process::exit(0);
-                });
-
                 // get the index
-                let i = self.eval(*index);
+                let i = self.eval(*index)?;
 
-                // get the value from the array
-                let v = self.eval(*value);
-
-                // set the value in the array
-                array.assign(i.clone(), v).unwrap_or_else(|| {
-                    println!("{}Invalid array index: {}{:?}", Color::BrightRed, Color::Red, i);
-                    flush_styles();
-                    std::process::exit(0);
-                });
+                // get the value to assign
+                let v = self.eval(*value)?;
 
-                // update the array in the environment
-                self.env.set(&ident, array);
+                // set the value in the variable in place
+                let target = self.env.get_mut(&ident).ok_or_else(|| {
+                    script_error!("{}Undefined variable: {}{}", Color::BrightRed, Color::Red, ident)
+                })?;
+                if target.assign(i.clone(), v)?.is_none() {
+                    return Err(script_error!("{}Invalid index: {}{:?}[{:?}]", Color::BrightRed, Color::Red, target, i));
+                }
                 Variable::Int(0)
             }
-            Node::TypeCast(ident, typ) => {
-                match *ident {
-                    Node::Ident(ref s) => {
-                        // change the type of the variable
-                        let value = self.env.get_or_else(&s);
-                        // cast the value to the new type
-                        match typ {
-                            VariableType::Int => {
-                                // cast the value to an int
-                                value.to_int().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                            VariableType::Float => {
-                                // cast the value to a float
-                                value.to_float().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                            VariableType::String => {
-                                // cast the value to a string
-                                value.to_string().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                            VariableType::Bool => {
-                                value.to_bool().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                            VariableType::Array => {
-                                value.to_array().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                            VariableType::Map => {
-                                value.to_map().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                            VariableType::Range => {
-                                value.to_range().unwrap_or_else(|| {
-                                    println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                                    flush_styles();
-                                    std::process::exit(0);
-                                })
-                            }
-                        }
-                    }
-                    _ => {
-                        println!("{}Invalid type cast: {}{:?}", Color::BrightRed, Color::Red, ident);
-                        flush_styles();
-                        std::process::exit(0);
-                    }
-                }
+            Node::TypeCast(node, typ) => {
+                let value = self.eval(*node)?;
+                // cast the value to the new type
+                let cast = match typ {
+                    VariableType::Int => value.to_int()?,
+                    VariableType::Float => value.to_float()?,
+                    VariableType::String => value.to_string(),
+                    VariableType::Char => value.to_char(),
+                    VariableType::Bool => value.to_bool(),
+                    VariableType::Array => value.to_array(),
+                    VariableType::Map => value.to_map(),
+                    VariableType::Range => value.to_range(),
+                    VariableType::Error => value.to_error(),
+                };
+                cast.ok_or_else(|| {
+                    script_error!("{}Invalid type cast: {}{:?} as {}", Color::BrightRed, Color::Red, value, typ)
+                })?
             }
-            Node::TypeCheck(ident, typ) => {
-                match *ident {
-                    Node::Ident(s) => {
-                        // check the type of the variable
-                        let value = self.env.get_or_else(&s);
-                        // check if the value is of the correct type
-                        match value {
-                            Variable::Int(_) => {
-                                if typ == VariableType::Int {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                            Variable::Float(_) => {
-                                if typ == VariableType::Float {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                            Variable::String(_) => {
-                                if typ == VariableType::String {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                            Variable::Bool(_) => {
-                                if typ == VariableType::Bool {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                            Variable::Range(_, _) => {
-                                if typ == VariableType::Range {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                            Variable::Array(_) => {
-                                if typ == VariableType::Array {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                            Variable::Map(_) => {
-                                if typ == VariableType::Map {
-                                    Variable::Bool(true)
-                                } else {
-                                    Variable::Bool(false)
-                                }
-                            }
-                        }
-                    }
-                    _ => {
-                        println!("{}Invalid type check: {}{:?}", Color::BrightRed, Color::Red, ident);
-                        flush_styles();
-                        std::process::exit(0);
-                    }
-                }
+            Node::TypeCheck(node, typ) => {
+                // check if the value is of the type
+                let is = match self.eval(*node)? {
+                    Variable::Int(_) => typ == VariableType::Int,
+                    Variable::Float(_) => typ == VariableType::Float,
+                    Variable::String(_) => typ == VariableType::String,
+                    Variable::Char(_) => typ == VariableType::Char,
+                    Variable::Bool(_) => typ == VariableType::Bool,
+                    Variable::Range(_, _) => typ == VariableType::Range,
+                    Variable::Array(_) => typ == VariableType::Array,
+                    Variable::Map(_) => typ == VariableType::Map,
+                    Variable::Error(_) => typ == VariableType::Error,
+                    Variable::Function(..) | Variable::Module(_) => false,
+                };
+                Variable::Bool(is)
             }
             Node::FunctionDecl(ident, args, body) => {
                 self.env.push_function(ident, Function::Local(args, *body));
                 Variable::Int(0)
             }
             Node::FunctionCall(ident, args) => {
-                let func = self.env.get_function(ident.clone()).unwrap_or_else(|| {
-                    println!("{}Invalid function call: {}{:?}", Color::BrightRed, Color::Red, ident);
-                    flush_styles();
-                    std::process::exit(0);
-                }).clone();
-
-                match func {
-                    Function::Native(f) => {
+                match self.find_function(&ident) {
+                    Some(Function::InPlace(f)) => self.call_in_place(f, args)?,
+                    _ => {
                         let mut vars = Vec::new();
                         for arg in args {
-                            vars.push(self.eval(*arg));
-                        }
-                        f(vars)
-                    }
-                    Function::Local(params, body) => {
-                        // create a new scope for the function
-                        self.env.push_scope();
-                        // ensure the number of arguments matches the number of parameters
-                        if params.len() != args.len() {
-                            println!("{}Invalid number of arguments: {}{:?}", Color::BrightRed, Color::Red, params);
-                            flush_styles();
-                            std::process::exit(0);
-                        }
-                        // set the parameters
-                        for (param, arg) in params.iter().zip(args.iter()) {
-                            let p = self.eval(*arg.clone());
-                            self.env.set(param, p);
-                        }
-                        // run the body
-                        let mut result = self.eval(body.clone());
-                        if let Some(f) = self.flag.clone() {
-                            if let InterFlag::Return(v) = f {
-                                self.flag = None;
-                                if let Some(v) = v {
-                                    result = self.eval(*v);
-                                }
-                            }
+                            vars.push(self.eval(*arg)?);
                         }
-                        // remove the scope
-                        self.env.pop_scope();
-                        result
+                        self.call_named(&ident, vars)?
                     }
                 }
             }
+            Node::Import(path, name) => {
+                let key = self.import(&path)?;
+                self.env.set(&name, Variable::Module(key));
+                Variable::Int(0)
+            }
             Node::If(cond, then, els) => {
                 // evaluate condition
-                let cond_val = self.eval(*cond);
+                let cond_val = self.eval(*cond)?;
 
                 match cond_val {
                     Variable::Int(0) => {
                         if let Some(els) = els {
-                            self.eval(*els)
+                            self.eval(*els)?
                         } else {
                             Variable::Int(0)
                         }
@@ -440,22 +702,20 @@ impl Interpreter {
                     Variable::Bool(b) => {
                         if b {
                             if let Some(then) = then {
-                                self.eval(*then)
+                                self.eval(*then)?
                             } else {
                                 Variable::Int(0)
                             }
                         } else {
                             if let Some(els) = els {
-                                self.eval(*els)
+                                self.eval(*els)?
                             } else {
                                 Variable::Int(0)
                             }
                         }
                     },
                     _ => {
-                        println!("{}Invalid condition: {}{:?}", Color::BrightRed, Color::Red, cond_val);
-                        flush_styles();
-                        std::process::exit(0);
+                        return Err(script_error!("{}Invalid condition: {}{:?}", Color::BrightRed, Color::Red, cond_val));
                     },
                 }
             }
@@ -472,20 +732,18 @@ impl Interpreter {
                                 self.flag = None;
                                 continue;
                             },
-                            InterFlag::Return(_) => break
+                            InterFlag::Return(_) | InterFlag::Exit => break
                         }
                     }
                     // evaluate condition
-                    let condition = self.eval(*cond.clone()).to_bool().unwrap_or_else(|| {
-                        println!("{}Invalid condition: {}{:?}", Color::BrightRed, Color::Red, cond);
-                        flush_styles();
-                        std::process::exit(0);
-                    });
+                    let condition = self.eval(*cond.clone())?.to_bool().ok_or_else(|| {
+                        script_error!("{}Invalid condition: {}{:?}", Color::BrightRed, Color::Red, cond)
+                    })?;
                     let Variable::Bool(c) = condition else { break; };
                     if !c { break; }
 
                     // run the body
-                    self.eval(*body.clone());
+                    self.eval(*body.clone())?;
                 }
 
                 last
@@ -502,11 +760,11 @@ impl Interpreter {
                             self.flag = None;
                             continue 'top;
                         },
-                        Some(InterFlag::Return(_)) => break,
+                        Some(InterFlag::Return(_)) | Some(InterFlag::Exit) => break,
                         _ => {}
                     }
                     // run the body
-                    self.eval(*body.clone());
+                    self.eval(*body.clone())?;
                 }
                 last
             }
@@ -525,10 +783,135 @@ impl Interpreter {
 
             Node::EOF => Variable::Int(0),
             _ => {
-                println!("{}Unexpected node: {}{:?}", Color::BrightRed, Color::Red, node);
-                flush_styles();
-                std::process::exit(0);
+                return Err(script_error!("{}Unexpected node: {}{:?}", Color::BrightRed, Color::Red, node));
             }
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use crate::debug::ScriptError;
+    use crate::lexer::Lexer;
+    use crate::matador_std;
+    use crate::module::ModuleResolver;
+    use crate::node::Node;
+    use crate::parser::Parser;
+    use crate::variable::Variable;
+    use super::Interpreter;
+
+    // modules by name, from memory
+    struct Sources(Vec<(&'static str, &'static str)>);
+
+    impl ModuleResolver for Sources {
+        fn resolve(&self, path: &str, _: Option<&str>) -> Result<String, String> {
+            Ok(path.to_string())
+        }
+
+        fn load(&self, key: &str) -> Result<String, String> {
+            self.0.iter().find(|(name, _)| *name == key).map(|(_, source)| source.to_string())
+                .ok_or_else(|| format!("no module {}", key))
+        }
+    }
+
+    // the value of the last statement of `main`
+    fn run(main: &str, modules: Vec<(&'static str, &'static str)>) -> Result<Variable, ScriptError> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_resolver(Box::new(Sources(modules)));
+        matador_std::attach_std(&mut interpreter);
+        let mut nodes = Parser::new(Lexer::new(main).lex()?).parse()?;
+        nodes.retain(|node| *node != Node::EOF);
+        Ok(interpreter.try_interpret(nodes)?.pop().unwrap())
+    }
+
+    #[test]
+    fn function_values_run_in_their_module() {
+        let r = ("r", "fn apply(f, x) {\n    return f(x)\n}");
+        let main = "import r\nfn inc(x) {\n    return x + 1\n}\nr.apply(inc, 3)";
+        assert_eq!(run(main, vec![r]).unwrap(), Variable::Int(4));
+
+        // and a function of a module passed back out of it
+        let m = ("m", "let step = 10\nfn add(x) {\n    return x + step\n}");
+        let main = "import m\nlet f = m.add\nf(1)";
+        assert_eq!(run(main, vec![m]).unwrap(), Variable::Int(11));
+    }
+
+    #[test]
+    fn short_circuit() {
+        // the right side is only evaluated when the left side doesn't decide the result, so
+        // these fail if it is evaluated eagerly
+        let guard = "let a = [\"x\"]\nlet i = 1\ni < len(a) && a[i] == \"x\"";
+        assert_eq!(run(guard, vec![]).unwrap(), Variable::Bool(false));
+        assert_eq!(run("true || 1 / 0", vec![]).unwrap(), Variable::Bool(true));
+        assert_eq!(run("false && 1 / 0 == 1", vec![]).unwrap(), Variable::Bool(false));
+        assert_eq!(run("(false && 1 / 0 == 1) || true", vec![]).unwrap(), Variable::Bool(true));
+        assert_eq!(run("let a = []\nlen(a) == 0 || a[0] > 1", vec![]).unwrap(), Variable::Bool(true));
+
+        // and it is evaluated when it does
+        assert!(run("false || 1 / 0 == 1", vec![]).is_err());
+        assert!(run("let a = [\"x\"]\nlet i = 0\ni < len(a) && a[i + 1] == \"x\"", vec![]).is_err());
+    }
+
+    #[test]
+    fn casts_and_type_checks() {
+        let cases = [
+            ("'a' as int", Variable::Int(97)),
+            ("'\\u{e9}' as int", Variable::Int(233)),
+            ("'\\n' as int", Variable::Int(10)),
+            ("98 as char", Variable::Char('b')),
+            ("(1 + 2) as float", Variable::Float(3.0)),
+            ("-1 as float", Variable::Float(-1.0)),
+            ("1 + 2 as float", Variable::Float(3.0)),
+            ("let s = \"12\"\ns[1] as int - '0' as int", Variable::Int(2)),
+            ("'x' is char", Variable::Bool(true)),
+            ("(1 + 2) is float", Variable::Bool(false)),
+            ("[1, 2] is array", Variable::Bool(true)),
+        ];
+        for (main, value) in cases {
+            assert_eq!(run(main, Vec::new()).unwrap(), value, "{}", main);
+        }
+        assert!(run("[1] as int", Vec::new()).unwrap_err().message.starts_with("Invalid type cast"));
+    }
+
+    #[test]
+    fn strings_iterate_by_char() {
+        let main = "let s = \"héllo\"\nlet sum = 0\nlet i = 0\nwhile i < sizeof s {\n    sum = sum + s[i] as int\n    i = i + 1\n}\nsum";
+        assert_eq!(run(main, Vec::new()).unwrap(), Variable::Int(104 + 233 + 108 + 108 + 111));
+    }
+
+    #[test]
+    fn int_min_literal() {
+        assert_eq!(run("-9223372036854775808", Vec::new()).unwrap(), Variable::Int(i64::MIN));
+        assert_eq!(run("1 + -9223372036854775808", Vec::new()).unwrap(), Variable::Int(i64::MIN + 1));
+        for main in ["9223372036854775808", "1 - 9223372036854775808", "-(9223372036854775808)"] {
+            let e = run(main, Vec::new()).unwrap_err();
+            assert!(e.message.starts_with("Integer literal out of range"), "{}", main);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn in_place_callbacks_see_the_variable() {
+        let main = "let a = [3, 1, 2]\nfn by(x, y) {\n    return x < y && len(a) == 3\n}\nsort(a, by)\na";
+        assert_eq!(format!("{}", run(main, Vec::new()).unwrap()), "[1, 2, 3]");
+
+        // a failed call leaves the variable as it was
+        let mut interpreter = Interpreter::new();
+        matador_std::attach_std(&mut interpreter);
+        let main = "let a = [3, 1, 2]\nfn bad(x, y) {\n    return \"no\"\n}\nsort(a, bad)";
+        let nodes = Parser::new(Lexer::new(main).lex().unwrap()).parse().unwrap();
+        assert!(interpreter.try_interpret(nodes).is_err());
+        assert_eq!(format!("{}", interpreter.env.get("a").unwrap()), "[3, 1, 2]");
+    }
+
+    #[test]
+    fn modules_can_be_reentered() {
+        // a.f -> b.g -> a.h through a function value
+        let a = ("a", "import b\nlet n = 1\nfn f() {\n    return b.g(h)\n}\nfn h() {\n    return n + 1\n}");
+        let b = ("b", "fn g(f) {\n    return f() * 10\n}");
+        assert_eq!(run("import a\na.f()", vec![a, b]).unwrap(), Variable::Int(20));
+
+        // and through an import of the running module
+        let a = ("a", "import b\nfn f() {\n    return b.g()\n}\nfn h() {\n    return 5\n}");
+        let b = ("b", "let r = 0\nfn g() {\n    import a\n    r = a.h()\n    return r\n}");
+        assert_eq!(run("import a\na.f()", vec![a, b]).unwrap(), Variable::Int(5));
+    }
+}
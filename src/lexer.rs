@@ -1,7 +1,11 @@
 use std::fmt::Display;
-use crate::debug_print;
+use std::ops::Range;
+use better_term::Color;
+use crate::debug::{script_error, ScriptError};
+use crate::logging::log;
 use crate::variable::VariableType;
 use crate::operator::Operator;
+use crate::format::FormatSpec;
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum TokenType {
@@ -33,6 +37,7 @@ pub(crate) enum TokenType {
     Sizeof,
     Drop,
     Exit,
+    Import,
 
     // block outlines
     // not in operators as they are not used in the same way
@@ -48,15 +53,26 @@ pub(crate) enum TokenType {
     Float(f64),
     Bool(bool),
     String(String),
+    Char(char),
+    InterpolatedString(Vec<StringPart>),
 
     EOF,
 }
 
+// a piece of an interpolated string such as "hp: {hp}/{max}"
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum StringPart {
+    Literal(String),
+    // source of the embedded expression, where it starts and its format specifier
+    Expr(String, (usize, usize), Option<FormatSpec>),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) struct Token {
     pub(crate) token_type: TokenType,
     lexeme: String,
-    pub pos: (usize, usize), // line, char
+    pub pos: (usize, usize), // line, char of the first character
+    pub(crate) range: Range<usize>, // bytes of the source
 }
 
 impl Display for Token {
@@ -65,249 +81,778 @@ impl Display for Token {
     }
 }
 
+// operators and punctuation
+// the lexer always takes the longest symbol that matches, so `==` is never lexed as two `=`
+const MAX_SYMBOL_LEN: usize = 2;
+
+fn symbol(s: &str) -> Option<TokenType> {
+    Some(match s {
+        // blocks
+        "{" => TokenType::LBrace,
+        "}" => TokenType::RBrace,
+
+        // control flow
+        "=" => TokenType::Assign,
+        "." => TokenType::Decimal,
+        "[" => TokenType::LBracket,
+        "]" => TokenType::RBracket,
+        ":" => TokenType::Colon,
+        "," => TokenType::Comma,
+
+        // operators
+        "(" => TokenType::Op(Operator::LParen),
+        ")" => TokenType::Op(Operator::RParen),
+        "+" => TokenType::Op(Operator::Plus),
+        "-" => TokenType::Op(Operator::Minus),
+        "*" => TokenType::Op(Operator::Mul),
+        "/" => TokenType::Op(Operator::Div),
+        "%" => TokenType::Op(Operator::Mod),
+        "**" => TokenType::Op(Operator::Pow),
+        ".." => TokenType::Op(Operator::Range),
+        "&" => TokenType::Op(Operator::BitAnd),
+        "|" => TokenType::Op(Operator::BitOr),
+        "^" => TokenType::Op(Operator::Xor),
+        "!" => TokenType::Op(Operator::Not),
+        "<<" => TokenType::Op(Operator::LShift),
+        ">>" => TokenType::Op(Operator::RShift),
+        "&&" => TokenType::Op(Operator::And),
+        "||" => TokenType::Op(Operator::Or),
+        "++" => TokenType::Op(Operator::Inc),
+        "--" => TokenType::Op(Operator::Dec),
+
+        // conditionals
+        "==" => TokenType::Op(Operator::Eq),
+        "!=" => TokenType::Op(Operator::Neq),
+        ">" => TokenType::Op(Operator::Gt),
+        "<" => TokenType::Op(Operator::Lt),
+        ">=" => TokenType::Op(Operator::Gte),
+        "<=" => TokenType::Op(Operator::Lte),
+
+        _ => return None,
+    })
+}
+
+fn keyword(s: &str) -> Option<TokenType> {
+    Some(match s {
+        "let" => TokenType::Let,
+        "fn" => TokenType::Fn,
+        "return" => TokenType::Return,
+        "if" => TokenType::If,
+        "else" => TokenType::Else,
+        "while" => TokenType::While,
+        "loop" => TokenType::Loop,
+        "for" => TokenType::For,
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
+        "in" => TokenType::In,
+        "as" => TokenType::As,
+        "is" => TokenType::Is,
+        "int" => TokenType::VariableType(VariableType::Int),
+        "float" => TokenType::VariableType(VariableType::Float),
+        "string" => TokenType::VariableType(VariableType::String),
+        "char" => TokenType::VariableType(VariableType::Char),
+        "bool" => TokenType::VariableType(VariableType::Bool),
+        "range" => TokenType::VariableType(VariableType::Range),
+        "array" => TokenType::VariableType(VariableType::Array),
+        "map" => TokenType::VariableType(VariableType::Map),
+        "error" => TokenType::VariableType(VariableType::Error),
+
+        "sizeof" => TokenType::Sizeof,
+        "drop" => TokenType::Drop,
+        "exit" => TokenType::Exit,
+        "import" => TokenType::Import,
+
+        "true" => TokenType::Bool(true),
+        "false" => TokenType::Bool(false),
+
+        _ => return None,
+    })
+}
+
+// every word `keyword` knows, for completion in editors
+pub(crate) const KEYWORDS: [&str; 28] = [
+    "let", "fn", "return", "if", "else", "while", "loop", "for", "break", "continue", "in", "as", "is",
+    "int", "float", "string", "char", "bool", "range", "array", "map", "error",
+    "sizeof", "drop", "exit", "import", "true", "false",
+];
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 pub struct Lexer<'a> {
     source: &'a str,
-    chars: Vec<char>,
-    pos: usize,
+    pos: usize, // byte offset into source
     code_pos: (usize, usize),
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Self {
+        Self::new_at(source, (1, 0))
+    }
+
+    // creates a lexer for source that is embedded in a larger file at the given position
+    pub(crate) fn new_at(source: &'a str, code_pos: (usize, usize)) -> Self {
         Lexer {
             source,
-            chars: source.chars().collect(),
             pos: 0,
-            code_pos: (1, 0),
+            code_pos,
         }
     }
 
-    pub fn lex(&mut self) -> Vec<Token> {
+    pub fn lex(&mut self) -> Result<Vec<Token>, ScriptError> {
         let mut tokens = Vec::new();
-        while self.pos < self.chars.len() {
-            let token = self.next_token();
-            debug_print!("{}: {:?}", tokens.len(), token);
+        loop {
+            let token = self.next_token()?;
+            log!(Lexer, Trace, "{}: {:?}", tokens.len(), token);
+            let eof = token.token_type == TokenType::EOF;
             tokens.push(token);
-        }
-        if let Some(last) = tokens.last() {
-            if last.token_type != TokenType::EOF {
-                tokens.push(Token {
-                    token_type: TokenType::EOF,
-                    lexeme: "".to_string(),
-                    pos: self.code_pos,
-                });
+            if eof {
+                break;
             }
         }
-        tokens
+        Ok(tokens)
     }
 
-    fn update_code_pos(&mut self, amt: usize) {
-        self.code_pos.1 += amt;
-        self.pos += amt;
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
     }
 
-    fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
-        let mut builder = String::new();
-        // iterate through chars until a pattern is found
-        while self.pos < self.chars.len() {
-            let c = self.chars[self.pos];
-            // skip comments
-            if c == '/' {
-                if self.pos + 1 < self.chars.len() && self.chars[self.pos + 1] == '/' {
-                    while self.pos < self.chars.len() && self.chars[self.pos] != '\n' {
-                        self.update_code_pos(1);
-                    }
-                    if self.pos < self.chars.len() && self.chars[self.pos] == '\n' {
-                        self.code_pos.0 += 1;
-                        self.code_pos.1 = 0;
-                    }
-                    continue;
-                } else if self.pos + 1 < self.chars.len() && self.chars[self.pos + 1] == '*' {
-                    self.update_code_pos(2);
-                    while self.pos < self.chars.len() {
-                        if self.chars[self.pos] == '*' && self.pos + 1 < self.chars.len() && self.chars[self.pos + 1] == '/' {
-                            self.update_code_pos(2);
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source[self.pos..].chars().nth(n)
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.code_pos.0 += 1;
+            self.code_pos.1 = 0;
+        } else {
+            self.code_pos.1 += 1;
+        }
+        Some(c)
+    }
+
+    fn advance_while<F: Fn(char) -> bool>(&mut self, f: F) {
+        while self.peek().is_some_and(&f) {
+            self.advance();
+        }
+    }
+
+    fn error<T>(&self, msg: &str) -> Result<T, ScriptError> {
+        self.error_at(self.offset().1, msg)
+    }
+
+    fn error_at<T>(&self, pos: (usize, usize), msg: &str) -> Result<T, ScriptError> {
+        Err(script_error!(at pos, "{}{} @ {:?}", Color::BrightRed, msg, pos))
+    }
+
+    pub(crate) fn next_token(&mut self) -> Result<Token, ScriptError> {
+        self.skip_trivia()?;
+
+        let start = self.pos;
+        let start_pos = (self.code_pos.0, self.code_pos.1 + 1);
+        let Some(c) = self.peek() else {
+            return Ok(Token {
+                token_type: TokenType::EOF,
+                lexeme: "".to_string(),
+                pos: start_pos,
+                range: start..start,
+            });
+        };
+
+        let token_type = if c.is_ascii_digit() {
+            self.lex_number(start_pos)?
+        } else if c == 'r' && matches!(self.peek_nth(1), Some('"') | Some('#')) {
+            self.lex_raw_string(start_pos)?
+        } else if is_ident_start(c) {
+            self.advance_while(is_ident_continue);
+            let word = &self.source[start..self.pos];
+            keyword(word).unwrap_or_else(|| TokenType::Ident(word.to_string()))
+        } else if c == '"' {
+            self.lex_string(start_pos)?
+        } else if c == '\'' {
+            self.lex_char()?
+        } else {
+            self.lex_symbol()?
+        };
+
+        Ok(Token {
+            token_type,
+            lexeme: self.source[start..self.pos].to_string(),
+            pos: start_pos,
+            range: start..self.pos,
+        })
+    }
+
+    // skips whitespace and comments
+    fn skip_trivia(&mut self) -> Result<(), ScriptError> {
+        while self.next_trivia()?.is_some() {}
+        Ok(())
+    }
+
+    // skips one run of whitespace or one comment, returns whether it was a comment
+    pub(crate) fn next_trivia(&mut self) -> Result<Option<bool>, ScriptError> {
+        Ok(match (self.peek(), self.peek_nth(1)) {
+            (Some(c), _) if c.is_whitespace() => {
+                self.advance_while(char::is_whitespace);
+                Some(false)
+            }
+            (Some('/'), Some('/')) => {
+                self.advance_while(|c| c != '\n');
+                Some(true)
+            }
+            (Some('/'), Some('*')) => {
+                let start_pos = (self.code_pos.0, self.code_pos.1 + 1);
+                self.advance();
+                self.advance();
+                loop {
+                    match (self.peek(), self.peek_nth(1)) {
+                        (Some('*'), Some('/')) => {
+                            self.advance();
+                            self.advance();
                             break;
                         }
-                        self.update_code_pos(1);
+                        (Some(_), _) => {
+                            self.advance();
+                        }
+                        (None, _) => return self.error_at(start_pos, "Unterminated block comment"),
                     }
-                    continue;
                 }
+                Some(true)
             }
-            // handle ending tokens
-            if c == ')' || c == '}' || c == ']' || c == ',' || c == '[' || c == '{' || c == '(' {
-                if !builder.is_empty() {
-                    break;
+            _ => None,
+        })
+    }
+
+    // the byte offset and position of the next character
+    pub(crate) fn offset(&self) -> (usize, (usize, usize)) {
+        (self.pos, (self.code_pos.0, self.code_pos.1 + 1))
+    }
+
+    // skips a character that could not be lexed, so lexing can go on after an error
+    pub(crate) fn skip_char(&mut self) {
+        self.advance();
+    }
+
+    // takes the longest operator or punctuation symbol at the current position
+    fn lex_symbol(&mut self) -> Result<TokenType, ScriptError> {
+        let rest = &self.source[self.pos..];
+        for len in (1..=MAX_SYMBOL_LEN).rev() {
+            if let Some(token_type) = rest.get(..len).and_then(symbol) {
+                // symbols are all ascii, so each byte is one character
+                for _ in 0..len {
+                    self.advance();
                 }
-                builder.push(c);
-                self.update_code_pos(1);
-                break;
+                return Ok(token_type);
             }
+        }
+        let c = self.peek().unwrap();
+        self.error(&format!("Unexpected character '{}'", c))
+    }
 
-            // handle leading single character tokens
-            // todo: this probably wont allow x =1, and would treat =1 as an identifier
-            if c == '(' {
-                builder.push(c);
-                self.update_code_pos(1);
-                break;
-            }
+    // lexes decimal ints and floats (`1_000`, `1.5`, `2e-3`) and prefixed ints (`0xFF`, `0b1010`, `0o17`)
+    // underscores may be used to separate digits
+    fn lex_number(&mut self, start_pos: (usize, usize)) -> Result<TokenType, ScriptError> {
+        let start = self.pos;
 
-            if c == '!' && self.pos + 1 < self.chars.len() && self.chars[self.pos + 1] == '=' {
-                if !builder.is_empty() {
-                    break;
-                }
-                builder.push_str("!=");
-                self.update_code_pos(2);
-                break;
+        let radix = match (self.peek(), self.peek_nth(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('b' | 'B')) => 2,
+            (Some('0'), Some('o' | 'O')) => 8,
+            _ => 10,
+        };
+
+        let mut is_float = false;
+        if radix != 10 {
+            self.advance();
+            self.advance();
+            self.advance_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        } else {
+            self.advance_while(|c| c.is_ascii_digit() || c == '_');
+
+            // a decimal point only belongs to the number if a digit follows it, so `1..5` is a range
+            if self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+                is_float = true;
+                self.advance();
+                self.advance_while(|c| c.is_ascii_digit() || c == '_');
             }
 
-            if c == '!' {
-                if !builder.is_empty() {
-                    break;
+            // exponent
+            if matches!(self.peek(), Some('e' | 'E')) {
+                let sign = matches!(self.peek_nth(1), Some('+' | '-')) as usize;
+                if self.peek_nth(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                    is_float = true;
+                    for _ in 0..=sign {
+                        self.advance();
+                    }
+                    self.advance_while(|c| c.is_ascii_digit() || c == '_');
                 }
-                builder.push(c);
-                self.update_code_pos(1);
-                break;
             }
+        }
 
-            if c.is_whitespace() {
-                break;
-            }
-            builder.push(c);
-            self.update_code_pos(1);
+        // anything that continues the literal, such as `1.2.3` or `12abc`, makes it malformed
+        let malformed = self.peek().is_some_and(is_ident_continue)
+            || (self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()));
+        if malformed {
+            self.advance_while(|c| is_ident_continue(c) || c == '.');
+            let text = &self.source[start..self.pos];
+            return self.error_at(start_pos, &format!("Invalid number literal '{}'", text));
+        }
 
-            // handle multi-character modifiers
-            if builder == "++" || builder == "--" {
-                break;
+        let text = &self.source[start..self.pos];
+        let digits: String = text.chars().filter(|c| *c != '_').collect();
+        if is_float {
+            match digits.parse::<f64>() {
+                Ok(f) if f.is_finite() => Ok(TokenType::Float(f)),
+                Ok(_) => self.error_at(start_pos, &format!("Float literal out of range '{}'", text)),
+                Err(_) => self.error_at(start_pos, &format!("Invalid float literal '{}'", text)),
+            }
+        } else {
+            let digits = if radix == 10 { &digits[..] } else { &digits[2..] };
+            if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+                return self.error_at(start_pos, &format!("Invalid integer literal '{}'", text));
+            }
+            match i64::from_str_radix(digits, radix) {
+                Ok(n) => Ok(TokenType::Int(n)),
+                // the magnitude of i64::MIN, only valid after a minus sign, which the parser checks
+                Err(_) if radix == 10 && digits == "9223372036854775808" => Ok(TokenType::Int(i64::MIN)),
+                Err(_) => self.error_at(start_pos, &format!("Integer literal out of range '{}', ints must fit in 64 bits", text)),
             }
         }
-        if builder.is_empty() {
-            // handle cases where it exits the loop without adding anything to the builder
-            if self.pos >= self.chars.len() {
-                return Token {
-                    token_type: TokenType::EOF,
-                    lexeme: "".to_string(),
-                    pos: self.code_pos,
-                };
+    }
+
+    // lexes a string literal, processing escape sequences
+    // strings opened with three quotes may span multiple lines, a line break directly
+    // after the opening quotes is not part of the string
+    // expressions in braces are interpolated, `{{` and `}}` are literal braces
+    fn lex_string(&mut self, start_pos: (usize, usize)) -> Result<TokenType, ScriptError> {
+        let multi_line = self.source[self.pos..].starts_with("\"\"\"");
+        if multi_line {
+            self.advance();
+            self.advance();
+            self.advance();
+            if self.peek() == Some('\n') {
+                self.advance();
             }
-            return self.next_token();
+        } else {
+            self.advance();
         }
-        let token_type = match &builder[..] {
-            // language keywords
-            "let" => TokenType::Let,
-            "fn" => TokenType::Fn,
-            "return" => TokenType::Return,
-            "if" => TokenType::If,
-            "else" => TokenType::Else,
-            "while" => TokenType::While,
-            "loop" => TokenType::Loop,
-            "for" => TokenType::For,
-            "break" => TokenType::Break,
-            "continue" => TokenType::Continue,
-            "in" => TokenType::In,
-            "as" => TokenType::As,
-            "is" => TokenType::Is,
-            "int" => TokenType::VariableType(VariableType::Int),
-            "float" => TokenType::VariableType(VariableType::Float),
-            "string" => TokenType::VariableType(VariableType::String),
-            "bool" => TokenType::VariableType(VariableType::Bool),
-            "range" => TokenType::VariableType(VariableType::Range),
-            "array" => TokenType::VariableType(VariableType::Array),
-            "map" => TokenType::VariableType(VariableType::Map),
-
-            "sizeof" => TokenType::Sizeof,
-            "drop" => TokenType::Drop,
-            "exit" => TokenType::Exit,
-
-            // blocks
-            "{" => TokenType::LBrace,
-            "}" => TokenType::RBrace,
-
-            // control flow
-            "=" => TokenType::Assign,
-            "." => TokenType::Decimal,
-            "[" => TokenType::LBracket,
-            "]" => TokenType::RBracket,
-            ":" => TokenType::Colon,
-            "," => TokenType::Comma,
-
-            // operators
-            "(" => TokenType::Op(Operator::LParen),
-            ")" => TokenType::Op(Operator::RParen),
-            "+" => TokenType::Op(Operator::Plus),
-            "-" => TokenType::Op(Operator::Minus),
-            "*" => TokenType::Op(Operator::Mul),
-            "/" => TokenType::Op(Operator::Div),
-            "%" => TokenType::Op(Operator::Mod),
-            "**" => TokenType::Op(Operator::Pow),
-            ".." => TokenType::Op(Operator::Range),
-            "&" => TokenType::Op(Operator::BitAnd),
-            "|" => TokenType::Op(Operator::BitOr),
-            "^" => TokenType::Op(Operator::Xor),
-            "!" => TokenType::Op(Operator::Not),
-            "<<" => TokenType::Op(Operator::LShift),
-            ">>" => TokenType::Op(Operator::RShift),
-            "&&" => TokenType::Op(Operator::And),
-            "||" => TokenType::Op(Operator::Or),
-            "++" => TokenType::Op(Operator::Inc),
-            "--" => TokenType::Op(Operator::Dec),
-
-            // conditionals
-            "==" => TokenType::Op(Operator::Eq),
-            "!=" => TokenType::Op(Operator::Neq),
-            ">" => TokenType::Op(Operator::Gt),
-            "<" => TokenType::Op(Operator::Lt),
-            ">=" => TokenType::Op(Operator::Gte),
-            "<=" => TokenType::Op(Operator::Lte),
-
-            // literals
-            "true" | "false" => {
-                TokenType::Bool(builder == "true")
+
+        let mut parts = Vec::new();
+        let mut s = String::new();
+        loop {
+            match (self.peek(), self.peek_nth(1)) {
+                (Some('"'), _) if !multi_line => {
+                    self.advance();
+                    break;
+                }
+                (Some('"'), _) if self.source[self.pos..].starts_with("\"\"\"") => {
+                    self.advance();
+                    self.advance();
+                    self.advance();
+                    break;
+                }
+                (Some('\\'), _) => {
+                    self.advance();
+                    s.push(self.read_escape()?);
+                }
+                (Some('{'), Some('{')) | (Some('}'), Some('}')) => {
+                    self.advance();
+                    s.push(self.advance().unwrap());
+                }
+                (Some('{'), _) => {
+                    if !s.is_empty() {
+                        parts.push(StringPart::Literal(std::mem::take(&mut s)));
+                    }
+                    parts.push(self.lex_interpolation(multi_line)?);
+                }
+                (Some('\n'), _) if !multi_line => return self.error_at(start_pos, "Unterminated string literal"),
+                (Some(c), _) => {
+                    self.advance();
+                    s.push(c);
+                }
+                (None, _) => return self.error_at(start_pos, "Unterminated string literal"),
             }
+        }
 
-            _ if builder.starts_with('"') => { // string literals
-                builder = String::from(&builder[1..]);
-                if builder.ends_with('"') {
-                    builder.pop();
-                    TokenType::String(builder.clone())
-                } else {
-                    while self.pos < self.chars.len() && self.chars[self.pos] != '"' {
-                        builder.push(self.chars[self.pos]);
-                        self.update_code_pos(1);
+        if parts.is_empty() {
+            return Ok(TokenType::String(s));
+        }
+        if !s.is_empty() {
+            parts.push(StringPart::Literal(s));
+        }
+        Ok(TokenType::InterpolatedString(parts))
+    }
+
+    // reads an embedded expression and its optional format specifier, starting at the `{`
+    // the expression itself is lexed and parsed later by the parser
+    fn lex_interpolation(&mut self, multi_line: bool) -> Result<StringPart, ScriptError> {
+        let open_pos = (self.code_pos.0, self.code_pos.1 + 1);
+        self.advance();
+        let expr_pos = self.code_pos;
+        let start = self.pos;
+        let mut depth = 0;
+        let mut spec_start = None;
+        loop {
+            match self.peek() {
+                Some('}') if depth == 0 => break,
+                Some('{') | Some('(') | Some('[') => depth += 1,
+                Some('}') | Some(')') | Some(']') => depth -= 1,
+                Some(':') if depth == 0 && spec_start.is_none() => spec_start = Some(self.pos),
+                // skip over strings and chars in the expression so their braces don't count
+                Some(q @ ('"' | '\'')) => {
+                    self.advance();
+                    while let Some(c) = self.peek() {
+                        if c == q || c == '\n' {
+                            break;
+                        }
+                        if c == '\\' {
+                            self.advance();
+                        }
+                        self.advance();
                     }
-                    self.update_code_pos(1);
-                    TokenType::String(builder.clone())
                 }
-            },
-            _ if builder.chars().all(|c| c.is_digit(10) || c == '.' || c == '-') => {
-                if builder.contains('.') {
-                    TokenType::Float(builder.parse().unwrap())
-                } else {
-                    TokenType::Int(builder.parse().unwrap())
+                Some('\n') if !multi_line => return self.error_at(open_pos, "Unterminated string interpolation"),
+                None => return self.error_at(open_pos, "Unterminated string interpolation"),
+                _ => {}
+            }
+            self.advance();
+        }
+        let end = self.pos;
+        self.advance(); // }
+
+        let expr_end = spec_start.unwrap_or(end);
+        let expr = self.source[start..expr_end].to_string();
+        if expr.trim().is_empty() {
+            return self.error_at(open_pos, "Empty string interpolation");
+        }
+        let spec = match spec_start {
+            Some(i) => {
+                let spec = &self.source[i + 1..end];
+                match FormatSpec::parse(spec) {
+                    Ok(spec) => Some(spec),
+                    Err(e) => return self.error_at(open_pos, &e),
                 }
-            },
+            }
+            None => None,
+        };
+        Ok(StringPart::Expr(expr, expr_pos, spec))
+    }
 
-            // identifiers
-            _ => TokenType::Ident(builder.clone()),
+    // lexes a raw string such as r"C:\path" or r#"say "hi""#, which ignore escape sequences
+    // and may span multiple lines, the closing quote needs as many #s as the opening one
+    fn lex_raw_string(&mut self, start_pos: (usize, usize)) -> Result<TokenType, ScriptError> {
+        self.advance(); // r
+        let mut hashes = 0;
+        while self.peek() == Some('#') {
+            self.advance();
+            hashes += 1;
+        }
+        if self.peek() != Some('"') {
+            return self.error_at(start_pos, "Invalid raw string literal, expected '\"'");
+        }
+        self.advance();
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let start = self.pos;
+        let Some(len) = self.source[start..].find(&terminator) else {
+            return self.error_at(start_pos, "Unterminated raw string literal");
         };
+        while self.pos < start + len + terminator.len() {
+            self.advance();
+        }
+        Ok(TokenType::String(self.source[start..start + len].to_string()))
+    }
 
-        Token {
-            token_type,
-            lexeme: builder,
-            pos: self.code_pos,
+    // lexes a char literal such as 'a' or '\n', starting at the opening quote
+    fn lex_char(&mut self) -> Result<TokenType, ScriptError> {
+        self.advance();
+        let c = match self.peek() {
+            Some('\\') => {
+                self.advance();
+                self.read_escape()?
+            }
+            Some('\'') | Some('\n') | None => return self.error("Empty char literal"),
+            Some(c) => {
+                self.advance();
+                c
+            }
+        };
+        if self.peek() != Some('\'') {
+            return self.error("Unterminated char literal");
         }
+        self.advance();
+        Ok(TokenType::Char(c))
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
-            if self.chars[self.pos] == '\n' {
-                self.code_pos.0 += 1;
-                self.code_pos.1 = 0;
-            } else {
-                self.code_pos.1 += 1;
+    // reads the escape sequence following a backslash
+    // supports \n, \t, \r, \0, \\, \', \" and \u{...} unicode escapes
+    fn read_escape(&mut self) -> Result<char, ScriptError> {
+        let Some(c) = self.advance() else {
+            return self.error("Unterminated escape sequence");
+        };
+        Ok(match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '\'' => '\'',
+            '"' => '"',
+            'u' => {
+                if self.peek() != Some('{') {
+                    return self.error("Invalid unicode escape, expected '\\u{...}'");
+                }
+                self.advance();
+                // at most 6 hex digits, so an unclosed escape stops before the quote or the line end
+                let mut hex = String::new();
+                while let Some(h) = self.peek().filter(|h| h.is_ascii_hexdigit() && hex.len() < 6) {
+                    hex.push(h);
+                    self.advance();
+                }
+                if self.peek() != Some('}') {
+                    return self.error(&format!("Unterminated unicode escape '\\u{{{}'", hex));
+                }
+                self.advance();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.error(&format!("Invalid unicode escape '\\u{{{}}}'", hex)),
+                }
             }
-            self.pos += 1;
+            _ => return self.error(&format!("Invalid escape sequence '\\{}'", c)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lexes the source and returns the token types without the trailing EOF
+    fn types(source: &str) -> Vec<TokenType> {
+        let mut tokens: Vec<TokenType> = Lexer::new(source).lex().unwrap().into_iter().map(|t| t.token_type).collect();
+        assert_eq!(tokens.pop(), Some(TokenType::EOF));
+        tokens
+    }
+
+    fn op(op: Operator) -> TokenType {
+        TokenType::Op(op)
+    }
+
+    fn ident(s: &str) -> TokenType {
+        TokenType::Ident(s.to_string())
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(types("let fn return if else while loop for break continue in as is sizeof drop exit import"), vec![
+            TokenType::Let, TokenType::Fn, TokenType::Return, TokenType::If, TokenType::Else,
+            TokenType::While, TokenType::Loop, TokenType::For, TokenType::Break, TokenType::Continue,
+            TokenType::In, TokenType::As, TokenType::Is, TokenType::Sizeof, TokenType::Drop, TokenType::Exit,
+            TokenType::Import,
+        ]);
+    }
+
+    #[test]
+    fn keyword_list() {
+        for word in super::KEYWORDS {
+            assert!(super::keyword(word).is_some(), "{} is not a keyword", word);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn variable_types() {
+        assert_eq!(types("int float string char bool range array map error"), vec![
+            TokenType::VariableType(VariableType::Int),
+            TokenType::VariableType(VariableType::Float),
+            TokenType::VariableType(VariableType::String),
+            TokenType::VariableType(VariableType::Char),
+            TokenType::VariableType(VariableType::Bool),
+            TokenType::VariableType(VariableType::Range),
+            TokenType::VariableType(VariableType::Array),
+            TokenType::VariableType(VariableType::Map),
+            TokenType::VariableType(VariableType::Error),
+        ]);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(types("+ - * / % ** .. & | ^ ! << >> && || ++ --"), vec![
+            op(Operator::Plus), op(Operator::Minus), op(Operator::Mul), op(Operator::Div),
+            op(Operator::Mod), op(Operator::Pow), op(Operator::Range), op(Operator::BitAnd),
+            op(Operator::BitOr), op(Operator::Xor), op(Operator::Not), op(Operator::LShift),
+            op(Operator::RShift), op(Operator::And), op(Operator::Or), op(Operator::Inc), op(Operator::Dec),
+        ]);
+        assert_eq!(types("== != > < >= <="), vec![
+            op(Operator::Eq), op(Operator::Neq), op(Operator::Gt),
+            op(Operator::Lt), op(Operator::Gte), op(Operator::Lte),
+        ]);
+    }
+
+    #[test]
+    fn punctuation() {
+        assert_eq!(types("= . [ ] : , { } ( )"), vec![
+            TokenType::Assign, TokenType::Decimal, TokenType::LBracket, TokenType::RBracket,
+            TokenType::Colon, TokenType::Comma, TokenType::LBrace, TokenType::RBrace,
+            op(Operator::LParen), op(Operator::RParen),
+        ]);
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(types("42 3.25 true false \"hello world\" 'c' '\\n' '\\u{1F402}'"), vec![
+            TokenType::Int(42), TokenType::Float(3.25), TokenType::Bool(true), TokenType::Bool(false),
+            TokenType::String("hello world".to_string()), TokenType::Char('c'),
+            TokenType::Char('\n'), TokenType::Char('🐂'),
+        ]);
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(types("x _private snake_case2 letter ifs"), vec![
+            ident("x"), ident("_private"), ident("snake_case2"), ident("letter"), ident("ifs"),
+        ]);
+    }
+
+    #[test]
+    fn whitespace_is_insignificant() {
+        assert_eq!(types("x+1"), vec![ident("x"), op(Operator::Plus), TokenType::Int(1)]);
+        assert_eq!(types("a==b"), vec![ident("a"), op(Operator::Eq), ident("b")]);
+        assert_eq!(types("x =1"), vec![ident("x"), TokenType::Assign, TokenType::Int(1)]);
+        assert_eq!(types("foo(a,b)"), vec![
+            ident("foo"), op(Operator::LParen), ident("a"), TokenType::Comma, ident("b"), op(Operator::RParen),
+        ]);
+        assert_eq!(types("arr[i]=\"x\""), vec![
+            ident("arr"), TokenType::LBracket, ident("i"), TokenType::RBracket,
+            TokenType::Assign, TokenType::String("x".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn minus_is_always_an_operator() {
+        assert_eq!(types("-5"), vec![op(Operator::Minus), TokenType::Int(5)]);
+        assert_eq!(types("x -5"), vec![ident("x"), op(Operator::Minus), TokenType::Int(5)]);
+        assert_eq!(types("x--5"), vec![ident("x"), op(Operator::Dec), TokenType::Int(5)]);
+    }
+
+    #[test]
+    fn ranges_are_not_floats() {
+        assert_eq!(types("1..5"), vec![TokenType::Int(1), op(Operator::Range), TokenType::Int(5)]);
+        assert_eq!(types("1.5"), vec![TokenType::Float(1.5)]);
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        assert_eq!(types("// line\nx /* block\n comment */ y // trailing"), vec![ident("x"), ident("y")]);
+    }
+
+    #[test]
+    fn eof() {
+        assert_eq!(types(""), vec![]);
+        assert_eq!(types("  // only a comment\n"), vec![]);
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(types(r#""a\tb\n\"q\" \\ \u{e9}""#), vec![TokenType::String("a\tb\n\"q\" \\ \u{e9}".to_string())]);
+        assert_eq!(types(r#"'\u{10FFFF}'"#), vec![TokenType::Char('\u{10FFFF}')]);
+        assert_eq!(types(r"'\n' '\'' '\\' 'é'"), vec![
+            TokenType::Char('\n'), TokenType::Char('\''), TokenType::Char('\\'), TokenType::Char('é'),
+        ]);
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let message = |source: &str| Lexer::new(source).lex().unwrap_err().message;
+        // an unclosed escape doesn't run on to a later brace
+        assert!(message("\"\\u{41\" + \"}\"").starts_with("Unterminated unicode escape '\\u{41'"));
+        assert!(message("\"\\u{41\n}\"").starts_with("Unterminated unicode escape '\\u{41'"));
+        assert!(message("'\\u{41'").starts_with("Unterminated unicode escape '\\u{41'"));
+        assert!(message("\"\\u{1234567}\"").starts_with("Unterminated unicode escape '\\u{123456'"));
+        assert!(message("\"\\u41\"").starts_with("Invalid unicode escape, expected '\\u{...}'"));
+        assert!(message("\"\\u{}\"").starts_with("Invalid unicode escape '\\u{}'"));
+        assert!(message("\"\\u{D800}\"").starts_with("Invalid unicode escape '\\u{D800}'"));
+        assert!(message("\"\\q\"").starts_with("Invalid escape sequence '\\q'"));
+    }
+
+    #[test]
+    fn raw_strings() {
+        assert_eq!(types(r#"r"C:\temp\n""#), vec![TokenType::String("C:\\temp\\n".to_string())]);
+        assert_eq!(types(r##"r#"say "hi""#"##), vec![TokenType::String("say \"hi\"".to_string())]);
+        assert_eq!(types("r x"), vec![ident("r"), ident("x")]);
+    }
+
+    #[test]
+    fn multi_line_strings() {
+        let tokens = Lexer::new("let s = \"\"\"\n  one\n  \"two\"\\t\n\"\"\"\nx").lex().unwrap();
+        assert_eq!(tokens[3].token_type, TokenType::String("  one\n  \"two\"\t\n".to_string()));
+        // the identifier after the string is on the right line
+        assert_eq!(tokens[4].token_type, ident("x"));
+        assert_eq!(tokens[4].pos, (5, 1));
+    }
+
+    #[test]
+    fn interpolated_strings() {
+        assert_eq!(types("\"hp: {hp}/{max:>4.1} {{x}}\""), vec![TokenType::InterpolatedString(vec![
+            StringPart::Literal("hp: ".to_string()),
+            StringPart::Expr("hp".to_string(), (1, 6), None),
+            StringPart::Literal("/".to_string()),
+            StringPart::Expr("max".to_string(), (1, 11), FormatSpec::parse(">4.1").ok()),
+            StringPart::Literal(" {x}".to_string()),
+        ])]);
+        // braces and quotes inside the expression don't end it
+        assert_eq!(types("\"{f(\"}\")}\""), vec![TokenType::InterpolatedString(vec![
+            StringPart::Expr("f(\"}\")".to_string(), (1, 2), None),
+        ])]);
+    }
+
+    #[test]
+    fn number_formats() {
+        assert_eq!(types("0xFF 0b1010 0o17 1_000_000 0x_dead_BEEF"), vec![
+            TokenType::Int(255), TokenType::Int(10), TokenType::Int(15), TokenType::Int(1_000_000), TokenType::Int(0xdead_beef),
+        ]);
+        assert_eq!(types("1e3 1e-3 2.5E+2 1_0.2_5"), vec![
+            TokenType::Float(1000.0), TokenType::Float(0.001), TokenType::Float(250.0), TokenType::Float(10.25),
+        ]);
+        assert_eq!(types("9223372036854775807"), vec![TokenType::Int(i64::MAX)]);
+        // an `e` that isn't followed by digits is not an exponent
+        assert_eq!(types("1.e"), vec![TokenType::Int(1), TokenType::Decimal, ident("e")]);
+        // the magnitude of i64::MIN is left for the parser to negate
+        assert_eq!(types("9223372036854775808"), vec![TokenType::Int(i64::MIN)]);
+        assert_eq!(types("--x"), vec![op(Operator::Dec), ident("x")]);
+        assert_eq!(types("- -1"), vec![op(Operator::Minus), op(Operator::Minus), TokenType::Int(1)]);
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        let message = |source: &str| Lexer::new(source).lex().unwrap_err().message;
+        assert!(message("1.2.3").starts_with("Invalid number literal '1.2.3'"));
+        assert!(message("12abc").starts_with("Invalid number literal '12abc'"));
+        assert!(message("0x").starts_with("Invalid integer literal '0x'"));
+        assert!(message("0xZZ").starts_with("Invalid integer literal '0xZZ'"));
+        assert!(message("0b102").starts_with("Invalid integer literal '0b102'"));
+        assert!(message("9223372036854775809").starts_with("Integer literal out of range '9223372036854775809'"));
+        assert!(message("0x8000000000000000").starts_with("Integer literal out of range '0x8000000000000000'"));
+        assert!(message("1e999").starts_with("Float literal out of range '1e999'"));
+    }
+
+    #[test]
+    fn errors_are_returned() {
+        let error = Lexer::new("let s = 1\n  \"open").lex().unwrap_err();
+        assert_eq!(error.pos, Some((2, 3)));
+        assert_eq!(error.message, "Unterminated string literal @ (2, 3)");
+    }
+
+    #[test]
+    fn positions() {
+        let tokens = Lexer::new("let x = 1\n  println(x)").lex().unwrap();
+        let positions: Vec<(usize, usize)> = tokens.iter().map(|t| t.pos).collect();
+        assert_eq!(positions, vec![(1, 1), (1, 5), (1, 7), (1, 9), (2, 3), (2, 10), (2, 11), (2, 12), (2, 13)]);
+    }
+}
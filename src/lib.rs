@@ -2,6 +2,7 @@
 
 use crate::lexer::Lexer;
 use crate::parser::Parser;
+use crate::random::Rng;
 
 mod parser;
 mod node;
@@ -14,34 +15,221 @@ mod scope;
 mod debug;
 mod function;
 mod matador_std;
+mod format;
+mod text;
+mod random;
+mod clock;
+mod module;
+mod sexpr;
+mod logging;
+mod syntax;
+mod json;
+mod lsp;
+mod formatter;
+mod lint;
+mod cli;
+mod repl;
+mod app;
 
-pub const DEBUG_OUTPUT: bool = false;
+pub use crate::variable::Overflow;
+pub use crate::clock::{Clock, SystemClock};
+pub use crate::debug::ScriptError;
+pub use crate::module::{FileResolver, ModuleResolver, NativeModule};
+pub use crate::function::{InPlaceFunction, NativeFunction};
+pub use crate::interpreter::Interpreter;
+pub use crate::lint::{lint_source, Lint, LintConfig, LintRule, Severity};
+pub use crate::logging::{LogCategory, LogLevel, LogSink, Logger, StderrSink};
+pub use crate::formatter::format_source;
+pub use crate::syntax::{tokenize, SourceToken, TokenCategory};
+pub use crate::text::Text;
+pub use crate::variable::Variable;
+
+// the command line of the matador binary
+#[doc(hidden)]
+pub use crate::app::main as run_cli;
 
 pub struct Matador {
     code: String,
+    overflow: Overflow,
+    rng_state: u64,
+    clock: Option<Box<dyn Clock>>,
+    resolver: Option<Box<dyn ModuleResolver>>,
+    modules: Vec<Box<dyn NativeModule>>,
+    args: Vec<String>,
+    logger: Option<Logger>,
 }
 
 impl Matador {
     pub fn new(code: String) -> Self {
         Self {
-            code
+            code,
+            overflow: Overflow::default(),
+            rng_state: Rng::from_time().state(),
+            clock: None,
+            resolver: None,
+            modules: Vec::new(),
+            args: Vec::new(),
+            logger: None,
         }
     }
 
-    pub fn execute(&self) {
-        // lexer
-        let mut lexer = Lexer::new(&self.code);
-        let tokens = lexer.lex();
+    /// Sets how integer overflow is handled, defaults to stopping with an error
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    /// Sets the state of the random number generator, a script run from the same state
+    /// gets the same random numbers. Defaults to a state seeded from the system clock
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.rng_state = state;
+    }
 
-        // parser
-        let mut parser = Parser::new(tokens);
-        let nodes = parser.parse();
+    /// The state of the random number generator, after `execute` this is the state the
+    /// script left it in, so it can be saved and restored later
+    pub fn rng_state(&self) -> u64 {
+        self.rng_state
+    }
+
+    /// Sets the clock used by the time functions, defaults to the system clock
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = Some(clock);
+    }
+
+    /// Sets how imports are found and loaded, defaults to files relative to the current directory
+    pub fn set_resolver(&mut self, resolver: Box<dyn ModuleResolver>) {
+        self.resolver = Some(resolver);
+    }
+
+    /// Adds a native module, scripts use its members under its name, i.e. `math.sqrt`. Unlike
+    /// the std its members are not available without the name
+    pub fn register_module(&mut self, module: Box<dyn NativeModule>) {
+        self.modules.push(module);
+    }
+
+    /// Sets the arguments passed to the script, scripts read them from the `args` array
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
 
-        // interpreter
+    /// Sets where the logs of the lexer, parser, interpreter and scopes go and which are kept,
+    /// nothing is logged by default
+    pub fn set_logger(&mut self, logger: Logger) {
+        self.logger = Some(logger);
+    }
+
+    /// Runs the script, an error stops it and is returned with its position. `exit` in the
+    /// script only stops the script
+    pub fn execute(&mut self) -> Result<(), ScriptError> {
         let mut interpreter = interpreter::Interpreter::new();
+        interpreter.set_overflow(self.overflow);
+        interpreter.set_rng_state(self.rng_state);
+        if let Some(clock) = self.clock.take() {
+            interpreter.set_clock(clock);
+        }
+        if let Some(resolver) = self.resolver.take() {
+            interpreter.set_resolver(resolver);
+        }
+        if let Some(logger) = self.logger.take() {
+            interpreter.set_logger(logger);
+        }
+        matador_std::attach_std(&mut interpreter);
+        for module in self.modules.iter() {
+            interpreter.register_module(module.as_ref());
+        }
+        interpreter.set_args(&self.args);
 
         // todo: register external functions and environment variables
 
-        interpreter.interpret(nodes);
+        // the logger is only installed while the script runs
+        let result = interpreter.with_logger(|interpreter| {
+            // lexer
+            let mut lexer = Lexer::new(&self.code);
+            let tokens = lexer.lex()?;
+
+            // parser
+            let mut parser = Parser::new(tokens);
+            let nodes = parser.parse()?;
+
+            interpreter.interpret(nodes)
+        });
+        self.rng_state = interpreter.rng_state();
+        self.logger = interpreter.take_logger();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Interpreter, Matador, NativeFunction, InPlaceFunction, NativeModule, ScriptError, Variable};
+
+    // a module a host program could register
+    struct Host;
+
+    impl NativeModule for Host {
+        fn name(&self) -> &str {
+            "host"
+        }
+
+        fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+            vec![("add", add), ("report", report)]
+        }
+
+        fn in_place_functions(&self) -> Vec<(&'static str, InPlaceFunction)> {
+            vec![("bump", bump)]
+        }
+
+        fn constants(&self) -> Vec<(&'static str, Variable)> {
+            vec![("answer", Variable::Int(42))]
+        }
+    }
+
+    fn add(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        match args.as_slice() {
+            [Variable::Int(a), Variable::Int(b)] => Ok(Variable::Int(a + b)),
+            _ => Err(ScriptError { message: "add takes two ints".to_string(), pos: None }),
+        }
     }
-}
\ No newline at end of file
+
+    fn bump(_: &mut Interpreter, target: &mut Variable, _: Vec<Variable>) -> Result<Variable, ScriptError> {
+        if let Variable::Int(n) = target {
+            *n += 1;
+        }
+        Ok(Variable::Int(0))
+    }
+
+    // hands a value back to the test as the error that stops the script
+    fn report(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        Err(ScriptError { message: format!("{}", args[0]), pos: None })
+    }
+
+    fn run_with_host(code: &str) -> ScriptError {
+        let mut matador = Matador::new(code.to_string());
+        matador.register_module(Box::new(Host));
+        matador.execute().unwrap_err()
+    }
+
+    #[test]
+    fn host_modules() {
+        let e = run_with_host("let n = 1\nhost.bump(n)\nhost.report(\"{host.add(n, 2)} {host.answer}\")");
+        assert_eq!(e.message, "4 42");
+        // the std stays available next to it, with and without its name
+        assert_eq!(run_with_host("host.report([math.abs(-1), abs(-2)])").message, "[1, 2]");
+        // host modules are only registered under their name, so they can't shadow the std
+        assert_eq!(run_with_host("add(1, 2)").message, "Invalid function call: \"add\"");
+        assert_eq!(run_with_host("host.add(1, \"2\")").message, "add takes two ints");
+    }
+
+    #[test]
+    fn errors_are_returned() {
+        let e = Matador::new("let a = 1\nprintln(a / 0)".to_string()).execute().unwrap_err();
+        assert_eq!(e.message, "Division by zero: 1 / 0");
+        let e = Matador::new("let = 1".to_string()).execute().unwrap_err();
+        assert_eq!(e.pos, Some((1, 5)));
+    }
+
+    #[test]
+    fn exit_only_stops_the_script() {
+        let code = "fn stop() {\n    exit\n}\nwhile true {\n    stop()\n}\nprintln(1 / 0)";
+        assert!(Matador::new(code.to_string()).execute().is_ok());
+    }
+}
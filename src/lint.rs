@@ -0,0 +1,604 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use crate::debug::ScriptError;
+use crate::lexer::{Lexer, Token, TokenType};
+use crate::matador_std;
+use crate::node::Node;
+use crate::parser::Parser;
+use crate::postfix::ShuntedStackItem;
+use crate::syntax::{lex_tolerant, Lexed};
+use crate::variable::Variable;
+
+/// A check of the linter
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LintRule {
+    /// a variable that is declared but never read
+    UnusedVariable,
+    /// a `let` of a name that is already declared, which assigns to the existing variable
+    Shadowing,
+    /// statements after `return`, `break`, `continue` or `exit`
+    UnreachableCode,
+    /// an assignment to a variable that was never declared
+    UndeclaredAssignment,
+    /// a call with a different number of arguments than the function has parameters
+    Arity,
+    /// an `if` whose condition is made of literals only
+    ConstantCondition,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 6] = [
+        LintRule::UnusedVariable,
+        LintRule::Shadowing,
+        LintRule::UnreachableCode,
+        LintRule::UndeclaredAssignment,
+        LintRule::Arity,
+        LintRule::ConstantCondition,
+    ];
+
+    /// Rules that find code which fails when it runs are errors, the others warnings
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            LintRule::UndeclaredAssignment | LintRule::Arity => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rule = match self {
+            LintRule::UnusedVariable => "unused-variable",
+            LintRule::Shadowing => "shadowing",
+            LintRule::UnreachableCode => "unreachable-code",
+            LintRule::UndeclaredAssignment => "undeclared-assignment",
+            LintRule::Arity => "arity",
+            LintRule::ConstantCondition => "constant-condition",
+        };
+        write!(f, "{}", rule)
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintRule::ALL.into_iter()
+            .find(|rule| rule.to_string() == s)
+            .ok_or_else(|| format!("unknown lint rule '{}'", s))
+    }
+}
+
+/// How much a lint matters, `Allow` turns a rule off
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+    Allow,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self {
+            Severity::Allow => "allow",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", severity)
+    }
+}
+
+/// The severity of each rule
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    severities: [Severity; 6],
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig { severities: LintRule::ALL.map(|rule| rule.default_severity()) }
+    }
+}
+
+impl LintConfig {
+    pub fn set(&mut self, rule: LintRule, severity: Severity) {
+        self.severities[rule as usize] = severity;
+    }
+
+    pub fn severity(&self, rule: LintRule) -> Severity {
+        self.severities[rule as usize]
+    }
+}
+
+/// A problem found by the linter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub message: String,
+    /// line and char, both starting at 1
+    pub pos: Option<(usize, usize)>,
+}
+
+/// Lints matador source code, sorted by position. Rules are turned off for a line by a
+/// `// lint: allow(rule, ...)` or `/* lint: allow(rule, ...) */` comment at its end or on
+/// the line before it. Source that
+/// doesn't lex or parse is returned as an error
+pub fn lint_source(source: &str, config: &LintConfig) -> Result<Vec<Lint>, ScriptError> {
+    let tokens = Lexer::new(source).lex()?;
+    let nodes = Parser::new(tokens.clone()).parse()?;
+    let mut linter = Linter::new(&tokens, &nodes, config);
+    linter.block(&nodes);
+    linter.end();
+
+    let allowed = suppressions(source);
+    let mut lints: Vec<Lint> = linter.lints.into_iter().filter(|lint| {
+        let suppressed = lint.pos.is_some_and(|(line, _)| allowed.get(&line).is_some_and(|rules| {
+            rules.iter().any(|r| r == "all" || *r == lint.rule.to_string())
+        }));
+        lint.severity != Severity::Allow && !suppressed
+    }).collect();
+    lints.sort_by_key(|lint| lint.pos);
+    Ok(lints)
+}
+
+// the rules allowed on each line by `lint: allow(...)` comments
+fn suppressions(source: &str) -> HashMap<usize, Vec<String>> {
+    let mut allowed: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut code_line = 0;
+    let lexed = lex_tolerant(source);
+    for (i, item) in lexed.iter().enumerate() {
+        match item {
+            Lexed::Token(token) => code_line = token.pos.0,
+            Lexed::Trivia(true, range, (line, _)) => {
+                let (comment, line) = (&source[range.clone()], *line);
+                let end_line = line + comment.matches('\n').count();
+                let comment = match comment.strip_prefix("/*") {
+                    Some(block) => block.strip_suffix("*/").unwrap_or(block),
+                    None => comment.trim_start_matches("//"),
+                };
+                let Some(rules) = comment.trim().strip_prefix("lint:")
+                    .and_then(|rest| rest.trim().strip_prefix("allow("))
+                    .and_then(|rest| rest.strip_suffix(')')) else {
+                    continue;
+                };
+                // a comment after code is about its line, a block comment before code about
+                // the line it ends on, and a comment on its own about the line after it
+                let next_line = lexed[i + 1..].iter().find_map(|item| match item {
+                    Lexed::Token(token) => Some(token.pos.0),
+                    _ => None,
+                });
+                let line = if code_line == line {
+                    line
+                } else if next_line == Some(end_line) {
+                    end_line
+                } else {
+                    end_line + 1
+                };
+                allowed.entry(line).or_default().extend(rules.split(',').map(|r| r.trim().to_string()));
+            }
+            _ => {}
+        }
+    }
+    allowed
+}
+
+// finds the tokens of nodes, the AST has no positions. Nodes are visited in the order of
+// the source, so each search starts after the last token found
+struct Locator<'a> {
+    tokens: &'a [Token],
+    cursor: usize,
+    // the position of the interpolated string being walked, its expressions have no tokens
+    interpolation: Option<(usize, usize)>,
+    found: Vec<(usize, usize)>,
+}
+
+impl Locator<'_> {
+    // finds the next token matching `f` and moves past it and `skip` more tokens
+    fn find(&mut self, skip: usize, f: impl Fn(&TokenType) -> bool) -> Option<usize> {
+        let i = (self.cursor..self.tokens.len()).find(|&i| f(&self.tokens[i].token_type))?;
+        self.cursor = (i + 1 + skip).min(self.tokens.len());
+        self.found.push(self.tokens[i].pos);
+        Some(i)
+    }
+
+    // the position of the next token matching `f`
+    fn locate(&mut self, skip: usize, f: impl Fn(&TokenType) -> bool) -> Option<(usize, usize)> {
+        if self.interpolation.is_some() {
+            return self.interpolation;
+        }
+        self.find(skip, f).map(|i| self.tokens[i].pos)
+    }
+
+    // the position of a name, qualified names like `lib.x` take several tokens
+    fn name(&mut self, name: &str) -> Option<(usize, usize)> {
+        let first = name.split('.').next().unwrap_or(name);
+        let skip = 2 * name.matches('.').count();
+        self.locate(skip, |t| match t {
+            TokenType::Ident(ident) => ident == first,
+            TokenType::VariableType(typ) => typ.to_string() == first,
+            _ => false,
+        })
+    }
+
+    // the position of the name after a keyword, i.e. the name in `let x`
+    fn declaration(&mut self, keyword: TokenType) -> Option<(usize, usize)> {
+        if self.interpolation.is_some() {
+            return self.interpolation;
+        }
+        let i = self.find(1, |t| *t == keyword)?;
+        self.tokens.get(i + 1).map(|t| t.pos)
+    }
+}
+
+struct Var {
+    name: String,
+    pos: Option<(usize, usize)>,
+    used: bool,
+    // parameters and imports aren't reported when unused
+    check_unused: bool,
+}
+
+struct Linter<'a> {
+    locator: Locator<'a>,
+    vars: Vec<Var>,
+    // the variables of each scope, the first is the global scope
+    scopes: Vec<Vec<usize>>,
+    // the scope depths where the bodies of functions start
+    functions: Vec<usize>,
+    // the number of parameters of the functions declared in the script
+    arities: HashMap<String, usize>,
+    // names declared by a `let` at the top level, functions can use them before they are declared
+    globals: HashSet<String>,
+    // names read in functions before the global they refer to was declared
+    late_reads: HashSet<String>,
+    config: &'a LintConfig,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(tokens: &'a [Token], nodes: &[Node], config: &'a LintConfig) -> Self {
+        let mut arities = HashMap::new();
+        // functions declared in the script replace the std ones of the same name
+        for module in matador_std::std_modules() {
+            for (name, arity) in module.arities() {
+                arities.insert(name.to_string(), arity);
+                arities.insert(format!("{}.{}", module.name(), name), arity);
+            }
+        }
+        let mut stack: Vec<&Node> = nodes.iter().collect();
+        while let Some(node) = stack.pop() {
+            if let Node::FunctionDecl(name, params, _) = node {
+                arities.insert(name.clone(), params.len());
+            }
+            stack.extend(node.children());
+        }
+        let globals = nodes.iter().filter_map(|node| match node {
+            Node::VarDecl(name, _) => Some(name.clone()),
+            _ => None,
+        }).collect();
+        Linter {
+            locator: Locator { tokens, cursor: 0, interpolation: None, found: Vec::new() },
+            vars: Vec::new(),
+            scopes: vec![Vec::new()],
+            functions: Vec::new(),
+            arities,
+            globals,
+            late_reads: HashSet::new(),
+            config,
+            lints: Vec::new(),
+        }
+    }
+
+    fn lint(&mut self, rule: LintRule, message: String, pos: Option<(usize, usize)>) {
+        self.lints.push(Lint { rule, severity: self.config.severity(rule), message, pos });
+    }
+
+    // the variable a name refers to. Functions see their own scopes and the global scope
+    fn lookup(&self, name: &str) -> Option<usize> {
+        let outer = self.functions.last().copied().unwrap_or(0);
+        self.scopes.iter().enumerate().rev()
+            .filter(|(depth, _)| *depth == 0 || *depth >= outer)
+            .flat_map(|(_, scope)| scope.iter().rev())
+            .copied()
+            .find(|&i| self.vars[i].name == name)
+    }
+
+    fn declare(&mut self, name: &str, pos: Option<(usize, usize)>, check_unused: bool) {
+        self.scopes.last_mut().unwrap().push(self.vars.len());
+        self.vars.push(Var { name: name.to_string(), pos, used: false, check_unused });
+    }
+
+    fn read(&mut self, name: &str) {
+        let name = name.split('.').next().unwrap_or(name);
+        match self.lookup(name) {
+            Some(i) => self.vars[i].used = true,
+            None if !self.functions.is_empty() => {
+                self.late_reads.insert(name.to_string());
+            }
+            None => {}
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        self.report_unused(scope);
+    }
+
+    fn report_unused(&mut self, scope: Vec<usize>) {
+        for i in scope {
+            let var = &self.vars[i];
+            if var.check_unused && !var.used && !var.name.starts_with('_') {
+                let message = format!("`{}` is never read", var.name);
+                self.lint(LintRule::UnusedVariable, message, var.pos);
+            }
+        }
+    }
+
+    // reports the global variables once everything is walked
+    fn end(&mut self) {
+        let globals = std::mem::take(&mut self.scopes[0]);
+        for &i in globals.iter() {
+            if self.late_reads.contains(&self.vars[i].name) {
+                self.vars[i].used = true;
+            }
+        }
+        self.report_unused(globals);
+    }
+
+    fn block(&mut self, nodes: &[Node]) {
+        // the position of the statement nothing runs after
+        let mut terminated: Option<Option<(usize, usize)>> = None;
+        let mut reported = false;
+        for node in nodes {
+            let mark = self.locator.found.len();
+            self.node(node);
+            let pos = self.locator.found.get(mark).copied();
+            // one lint for the rest of the block
+            if let Some(terminator) = terminated.filter(|_| !reported) {
+                // literals have no tokens that are located, the lint goes to the terminator
+                self.lint(LintRule::UnreachableCode, "unreachable code, nothing after this runs".to_string(), pos.or(terminator));
+                reported = true;
+            }
+            if terminated.is_none() && terminates(node) {
+                terminated = Some(pos);
+            }
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::VarDecl(name, value) => {
+                let pos = self.locator.declaration(TokenType::Let);
+                if let Some(value) = value {
+                    self.node(value);
+                }
+                let in_function = !self.functions.is_empty();
+                if self.lookup(name).is_some() || (in_function && self.globals.contains(name)) {
+                    let message = format!("`{}` is already declared, this `let` assigns to the existing variable", name);
+                    // the interpreter reuses the variable, so there is nothing to declare
+                    self.lint(LintRule::Shadowing, message, pos);
+                    return;
+                }
+                self.declare(name, pos, true);
+            }
+            Node::VarAssign(name, value) | Node::ArrayMapAssign(name, _, value) => {
+                let pos = self.locator.name(name);
+                if let Node::ArrayMapAssign(_, index, _) = node {
+                    self.node(index);
+                    // changing an element reads the array
+                    self.read(name);
+                }
+                self.node(value);
+                let declared = name.contains('.') || self.lookup(name).is_some()
+                    || (!self.functions.is_empty() && self.globals.contains(name));
+                if !declared {
+                    let message = format!("assignment to `{}`, which is not declared, use `let {} = ...`", name, name);
+                    self.lint(LintRule::UndeclaredAssignment, message, pos);
+                }
+            }
+            Node::Ident(name) => {
+                self.locator.name(name);
+                self.read(name);
+            }
+            Node::ArrayMapAccess(name, index) => {
+                self.locator.name(name);
+                self.read(name);
+                self.node(index);
+            }
+            Node::FunctionCall(name, args) => {
+                let pos = self.locator.name(name);
+                // a variable holding a function, or a module
+                let is_variable = self.lookup(name.split('.').next().unwrap_or(name)).is_some();
+                self.read(name);
+                if let Some(&arity) = self.arities.get(name) {
+                    if !is_variable && arity != args.len() {
+                        let message = format!("`{}` takes {} argument(s) but {} were given", name, arity, args.len());
+                        self.lint(LintRule::Arity, message, pos);
+                    }
+                }
+                args.iter().for_each(|arg| self.node(arg));
+            }
+            Node::FunctionDecl(_, params, body) => {
+                if self.locator.declaration(TokenType::Fn).is_some() {
+                    // skip the parameters, they would be found as reads of their names
+                    self.locator.find(0, |t| *t == TokenType::LBrace);
+                }
+                self.push_scope();
+                self.functions.push(self.scopes.len() - 1);
+                for param in params {
+                    self.declare(param, None, false);
+                }
+                match body.as_ref() {
+                    Node::Block(nodes) => self.block(nodes),
+                    body => self.node(body),
+                }
+                self.functions.pop();
+                self.pop_scope();
+            }
+            Node::Block(nodes) => {
+                self.push_scope();
+                self.block(nodes);
+                self.pop_scope();
+            }
+            Node::If(cond, then, els) => {
+                let pos = self.locator.locate(0, |t| *t == TokenType::If);
+                if is_constant(cond) {
+                    self.lint(LintRule::ConstantCondition, "the condition of this `if` is always the same".to_string(), pos);
+                }
+                self.node(cond);
+                then.iter().chain(els.iter()).for_each(|n| self.node(n));
+            }
+            Node::While(..) | Node::Loop(_) | Node::Return(_) | Node::Break | Node::Continue | Node::Exit => {
+                let keyword = match node {
+                    Node::While(..) => TokenType::While,
+                    Node::Loop(_) => TokenType::Loop,
+                    Node::Return(_) => TokenType::Return,
+                    Node::Break => TokenType::Break,
+                    Node::Continue => TokenType::Continue,
+                    _ => TokenType::Exit,
+                };
+                self.locator.locate(0, |t| *t == keyword);
+                node.children().into_iter().for_each(|n| self.node(n));
+            }
+            Node::Drop(name) => {
+                self.locator.declaration(TokenType::Drop);
+                self.read(name);
+            }
+            Node::Import(_, name) => {
+                let pos = self.locator.locate(1, |t| *t == TokenType::Import);
+                if self.locator.tokens.get(self.locator.cursor).is_some_and(|t| t.token_type == TokenType::As) {
+                    self.locator.cursor += 2;
+                }
+                self.declare(name, pos, false);
+            }
+            // the expressions of an interpolated string are inside its token
+            Node::Concat(nodes) => {
+                let pos = self.locator.locate(0, |t| matches!(t, TokenType::InterpolatedString(_)));
+                let outer = self.locator.interpolation.take();
+                self.locator.interpolation = outer.or(pos);
+                nodes.iter().for_each(|n| self.node(n));
+                self.locator.interpolation = outer;
+            }
+            node => node.children().into_iter().for_each(|n| self.node(n)),
+        }
+    }
+}
+
+// whether nothing after a statement runs
+fn terminates(node: &Node) -> bool {
+    match node {
+        Node::Return(_) | Node::Break | Node::Continue | Node::Exit => true,
+        Node::Block(nodes) => nodes.iter().any(terminates),
+        Node::If(_, Some(then), Some(els)) => terminates(then) && terminates(els),
+        _ => false,
+    }
+}
+
+// whether an expression is made of literals only
+fn is_constant(node: &Node) -> bool {
+    match node {
+        Node::Variable(v) => !matches!(v, Variable::Function(..) | Variable::Module(_)),
+        Node::ShuntedStack(stack) => stack.items().iter().all(|item| match item {
+            ShuntedStackItem::Operand(node) => is_constant(node),
+            _ => true,
+        }),
+        Node::TypeCheck(node, _) | Node::TypeCast(node, _) => is_constant(node),
+        Node::Negative | Node::Not | Node::Expression => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint_source, LintConfig, LintRule, Severity};
+
+    fn lints(source: &str) -> Vec<(LintRule, (usize, usize))> {
+        lint_source(source, &LintConfig::default()).unwrap().into_iter()
+            .map(|lint| (lint.rule, lint.pos.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn unused_and_shadowed() {
+        let source = "let a = 1\nlet b = a\nlet a = 2\nfn f(x) {\n    let c = b\n    let _d = 0\n}\nf(1)\n";
+        assert_eq!(lints(source), vec![
+            (LintRule::Shadowing, (3, 5)),
+            (LintRule::UnusedVariable, (5, 9)),
+        ]);
+        // globals read in functions declared before them are used
+        assert_eq!(lints("fn f() {\n    println(g)\n}\nlet g = 1\nf()\n"), vec![]);
+        assert_eq!(lints("fn f() {\n    let g = 2\n    println(g)\n}\nlet g = 1\nprintln(g)\n"), vec![(LintRule::Shadowing, (2, 9))]);
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = "fn f(x) {\n    if x {\n        return 1\n    } else {\n        return 2\n    }\n    println(x)\n    x = 3\n}\nf(1)\nloop {\n    break\n    exit\n}\n";
+        assert_eq!(lints(source), vec![
+            (LintRule::UnreachableCode, (7, 5)),
+            (LintRule::UnreachableCode, (13, 5)),
+        ]);
+        // a literal has no position of its own
+        assert_eq!(lints("fn f() {\n    return 1\n    5\n}\nf()\n"), vec![(LintRule::UnreachableCode, (2, 5))]);
+    }
+
+    #[test]
+    fn assignments_and_calls() {
+        let source = "let a = [1]\nb = 2\na[0] = 3\nfn add(x, y) {\n    return x + y\n}\nprintln(add(1))\nlet s = \"{add(1, 2, 3)}\"\nprintln(s)\n";
+        assert_eq!(lints(source), vec![
+            (LintRule::UndeclaredAssignment, (2, 1)),
+            (LintRule::Arity, (7, 9)),
+            (LintRule::Arity, (8, 9)),
+        ]);
+        // the std functions that take a fixed number of arguments, unless the script declares them
+        let source = "println(sqrt(1, 2))\nlet a = [1]\npush(a, 1, 2)\nprintln(math.abs())\nprintln(max(1, 2, 3))\nfn len() {\n    return 0\n}\nprintln(len())\n";
+        assert_eq!(lints(source), vec![
+            (LintRule::Arity, (1, 9)),
+            (LintRule::Arity, (3, 1)),
+            (LintRule::Arity, (4, 9)),
+        ]);
+    }
+
+    #[test]
+    fn constant_conditions() {
+        let source = "let x = 1\nif true {\n    println(x)\n} else if 1 + 2 > 3 {\n}\nif x > 2 {\n}\n";
+        assert_eq!(lints(source), vec![
+            (LintRule::ConstantCondition, (2, 1)),
+            (LintRule::ConstantCondition, (4, 8)),
+        ]);
+    }
+
+    #[test]
+    fn severities_and_suppressions() {
+        let source = "let a = 1 // lint: allow(unused-variable)\n// lint: allow(all)\nb = 2\nc = 3\nlet d = 4\n";
+        assert_eq!(lints(source), vec![
+            (LintRule::UndeclaredAssignment, (4, 1)),
+            (LintRule::UnusedVariable, (5, 5)),
+        ]);
+        let mut config = LintConfig::default();
+        config.set(LintRule::UnusedVariable, Severity::Allow);
+        config.set(LintRule::UndeclaredAssignment, Severity::Warning);
+        let found = lint_source(source, &config).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Warning);
+        assert!(lint_source("let = 1", &config).is_err());
+
+        let source = "b = 2 /* lint: allow(undeclared-assignment) */\n/*\n lint: allow(all)\n*/\nc = 3\n/* lint: allow(all) */ d = 4\ne = 5\n";
+        assert_eq!(lints(source), vec![(LintRule::UndeclaredAssignment, (7, 1))]);
+    }
+
+    #[test]
+    fn unterminated() {
+        // code that is still being written is a parse error, not a crash
+        for source in ["fn f() {\n", "if true {\n    println(1)\n", "f(1,", "let a = [1, 2"] {
+            let e = lint_source(source, &LintConfig::default()).unwrap_err();
+            assert!(e.message.contains("Missing"), "{}: {}", source, e.message);
+        }
+        assert_eq!(lint_source("fn f() {\n", &LintConfig::default()).unwrap_err().pos, Some((2, 1)));
+    }
+}
@@ -0,0 +1,251 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+use better_term::{Color, flush_styles};
+
+/// How detailed logging is, each level includes the levels before it
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum LogLevel {
+    #[default]
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// The part of matador a log message comes from
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LogCategory {
+    Lexer,
+    Parser,
+    Interpreter,
+    Scope,
+}
+
+impl LogCategory {
+    const ALL: [LogCategory; 4] = [LogCategory::Lexer, LogCategory::Parser, LogCategory::Interpreter, LogCategory::Scope];
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
+        write!(f, "{}", level)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "off" => LogLevel::Off,
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" => LogLevel::Debug,
+            "trace" => LogLevel::Trace,
+            _ => return Err(format!("unknown log level '{}'", s)),
+        })
+    }
+}
+
+impl fmt::Display for LogCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let category = match self {
+            LogCategory::Lexer => "lexer",
+            LogCategory::Parser => "parser",
+            LogCategory::Interpreter => "interpreter",
+            LogCategory::Scope => "scope",
+        };
+        write!(f, "{}", category)
+    }
+}
+
+impl FromStr for LogCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LogCategory::ALL.into_iter()
+            .find(|category| category.to_string() == s)
+            .ok_or_else(|| format!("unknown log category '{}'", s))
+    }
+}
+
+/// Receives the log messages, a host implements this to capture them. Closures taking the
+/// level, category and message are sinks too
+pub trait LogSink {
+    fn log(&mut self, level: LogLevel, category: LogCategory, message: &str);
+}
+
+impl<F: FnMut(LogLevel, LogCategory, &str)> LogSink for F {
+    fn log(&mut self, level: LogLevel, category: LogCategory, message: &str) {
+        self(level, category, message)
+    }
+}
+
+/// Writes log messages to stderr
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrSink;
+
+impl LogSink for StderrSink {
+    fn log(&mut self, level: LogLevel, category: LogCategory, message: &str) {
+        eprintln!("{}[{} {}] {}{}", Color::BrightBlack, level, category, Color::White, message);
+        flush_styles();
+    }
+}
+
+/// Decides which messages are logged, by a level for each category, and where they go
+pub struct Logger {
+    levels: [LogLevel; 4],
+    sink: Box<dyn LogSink>,
+}
+
+impl Logger {
+    /// A logger that logs nothing until levels are set
+    pub fn new(sink: Box<dyn LogSink>) -> Self {
+        Logger { levels: [LogLevel::Off; 4], sink }
+    }
+
+    pub fn set_level(&mut self, category: LogCategory, level: LogLevel) {
+        self.levels[category as usize] = level;
+    }
+
+    /// Sets the level of every category
+    pub fn set_all_levels(&mut self, level: LogLevel) {
+        self.levels = [level; 4];
+    }
+
+    pub fn level(&self, category: LogCategory) -> LogLevel {
+        self.levels[category as usize]
+    }
+
+    /// Sets levels from a filter like `debug` for every category, or `parser=debug,scope=trace`
+    /// for single categories. This is the format of the `MATADOR_LOG` environment variable
+    pub fn apply_filter(&mut self, filter: &str) -> Result<(), String> {
+        for part in filter.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.split_once('=') {
+                Some((category, level)) => self.set_level(category.trim().parse()?, level.trim().parse()?),
+                None => self.set_all_levels(part.parse()?),
+            }
+        }
+        Ok(())
+    }
+
+    fn enabled(&self, category: LogCategory, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.level(category)
+    }
+}
+
+thread_local! {
+    // the logger of the interpreter that is running on this thread, see Interpreter::with_logger
+    static LOGGER: RefCell<Option<Logger>> = const { RefCell::new(None) };
+}
+
+// installs the logger used by everything running on this thread and returns the one it
+// replaces, None turns logging off
+pub(crate) fn replace_logger(logger: Option<Logger>) -> Option<Logger> {
+    LOGGER.replace(logger)
+}
+
+pub(crate) fn _enabled(category: LogCategory, level: LogLevel) -> bool {
+    LOGGER.with(|logger| {
+        logger.try_borrow().is_ok_and(|logger| logger.as_ref().is_some_and(|logger| logger.enabled(category, level)))
+    })
+}
+
+pub(crate) fn _log(category: LogCategory, level: LogLevel, args: fmt::Arguments) {
+    LOGGER.with(|logger| {
+        // a message logged by the sink itself while it is logging is dropped
+        let Ok(mut logger) = logger.try_borrow_mut() else {
+            return;
+        };
+        if let Some(logger) = logger.as_mut() {
+            if logger.enabled(category, level) {
+                logger.sink.log(level, category, &args.to_string());
+            }
+        }
+    });
+}
+
+// logs a message, the arguments are only evaluated when the category logs the level
+macro_rules! log {
+    ($category:ident, $level:ident, $($arg:tt)*) => {
+        if $crate::logging::_enabled($crate::logging::LogCategory::$category, $crate::logging::LogLevel::$level) {
+            $crate::logging::_log(
+                $crate::logging::LogCategory::$category,
+                $crate::logging::LogLevel::$level,
+                format_args!($($arg)*),
+            )
+        }
+    };
+}
+
+pub(crate) use log;
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::Matador;
+    use super::*;
+
+    // a logger that keeps the messages of every category up to the level
+    fn collecting(level: LogLevel) -> (Logger, Rc<RefCell<Vec<String>>>) {
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let kept = messages.clone();
+        let mut logger = Logger::new(Box::new(move |_, category: LogCategory, message: &str| {
+            kept.borrow_mut().push(format!("{} {}", category, message));
+        }));
+        logger.set_all_levels(level);
+        (logger, messages)
+    }
+
+    #[test]
+    fn disabled_messages_are_not_formatted() {
+        let (logger, messages) = collecting(LogLevel::Debug);
+        let outer = replace_logger(Some(logger));
+        let formatted = RefCell::new(0);
+        let count = || {
+            *formatted.borrow_mut() += 1;
+            "x"
+        };
+        log!(Parser, Trace, "{}", count());
+        log!(Parser, Debug, "{}", count());
+        replace_logger(outer);
+        assert_eq!(*formatted.borrow(), 1);
+        assert_eq!(messages.borrow().len(), 1);
+    }
+
+    #[test]
+    fn sinks_can_log() {
+        let mut logger = Logger::new(Box::new(|_, _, _: &str| log!(Scope, Error, "from the sink")));
+        logger.set_all_levels(LogLevel::Trace);
+        let outer = replace_logger(Some(logger));
+        log!(Scope, Error, "logged");
+        replace_logger(outer);
+    }
+
+    #[test]
+    fn the_logger_stays_with_the_script() {
+        let (logger, messages) = collecting(LogLevel::Trace);
+        let mut matador = Matador::new("let a = 1".to_string());
+        matador.set_logger(logger);
+        matador.execute().unwrap();
+        assert!(!messages.borrow().is_empty());
+        assert!(!_enabled(LogCategory::Scope, LogLevel::Error));
+
+        // it is kept for the next run
+        messages.borrow_mut().clear();
+        matador.execute().unwrap();
+        assert!(!messages.borrow().is_empty());
+    }
+}
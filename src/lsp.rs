@@ -0,0 +1,592 @@
+// a language server for matador scripts, spoken over stdio by editors
+
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use crate::json::Json;
+use crate::lexer::KEYWORDS;
+use crate::lsp::analysis::{Analysis, Symbol, SymbolKind};
+use crate::matador_std::std_modules;
+
+mod analysis;
+
+// error codes of json-rpc
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// kinds of the protocol
+const SYMBOL_MODULE: usize = 2;
+const SYMBOL_FUNCTION: usize = 12;
+const SYMBOL_VARIABLE: usize = 13;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_VARIABLE: usize = 6;
+const COMPLETION_MODULE: usize = 9;
+const COMPLETION_KEYWORD: usize = 14;
+const COMPLETION_CONSTANT: usize = 21;
+const SEVERITY_ERROR: usize = 1;
+
+// messages longer than this are skipped instead of read into memory
+const MAX_MESSAGE_LENGTH: usize = 64 * 1024 * 1024;
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+// the native functions and constants of a std module
+struct NativeMembers {
+    module: String,
+    functions: Vec<&'static str>,
+    constants: Vec<&'static str>,
+}
+
+struct Server {
+    documents: HashMap<String, Document>,
+    natives: Vec<NativeMembers>,
+    shutdown: bool,
+}
+
+// runs the server until the client sends `exit`, returns the exit code
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> i32 {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input) {
+        let replies = match message.map(|message| Json::parse(&message)) {
+            Err(length) => vec![error_reply(Json::Null, INVALID_REQUEST, format!(
+                "message of {} bytes is longer than the limit of {} bytes", length, MAX_MESSAGE_LENGTH
+            ))],
+            Ok(Ok(message)) => {
+                let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+                if method == "exit" {
+                    break;
+                }
+                let params = message.get("params").unwrap_or(&Json::Null);
+                match message.get("id") {
+                    Some(id) => vec![server.request(id.clone(), method, params)],
+                    None => server.notification(method, params),
+                }
+            }
+            // the id can't be read, so the reply has none
+            Ok(Err(e)) => vec![error_reply(Json::Null, PARSE_ERROR, format!("invalid json: {}", e))],
+        };
+        for reply in replies {
+            if write_message(&mut output, &reply.to_string()).is_err() {
+                return 1;
+            }
+        }
+    }
+    if server.shutdown { 0 } else { 1 }
+}
+
+// reads a message framed by a Content-Length header, None at the end of input. Messages over
+// the length limit are skipped and returned as their length
+fn read_message<R: BufRead>(input: &mut R) -> Option<Result<String, usize>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length?;
+    if length > MAX_MESSAGE_LENGTH {
+        let skipped = std::io::copy(&mut Read::take(&mut *input, length as u64), &mut std::io::sink()).ok()?;
+        return (skipped == length as u64).then_some(Err(length));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).ok()?;
+    String::from_utf8(body).ok().map(Ok)
+}
+
+fn error_reply(id: Json, code: i64, message: String) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        ("error", Json::object([("code", code.into()), ("message", message.into())])),
+    ])
+}
+
+fn write_message<W: Write>(output: &mut W, body: &str) -> std::io::Result<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+impl Server {
+    fn new() -> Self {
+        let natives = std_modules().iter().map(|module| {
+            let mut functions: Vec<&'static str> = module.functions().into_iter().map(|(name, _)| name).collect();
+            functions.extend(module.in_place_functions().into_iter().map(|(name, _)| name));
+            functions.sort();
+            let mut constants: Vec<&'static str> = module.constants().into_iter().map(|(name, _)| name).collect();
+            constants.sort();
+            NativeMembers { module: module.name().to_string(), functions, constants }
+        }).collect();
+        Server { documents: HashMap::new(), natives, shutdown: false }
+    }
+
+    fn request(&mut self, id: Json, method: &str, params: &Json) -> Json {
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.at_position(params, |_, uri, document, offset| {
+                document.analysis.definition(offset).map_or(Json::Null, |symbol| Json::object([
+                    ("uri", uri.into()),
+                    ("range", range(&document.text, &symbol.range)),
+                ]))
+            }),
+            "textDocument/hover" => self.at_position(params, |server, _, document, offset| server.hover(document, offset)),
+            "textDocument/completion" => self.at_position(params, |server, _, document, offset| server.completion(document, offset)),
+            "textDocument/documentSymbol" => match self.document(params) {
+                Some((_, document)) => Ok(document_symbols(document, None)),
+                None => Err((INVALID_PARAMS, "unknown document".to_string())),
+            },
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        };
+        match result {
+            Ok(result) => Json::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)]),
+            Err((code, message)) => error_reply(id, code, message),
+        }
+    }
+
+    // handles a message that gets no reply, returns the notifications to send back
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let Some(uri) = params.at(&["textDocument", "uri"]).and_then(Json::as_str) else {
+            return Vec::new();
+        };
+        let uri = uri.to_string();
+        let text = match method {
+            "textDocument/didOpen" => params.at(&["textDocument", "text"]).and_then(Json::as_str),
+            // documents are synced in full, so the last change is the whole text
+            "textDocument/didChange" => params.get("contentChanges").and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text"))
+                .and_then(Json::as_str),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![diagnostics(&uri, None)];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        let document = Document { analysis: Analysis::new(text), text: text.to_string() };
+        let notification = diagnostics(&uri, Some(&document));
+        self.documents.insert(uri, document);
+        vec![notification]
+    }
+
+    fn document<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document)> {
+        let uri = params.at(&["textDocument", "uri"]).and_then(Json::as_str)?;
+        self.documents.get(uri).map(|document| (uri, document))
+    }
+
+    // runs a request about a position in a document
+    fn at_position(&self, params: &Json, f: impl FnOnce(&Server, &str, &Document, usize) -> Json) -> Result<Json, (i64, String)> {
+        let (uri, document) = self.document(params).ok_or((INVALID_PARAMS, "unknown document".to_string()))?;
+        let line = params.at(&["position", "line"]).and_then(Json::as_usize);
+        let character = params.at(&["position", "character"]).and_then(Json::as_usize);
+        match line.zip(character) {
+            Some((line, character)) => Ok(f(self, uri, document, offset(&document.text, line, character))),
+            None => Err((INVALID_PARAMS, "invalid position".to_string())),
+        }
+    }
+
+    fn hover(&self, document: &Document, offset: usize) -> Json {
+        let Some((_, token)) = document.analysis.token_at(offset) else {
+            return Json::Null;
+        };
+        let name = &document.text[token.range.clone()];
+        let detail = match document.analysis.definition(offset) {
+            Some(symbol) => symbol.detail.clone(),
+            None => match self.native(&document.text[..token.range.start], name) {
+                Some(detail) => detail,
+                None => return Json::Null,
+            },
+        };
+        Json::object([
+            ("contents", Json::object([
+                ("kind", "markdown".into()),
+                ("value", format!("```matador\n{}\n```", detail).into()),
+            ])),
+            ("range", range(&document.text, &token.range)),
+        ])
+    }
+
+    // describes a native function or constant, `before` is the source before its name
+    fn native(&self, before: &str, name: &str) -> Option<String> {
+        let modules: Vec<&NativeMembers> = match module_before(before) {
+            Some(module) => self.natives.iter().filter(|m| m.module == module).collect(),
+            None => self.natives.iter().collect(),
+        };
+        modules.iter().find_map(|m| {
+            if m.functions.contains(&name) {
+                Some(format!("native fn {}.{}", m.module, name))
+            } else if m.constants.contains(&name) {
+                Some(format!("const {}.{}", m.module, name))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn completion(&self, document: &Document, offset: usize) -> Json {
+        let before = &document.text[..offset];
+        let word_start = before.trim_end_matches(is_ident_char).len();
+        let mut items = Vec::new();
+
+        // members of a module after `module.`
+        if let Some(module) = module_before(&before[..word_start]) {
+            if let Some(members) = self.natives.iter().find(|m| m.module == module) {
+                items.extend(members.functions.iter().map(|f| completion_item(f, COMPLETION_FUNCTION, "native fn")));
+                items.extend(members.constants.iter().map(|c| completion_item(c, COMPLETION_CONSTANT, "const")));
+            }
+            return Json::Array(items);
+        }
+
+        items.extend(KEYWORDS.iter().map(|k| completion_item(k, COMPLETION_KEYWORD, "keyword")));
+        for members in self.natives.iter() {
+            items.push(completion_item(&members.module, COMPLETION_MODULE, "std module"));
+            items.extend(members.functions.iter().map(|f| completion_item(f, COMPLETION_FUNCTION, &format!("native fn {}.{}", members.module, f))));
+            items.extend(members.constants.iter().map(|c| completion_item(c, COMPLETION_CONSTANT, &format!("const {}.{}", members.module, c))));
+        }
+        for symbol in document.analysis.visible(word_start) {
+            let kind = match symbol.kind {
+                SymbolKind::Function => COMPLETION_FUNCTION,
+                SymbolKind::Module => COMPLETION_MODULE,
+                SymbolKind::Variable | SymbolKind::Parameter => COMPLETION_VARIABLE,
+            };
+            items.push(completion_item(&symbol.name, kind, &symbol.detail));
+        }
+        Json::Array(items)
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("capabilities", Json::object([
+            // the whole document is sent on every change
+            ("textDocumentSync", 1usize.into()),
+            ("definitionProvider", true.into()),
+            ("hoverProvider", true.into()),
+            ("completionProvider", Json::object([("triggerCharacters", vec![".".into()].into())])),
+            ("documentSymbolProvider", true.into()),
+        ])),
+        ("serverInfo", Json::object([
+            ("name", "matador".into()),
+            ("version", env!("CARGO_PKG_VERSION").into()),
+        ])),
+    ])
+}
+
+// the diagnostics of a document, none for a closed document
+fn diagnostics(uri: &str, document: Option<&Document>) -> Json {
+    let diagnostics = document.map_or(Vec::new(), |document| {
+        document.analysis.diagnostics.iter().map(|d| Json::object([
+            ("range", range(&document.text, &d.range)),
+            ("severity", SEVERITY_ERROR.into()),
+            ("source", "matador".into()),
+            ("message", d.message.clone().into()),
+        ])).collect()
+    });
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        ("params", Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())])),
+    ])
+}
+
+// the symbols declared in a function, or at the top level for None, with the
+// declarations of functions nested in them
+fn document_symbols(document: &Document, parent: Option<usize>) -> Json {
+    let symbols = &document.analysis.symbols;
+    let children = symbols.iter().enumerate()
+        .filter(|(_, s)| s.parent == parent && s.kind != SymbolKind::Parameter)
+        .map(|(i, symbol)| document_symbol(document, symbol, i))
+        .collect::<Vec<Json>>();
+    children.into()
+}
+
+fn document_symbol(document: &Document, symbol: &Symbol, index: usize) -> Json {
+    let kind = match symbol.kind {
+        SymbolKind::Function => SYMBOL_FUNCTION,
+        SymbolKind::Module => SYMBOL_MODULE,
+        SymbolKind::Variable | SymbolKind::Parameter => SYMBOL_VARIABLE,
+    };
+    let mut entries = vec![
+        ("name".to_string(), symbol.name.clone().into()),
+        ("detail".to_string(), symbol.detail.clone().into()),
+        ("kind".to_string(), kind.into()),
+        ("range".to_string(), range(&document.text, &symbol.full_range)),
+        ("selectionRange".to_string(), range(&document.text, &symbol.range)),
+    ];
+    if symbol.kind == SymbolKind::Function {
+        entries.push(("children".to_string(), document_symbols(document, Some(index))));
+    }
+    Json::Object(entries)
+}
+
+fn completion_item(label: &str, kind: usize, detail: &str) -> Json {
+    Json::object([("label", label.into()), ("kind", kind.into()), ("detail", detail.into())])
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// the module in `module.` at the end of some source
+fn module_before(before: &str) -> Option<&str> {
+    let before = before.strip_suffix('.')?;
+    let module = &before[before.trim_end_matches(is_ident_char).len()..];
+    (!module.is_empty()).then_some(module)
+}
+
+// positions in the protocol are a line and a character in utf-16, both starting at 0
+
+fn position(source: &str, offset: usize) -> Json {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count();
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+fn range(source: &str, range: &std::ops::Range<usize>) -> Json {
+    Json::object([("start", position(source, range.start)), ("end", position(source, range.end))])
+}
+
+// the byte offset of a position, positions past the end of a line are at its end
+fn offset(source: &str, line: usize, character: usize) -> usize {
+    let Some(line_start) = source.split_inclusive('\n').take(line).map(str::len).try_fold(0, |start, len| {
+        (start + len <= source.len()).then_some(start + len)
+    }) else {
+        return source.len();
+    };
+    let mut units = 0;
+    for (i, c) in source[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{serve, MAX_MESSAGE_LENGTH};
+    use crate::json::Json;
+
+    // runs the server with messages from a client and returns its exit code and replies
+    fn session(messages: &[Json]) -> (i32, Vec<Json>) {
+        let mut input = String::new();
+        for message in messages {
+            let body = message.to_string();
+            input.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        }
+        let mut output = Vec::new();
+        let code = serve(input.as_bytes(), &mut output);
+        let output = String::from_utf8(output).unwrap();
+        let replies = output.split("Content-Length: ").skip(1)
+            .map(|message| Json::parse(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+            .collect();
+        (code, replies)
+    }
+
+    fn request(id: usize, method: &str, params: Json) -> Json {
+        Json::object([("jsonrpc", "2.0".into()), ("id", id.into()), ("method", method.into()), ("params", params)])
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        Json::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)])
+    }
+
+    fn open(text: &str) -> Json {
+        notification("textDocument/didOpen", Json::object([("textDocument", Json::object([
+            ("uri", "file:///test.mtdr".into()),
+            ("languageId", "matador".into()),
+            ("version", 1usize.into()),
+            ("text", text.into()),
+        ]))]))
+    }
+
+    fn at(id: usize, method: &str, line: usize, character: usize) -> Json {
+        request(id, method, Json::object([
+            ("textDocument", Json::object([("uri", "file:///test.mtdr".into())])),
+            ("position", Json::object([("line", line.into()), ("character", character.into())])),
+        ]))
+    }
+
+    fn result(replies: &[Json], id: usize) -> &Json {
+        replies.iter().find(|r| r.get("id").and_then(Json::as_usize) == Some(id)).unwrap().get("result").unwrap()
+    }
+
+    fn labels(completion: &Json) -> Vec<&str> {
+        completion.as_array().unwrap().iter().map(|item| item.get("label").and_then(Json::as_str).unwrap()).collect()
+    }
+
+    const SCRIPT: &str = "let hp = 100\nfn heal(amount) {\n    let total = hp + amount\n    return total\n}\nprintln(heal(5))\nmath.\n";
+
+    #[test]
+    fn lifecycle() {
+        let (code, replies) = session(&[
+            request(1, "initialize", Json::object([])),
+            notification("initialized", Json::object([])),
+            request(2, "textDocument/formatting", Json::object([])),
+            request(3, "shutdown", Json::Null),
+            notification("exit", Json::Null),
+        ]);
+        assert_eq!(code, 0);
+        assert_eq!(result(&replies, 1).at(&["capabilities", "hoverProvider"]), Some(&Json::Bool(true)));
+        assert_eq!(replies[1].at(&["error", "code"]), Some(&Json::Number(-32601.0)));
+        assert_eq!(result(&replies, 3), &Json::Null);
+
+        // exiting without a shutdown request is an error
+        assert_eq!(session(&[notification("exit", Json::Null)]).0, 1);
+    }
+
+    #[test]
+    fn invalid_json() {
+        let input = "Content-Length: 9\r\n\r\n{\"id\": 1,Content-Length: 33\r\n\r\n{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}";
+        let mut output = Vec::new();
+        // the server goes on to the exit after the error
+        assert_eq!(serve(input.as_bytes(), &mut output), 1);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("Content-Length").count(), 1);
+        let reply = Json::parse(output.split_once("\r\n\r\n").unwrap().1).unwrap();
+        assert_eq!(reply.at(&["error", "code"]), Some(&Json::Number(-32700.0)));
+        assert_eq!(reply.get("id"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let (_, replies) = session(&[open("let x = (1 + 2\nlet s = \"open\n")]);
+        let diagnostics = replies[0].at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].at(&["range", "start", "line"]).and_then(Json::as_usize), Some(1));
+        assert_eq!(diagnostics[0].at(&["range", "start", "character"]).and_then(Json::as_usize), Some(8));
+
+        // parse errors are reported at the token they were found at
+        let (_, replies) = session(&[open("let x = 1\nlet = 5\n")]);
+        let diagnostics = replies[0].at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].at(&["range", "start", "line"]).and_then(Json::as_usize), Some(1));
+        assert_eq!(diagnostics[0].at(&["range", "end", "character"]).and_then(Json::as_usize), Some(5));
+
+        let (_, replies) = session(&[open(SCRIPT.trim_end_matches("math.\n"))]);
+        assert_eq!(replies[0].at(&["params", "diagnostics"]), Some(&Json::Array(Vec::new())));
+    }
+
+    #[test]
+    fn too_long() {
+        // the body is skipped without reading it into memory, the exit after it still works
+        let length = MAX_MESSAGE_LENGTH + 1;
+        let input = format!("Content-Length: {}\r\n\r\n{}Content-Length: 33\r\n\r\n{{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}}", length, " ".repeat(length));
+        let mut output = Vec::new();
+        assert_eq!(serve(input.as_bytes(), &mut output), 1);
+        let output = String::from_utf8(output).unwrap();
+        let reply = Json::parse(output.split_once("\r\n\r\n").unwrap().1).unwrap();
+        assert_eq!(reply.at(&["error", "code"]), Some(&Json::Number(-32600.0)));
+
+        // a header claiming more than the limit with no body behind it ends the input
+        let input = "Content-Length: 18446744073709551615\r\n\r\n";
+        let mut output = Vec::new();
+        assert_eq!(serve(input.as_bytes(), &mut output), 1);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn unterminated() {
+        // half typed code is reported like any other parse error and the server keeps running
+        let (code, replies) = session(&[
+            open("fn f() {\n"),
+            request(1, "shutdown", Json::Null),
+            notification("exit", Json::Null),
+        ]);
+        assert_eq!(code, 0);
+        let diagnostics = replies[0].at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let message = diagnostics[0].get("message").and_then(Json::as_str).unwrap();
+        assert!(message.contains("Missing Right Brace ('}')"), "{}", message);
+
+        for text in ["if true {", "f(", "let a = [1,", "x["] {
+            let (_, replies) = session(&[open(text)]);
+            let diagnostics = replies[0].at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+            assert_eq!(diagnostics.len(), 1, "{}", text);
+        }
+    }
+
+    #[test]
+    fn definition_and_hover() {
+        let (_, replies) = session(&[
+            open(SCRIPT),
+            at(1, "textDocument/definition", 2, 17),
+            at(2, "textDocument/definition", 5, 9),
+            at(3, "textDocument/hover", 2, 9),
+            at(4, "textDocument/hover", 5, 3),
+            at(5, "textDocument/definition", 5, 2),
+        ]);
+        // `hp` in heal is declared on the first line
+        assert_eq!(result(&replies, 1).at(&["range", "start", "line"]), Some(&Json::Number(0.0)));
+        assert_eq!(result(&replies, 1).at(&["range", "start", "character"]), Some(&Json::Number(4.0)));
+        assert_eq!(result(&replies, 2).at(&["range", "start", "line"]), Some(&Json::Number(1.0)));
+        assert_eq!(result(&replies, 3).at(&["contents", "value"]).and_then(Json::as_str), Some("```matador\nlet total\n```"));
+        assert_eq!(result(&replies, 4).at(&["contents", "value"]).and_then(Json::as_str), Some("```matador\nnative fn io.println\n```"));
+        assert_eq!(result(&replies, 5), &Json::Null);
+
+        let (_, replies) = session(&[open("let name = \"bull\"\nlet n = sizeof name\n"), at(1, "textDocument/hover", 1, 4)]);
+        assert_eq!(result(&replies, 1).at(&["contents", "value"]).and_then(Json::as_str), Some("```matador\nlet n: int\n```"));
+    }
+
+    #[test]
+    fn completion() {
+        let (_, replies) = session(&[open(SCRIPT), at(1, "textDocument/completion", 3, 11), at(2, "textDocument/completion", 6, 5)]);
+        let items = labels(result(&replies, 1));
+        for label in ["while", "println", "math", "hp", "heal", "amount", "total"] {
+            assert!(items.contains(&label), "missing {}", label);
+        }
+        let items = labels(result(&replies, 2));
+        assert!(items.contains(&"sqrt"));
+        assert!(!items.contains(&"println"));
+
+        // locals of a function are out of scope after it
+        let (_, replies) = session(&[open(SCRIPT), at(1, "textDocument/completion", 5, 0)]);
+        let items = labels(result(&replies, 1));
+        assert!(items.contains(&"heal") && !items.contains(&"total") && !items.contains(&"amount"));
+    }
+
+    #[test]
+    fn document_symbols() {
+        let (_, replies) = session(&[
+            open(SCRIPT),
+            request(1, "textDocument/documentSymbol", Json::object([("textDocument", Json::object([("uri", "file:///test.mtdr".into())]))])),
+        ]);
+        let symbols = result(&replies, 1).as_array().unwrap();
+        let names: Vec<&str> = symbols.iter().map(|s| s.get("name").and_then(Json::as_str).unwrap()).collect();
+        assert_eq!(names, vec!["hp", "heal"]);
+        assert_eq!(symbols[1].at(&["range", "end", "line"]), Some(&Json::Number(4.0)));
+        let children = symbols[1].get("children").and_then(Json::as_array).unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].get("name").and_then(Json::as_str), Some("total"));
+    }
+
+    #[test]
+    fn utf16_positions() {
+        assert_eq!(super::offset("a😀b\nc", 0, 3), 5);
+        assert_eq!(super::offset("a😀b\nc", 1, 0), 7);
+        assert_eq!(super::offset("ab\nc", 0, 10), 2);
+        assert_eq!(super::position("a😀b\nc", 5), Json::object([("line", 0usize.into()), ("character", 3usize.into())]));
+    }
+}
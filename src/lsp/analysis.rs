@@ -0,0 +1,320 @@
+use std::ops::Range;
+use crate::lexer::{Lexer, Token, TokenType};
+use crate::node::Node;
+use crate::operator::Operator;
+use crate::parser::{module_name, Parser};
+use crate::postfix::ShuntedStackItem;
+use crate::syntax::{lex_tolerant, Lexed};
+use crate::variable::Variable;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum SymbolKind {
+    Variable,
+    Function,
+    Parameter,
+    Module,
+}
+
+// something declared in a script
+#[derive(Debug, Clone)]
+pub(crate) struct Symbol {
+    pub(crate) name: String,
+    pub(crate) kind: SymbolKind,
+    // the name in the declaration
+    pub(crate) range: Range<usize>,
+    // the whole declaration, for functions up to the end of the body
+    pub(crate) full_range: Range<usize>,
+    // where the symbol can be used
+    pub(crate) scope: Range<usize>,
+    // the function it is declared in
+    pub(crate) parent: Option<usize>,
+    // the declaration as shown on hover, i.e. `let hp: int` or `fn add(a, b)`
+    pub(crate) detail: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    pub(crate) range: Range<usize>,
+    pub(crate) message: String,
+}
+
+// what an editor needs to know about a script, worked out from its tokens and its AST
+pub(crate) struct Analysis {
+    pub(crate) tokens: Vec<Token>,
+    pub(crate) symbols: Vec<Symbol>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+struct Scope {
+    start: usize,
+    symbols: Vec<usize>,
+    // the function whose body this is
+    function: Option<usize>,
+}
+
+impl Analysis {
+    pub(crate) fn new(source: &str) -> Self {
+        let mut tokens = Vec::new();
+        let mut diagnostics = Vec::new();
+        for lexed in lex_tolerant(source) {
+            match lexed {
+                Lexed::Token(token) => tokens.push(token),
+                Lexed::Error(e, range, _) => diagnostics.push(Diagnostic { range, message: e.message }),
+                Lexed::Trivia(..) => {}
+            }
+        }
+
+        // the parser stops at the first error, so it only runs on source that lexes. While there
+        // is a lex error no parse errors are reported, they show up once the lex errors are fixed
+        let mut types = Vec::new();
+        if diagnostics.is_empty() {
+            match Lexer::new(source).lex().and_then(|tokens| Parser::new(tokens).parse()) {
+                Ok(nodes) => nodes.iter().for_each(|node| declared_types(node, &mut types)),
+                Err(e) => {
+                    let range = e.pos.map_or(0..0, |pos| token_range(&tokens, source, pos));
+                    diagnostics.push(Diagnostic { range, message: e.message });
+                }
+            }
+        }
+
+        let symbols = find_symbols(&tokens, &types, source.len());
+        Analysis { tokens, symbols, diagnostics }
+    }
+
+    // the token under the cursor, a cursor right after a token is on it
+    pub(crate) fn token_at(&self, offset: usize) -> Option<(usize, &Token)> {
+        self.tokens.iter().enumerate()
+            .find(|(_, t)| t.range.start <= offset && offset <= t.range.end && matches!(t.token_type, TokenType::Ident(_)))
+            .or_else(|| self.tokens.iter().enumerate().find(|(_, t)| t.range.start <= offset && offset < t.range.end))
+    }
+
+    // the symbols that can be used at an offset, a name declared twice gives the later one
+    pub(crate) fn visible(&self, offset: usize) -> Vec<&Symbol> {
+        let mut visible: Vec<&Symbol> = Vec::new();
+        for symbol in self.symbols.iter() {
+            if symbol.scope.start > offset || offset > symbol.scope.end {
+                continue;
+            }
+            match visible.iter_mut().find(|s| s.name == symbol.name) {
+                Some(s) if symbol.range.start > s.range.start => *s = symbol,
+                Some(_) => {}
+                None => visible.push(symbol),
+            }
+        }
+        visible
+    }
+
+    // the declaration of the name under the cursor
+    pub(crate) fn definition(&self, offset: usize) -> Option<&Symbol> {
+        let (i, token) = self.token_at(offset)?;
+        let TokenType::Ident(name) = &token.token_type else {
+            return None;
+        };
+        // the cursor is on a declaration
+        if let Some(symbol) = self.symbols.iter().find(|s| s.range == token.range) {
+            return Some(symbol);
+        }
+        // members of modules are declared in other files
+        if i > 0 && self.tokens[i - 1].token_type == TokenType::Decimal {
+            return None;
+        }
+        self.visible(token.range.start).into_iter().find(|s| &s.name == name)
+    }
+}
+
+// the bytes of the token at a line and char, for errors that only know their position
+fn token_range(tokens: &[Token], source: &str, pos: (usize, usize)) -> Range<usize> {
+    if let Some(token) = tokens.iter().find(|t| t.pos == pos) {
+        return token.range.clone();
+    }
+    // the end of the file, or the position of an error inside a token
+    let line_start: usize = source.split_inclusive('\n').take(pos.0.saturating_sub(1)).map(str::len).sum();
+    let offset = source[line_start..].char_indices().nth(pos.1.saturating_sub(1))
+        .map_or(source.len(), |(i, _)| line_start + i);
+    offset..offset
+}
+
+// the types of `let` declarations in the order they appear, None when the type isn't
+// clear from the value
+fn declared_types(node: &Node, types: &mut Vec<Option<String>>) {
+    if let Node::VarDecl(_, value) = node {
+        types.push(value.as_ref().and_then(|v| type_of(v)));
+    }
+    for child in node.children() {
+        declared_types(child, types);
+    }
+}
+
+fn type_of(node: &Node) -> Option<String> {
+    match node {
+        Node::Variable(v) => match v {
+            Variable::Int(_) => Some("int".to_string()),
+            Variable::Float(_) => Some("float".to_string()),
+            Variable::String(_) => Some("string".to_string()),
+            Variable::Char(_) => Some("char".to_string()),
+            Variable::Bool(_) => Some("bool".to_string()),
+            Variable::Range(..) => Some("range".to_string()),
+            Variable::Array(_) => Some("array".to_string()),
+            Variable::Map(_) => Some("map".to_string()),
+            Variable::Error(_) => Some("error".to_string()),
+            Variable::Function(..) | Variable::Module(_) => None,
+        },
+        Node::Array(_) => Some("array".to_string()),
+        Node::Concat(_) | Node::Format(..) => Some("string".to_string()),
+        Node::TypeCast(_, typ) => Some(typ.to_string()),
+        Node::TypeCheck(..) => Some("bool".to_string()),
+        Node::Sizeof(_) => Some("int".to_string()),
+        Node::ShuntedStack(stack) => {
+            if let Some(node) = stack.single_operand() {
+                return type_of(node);
+            }
+            match stack.items().last()? {
+                ShuntedStackItem::Operator(op) => match op {
+                    Operator::Eq | Operator::Neq | Operator::Gt | Operator::Lt | Operator::Gte
+                    | Operator::Lte | Operator::And | Operator::Or | Operator::Not => Some("bool".to_string()),
+                    Operator::Range => Some("range".to_string()),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn find_symbols(tokens: &[Token], types: &[Option<String>], len: usize) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = Vec::new();
+    let mut scopes = vec![Scope { start: 0, symbols: Vec::new(), function: None }];
+    // a function whose body is next, with its parameters
+    let mut pending: Option<(usize, Vec<&Token>)> = None;
+    let mut lets = types.iter();
+
+    let ident = |i: usize| match tokens.get(i).map(|t| &t.token_type) {
+        Some(TokenType::Ident(name)) => Some((name.clone(), &tokens[i])),
+        _ => None,
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        let scope = scopes.last().unwrap();
+        let parent = scopes.iter().rev().find_map(|s| s.function);
+        let scope_start = scope.start;
+        let declared = match &token.token_type {
+            TokenType::Let => {
+                let typ = lets.next().cloned().flatten();
+                ident(i + 1).map(|(name, name_token)| {
+                    let detail = match typ {
+                        Some(typ) => format!("let {}: {}", name, typ),
+                        None => format!("let {}", name),
+                    };
+                    let range = name_token.range.clone();
+                    Symbol {
+                        name,
+                        kind: SymbolKind::Variable,
+                        full_range: token.range.start..range.end,
+                        scope: range.end..len,
+                        range,
+                        parent,
+                        detail,
+                    }
+                })
+            }
+            TokenType::Fn => ident(i + 1).map(|(name, name_token)| {
+                let mut params = Vec::new();
+                if tokens.get(i + 2).is_some_and(|t| t.token_type == TokenType::Op(Operator::LParen)) {
+                    let mut j = i + 3;
+                    while let Some((_, param)) = ident(j) {
+                        params.push(param);
+                        j += if tokens.get(j + 1).is_some_and(|t| t.token_type == TokenType::Comma) { 2 } else { 1 };
+                    }
+                }
+                let names: Vec<String> = params.iter().filter_map(|p| match &p.token_type {
+                    TokenType::Ident(name) => Some(name.clone()),
+                    _ => None,
+                }).collect();
+                pending = Some((symbols.len(), params));
+                Symbol {
+                    detail: format!("fn {}({})", name, names.join(", ")),
+                    name,
+                    kind: SymbolKind::Function,
+                    range: name_token.range.clone(),
+                    full_range: token.range.start..name_token.range.end,
+                    // functions can be called from anywhere in their scope
+                    scope: scope_start..len,
+                    parent,
+                }
+            }),
+            TokenType::Import => {
+                let (path, name_token) = match tokens.get(i + 1).map(|t| &t.token_type) {
+                    Some(TokenType::String(path)) => (path.clone(), &tokens[i + 1]),
+                    Some(TokenType::Ident(name)) => (name.clone(), &tokens[i + 1]),
+                    _ => continue,
+                };
+                let (name, name_token) = match tokens.get(i + 2).map(|t| &t.token_type) {
+                    Some(TokenType::As) => match ident(i + 3) {
+                        Some(alias) => alias,
+                        None => continue,
+                    },
+                    _ => match module_name(&path) {
+                        Some(name) => (name, name_token),
+                        None => continue,
+                    },
+                };
+                let range = name_token.range.clone();
+                Some(Symbol {
+                    detail: format!("import {} as {}", path, name),
+                    name,
+                    kind: SymbolKind::Module,
+                    full_range: token.range.start..range.end,
+                    scope: range.end..len,
+                    range,
+                    parent,
+                })
+            }
+            TokenType::LBrace => {
+                let mut scope = Scope { start: token.range.start, symbols: Vec::new(), function: None };
+                if let Some((function, params)) = pending.take() {
+                    scope.function = Some(function);
+                    for param in params {
+                        if let TokenType::Ident(name) = &param.token_type {
+                            scope.symbols.push(symbols.len());
+                            symbols.push(Symbol {
+                                name: name.clone(),
+                                kind: SymbolKind::Parameter,
+                                range: param.range.clone(),
+                                full_range: param.range.clone(),
+                                scope: token.range.start..len,
+                                parent: Some(function),
+                                detail: format!("param {}", name),
+                            });
+                        }
+                    }
+                }
+                scopes.push(scope);
+                None
+            }
+            TokenType::RBrace if scopes.len() > 1 => {
+                let scope = scopes.pop().unwrap();
+                close_scope(&mut symbols, scope, token.range.end);
+                None
+            }
+            _ => None,
+        };
+        if let Some(symbol) = declared {
+            scopes.last_mut().unwrap().symbols.push(symbols.len());
+            symbols.push(symbol);
+        }
+    }
+    while let Some(scope) = scopes.pop() {
+        close_scope(&mut symbols, scope, len);
+    }
+    symbols
+}
+
+// ends the symbols of a block at its closing brace
+fn close_scope(symbols: &mut [Symbol], scope: Scope, end: usize) {
+    for i in scope.symbols {
+        symbols[i].scope.end = end;
+    }
+    if let Some(function) = scope.function {
+        symbols[function].full_range.end = end;
+    }
+}
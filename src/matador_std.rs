@@ -1,94 +1,148 @@
-use better_term::{Color, read_input};
+use std::fmt::Display;
+use better_term::Color;
+use crate::debug::{script_error, ScriptError};
 use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::text::Text;
 use crate::variable::Variable;
 
+mod io;
+mod convert;
+mod strings;
+mod arrays;
+mod math;
+mod random;
+mod time;
+
+// the modules of the standard library
+pub(crate) fn std_modules() -> Vec<Box<dyn NativeModule>> {
+    vec![
+        Box::new(io::Io),
+        Box::new(convert::Convert),
+        Box::new(strings::Strings),
+        Box::new(arrays::Arrays),
+        Box::new(math::Math),
+        Box::new(random::Random),
+        Box::new(time::Time),
+    ]
+}
+
 pub fn attach_std(interpreter: &mut Interpreter) {
-    // register the standard functions
-
-    // === PRINT ===
-    interpreter.register_native_function("print", |args| {
-        if args.len() > 1 {
-            // error
-            print!("{} Print only takes 1 argument!", Color::Red);
-        } else if args.len() == 1 {
-            print!("{}", args[0]);
-        }
-        Variable::Int(0)
-    });
-
-    interpreter.register_native_function("println", |args| {
-        if args.len() > 1 {
-            // error
-            println!("{} Print only takes 1 argument!", Color::Red);
-        } else if args.len() == 1 {
-            println!("{}", args[0]);
-        } else {
-            println!();
-        }
-        Variable::Int(0)
-    });
-
-    // === READ ===
-    interpreter.register_native_function("readln", |args| {
-        if args.len() > 0 {
-            // error
-            println!("{} readln takes no arguments", Color::Red);
-        }
-        let input = read_input!();
-        Variable::String(input)
-    });
-
-    interpreter.register_native_function("readint", |args| {
-        if args.len() > 0 {
-            // error
-            println!("{} readint takes no arguments", Color::Red);
-        }
-        let input = read_input!();
-        Variable::Int(input.parse().unwrap_or_else(|_| {
-            println!("{} Invalid input, expected an integer", Color::Red);
-            std::process::exit(1);
-        }))
-    });
-
-    interpreter.register_native_function("readbool", |args| {
-        if args.len() > 0 {
-            // error
-            println!("{} readbool takes no arguments", Color::Red);
-        }
-        let input = read_input!();
-        Variable::Bool(input.parse().unwrap_or_else(|_| {
-            println!("{} Invalid input, expected a boolean", Color::Red);
-            std::process::exit(1);
-        }))
-    });
-
-    interpreter.register_native_function("readfloat", |args| {
-        if args.len() > 0 {
-            // error
-            println!("{} readfloat takes no arguments", Color::Red);
-        }
-        let input = read_input!();
-        Variable::Float(input.parse().unwrap_or_else(|_| {
-            println!("{} Invalid input, expected a float", Color::Red);
-            std::process::exit(1);
-        }))
-    });
-
-    // === CHAR ===
-    // todo: remove this and add a char variable type
-    interpreter.register_native_function("to_ascii_char", |args| {
-        if args.len() > 1 {
-            // error
-            println!("{} to_ascii_char takes 1 argument", Color::Red);
+    // the std is available under the module names, i.e. `math.sqrt`, and without them
+    for module in std_modules() {
+        interpreter.register_module(module.as_ref());
+        interpreter.register_module_globally(module.as_ref());
+    }
+}
+
+// an error from a native function
+fn native_error(name: &str, msg: impl Display) -> ScriptError {
+    script_error!("{}Error in {}: {}{}", Color::BrightRed, name, Color::Red, msg)
+}
+
+fn type_error(name: &str, v: &Variable) -> ScriptError {
+    native_error(name, format!("invalid argument {:?}", v))
+}
+
+fn check_args(name: &str, args: &[Variable], n: usize) -> Result<(), ScriptError> {
+    if args.len() != n {
+        return Err(native_error(name, format!("expected {} argument(s), found {}", n, args.len())));
+    }
+    Ok(())
+}
+
+fn string_arg<'a>(name: &str, args: &'a [Variable], i: usize) -> Result<&'a Text, ScriptError> {
+    match &args[i] {
+        Variable::String(s) => Ok(s),
+        v => Err(type_error(name, v)),
+    }
+}
+
+fn int_arg(name: &str, args: &[Variable], i: usize) -> Result<i64, ScriptError> {
+    match &args[i] {
+        Variable::Int(n) => Ok(*n),
+        v => Err(type_error(name, v)),
+    }
+}
+
+fn array_arg<'a>(name: &str, args: &'a [Variable], i: usize) -> Result<&'a Vec<Box<Variable>>, ScriptError> {
+    match &args[i] {
+        Variable::Array(a) => Ok(a),
+        v => Err(type_error(name, v)),
+    }
+}
+
+fn array_target<'a>(name: &str, target: &'a mut Variable) -> Result<&'a mut Vec<Box<Variable>>, ScriptError> {
+    match target {
+        Variable::Array(a) => Ok(a),
+        v => Err(type_error(name, v)),
+    }
+}
+
+// a string or a char to search for
+fn pattern_arg(name: &str, args: &[Variable], i: usize) -> Result<String, ScriptError> {
+    match &args[i] {
+        Variable::String(s) => Ok(s.to_string()),
+        Variable::Char(c) => Ok(c.to_string()),
+        v => Err(type_error(name, v)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::ScriptError;
+    use crate::interpreter::Interpreter;
+    use crate::lexer::Lexer;
+    use crate::node::Node;
+    use crate::parser::Parser;
+    use crate::variable::Variable;
+    use super::{attach_std, std_modules};
+
+    // the functions that take a varying number of arguments, so they declare no arity
+    const VARIADIC: [&str; 7] = ["print", "println", "min", "max", "log", "sort", "range"];
+
+    // the value of the last statement
+    fn run(code: &str) -> Result<Variable, ScriptError> {
+        let mut interpreter = Interpreter::new();
+        attach_std(&mut interpreter);
+        let mut nodes = Parser::new(Lexer::new(code).lex()?).parse()?;
+        nodes.retain(|node| *node != Node::EOF);
+        Ok(interpreter.try_interpret(nodes)?.pop().unwrap())
+    }
+
+    #[test]
+    fn arities_match_functions() {
+        for module in std_modules() {
+            let functions: Vec<&str> = module.functions().into_iter().map(|(name, _)| name).collect();
+            let in_place: Vec<&str> = module.in_place_functions().into_iter().map(|(name, _)| name).collect();
+            let arities = module.arities();
+            for name in functions.iter().chain(in_place.iter()) {
+                let declared = arities.iter().any(|(n, _)| n == name);
+                assert_ne!(declared, VARIADIC.contains(name), "{}.{} needs an arity or is variadic", module.name(), name);
+            }
+
+            for (name, arity) in arities {
+                assert!(functions.contains(&name) || in_place.contains(&name), "{}.{} is not a function", module.name(), name);
+                // one argument too many is an error, under the module name and without it. In-place
+                // functions count their first argument but the error doesn't
+                let (first, checked) = if in_place.contains(&name) { ("a", arity - 1) } else { ("0", arity) };
+                let args: Vec<&str> = std::iter::once(first).chain(std::iter::repeat_n("0", arity)).collect();
+                for callee in [format!("{}.{}", module.name(), name), name.to_string()] {
+                    let e = run(&format!("let a = [1]\n{}({})", callee, args.join(", "))).unwrap_err();
+                    let expected = format!("Error in {}: expected {} argument(s), found {}", name, checked, checked + 1);
+                    assert!(e.message.contains(&expected), "{}: {}", callee, e.message);
+                }
+            }
         }
-        let int = match args[0] {
-            Variable::Int(i) => i,
-            _ => {
-                println!("{} to_ascii_char takes an integer", Color::Red);
-                std::process::exit(1);
+    }
+
+    #[test]
+    fn constants_are_namespaced_and_global() {
+        for module in std_modules() {
+            for (name, value) in module.constants() {
+                assert_eq!(run(&format!("{}.{}", module.name(), name)).unwrap(), value);
+                assert_eq!(run(name).unwrap(), value);
             }
-        };
-        let c = int as u8 as char;
-        Variable::String(c.to_string())
-    });
-}
\ No newline at end of file
+        }
+    }
+}
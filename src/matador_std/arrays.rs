@@ -0,0 +1,261 @@
+use std::cmp::Ordering;
+use crate::debug::ScriptError;
+use crate::function::{InPlaceFunction, NativeFunction};
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::Variable;
+use super::{native_error, type_error, check_args, int_arg, array_arg, array_target};
+
+// array functions, functions that change an array change the variable passed to them
+pub(crate) struct Arrays;
+
+impl NativeModule for Arrays {
+    fn name(&self) -> &str {
+        "arrays"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("index_of", index_of),
+            ("slice", slice),
+            ("map", map),
+            ("filter", filter),
+            ("reduce", reduce),
+            ("any", any),
+            ("all", all),
+            ("zip", zip),
+            ("enumerate", enumerate),
+            ("range", range),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("push", 2),
+            ("pop", 1),
+            ("insert", 3),
+            ("remove", 2),
+            ("index_of", 2),
+            ("slice", 3),
+            ("map", 2),
+            ("filter", 2),
+            ("reduce", 3),
+            ("any", 2),
+            ("all", 2),
+            ("zip", 2),
+            ("enumerate", 1),
+        ]
+    }
+
+    fn in_place_functions(&self) -> Vec<(&'static str, InPlaceFunction)> {
+        vec![
+            ("push", push),
+            ("pop", pop),
+            ("insert", insert),
+            ("remove", remove),
+            ("sort", sort),
+        ]
+    }
+}
+
+fn push(_: &mut Interpreter, target: &mut Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("push", &args, 1)?;
+    array_target("push", target)?.push(Box::new(args[0].clone()));
+    Ok(Variable::Int(0))
+}
+
+fn pop(_: &mut Interpreter, target: &mut Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("pop", &args, 0)?;
+    let v = array_target("pop", target)?.pop().ok_or_else(|| native_error("pop", "the array is empty"))?;
+    Ok(*v)
+}
+
+fn insert(_: &mut Interpreter, target: &mut Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("insert", &args, 2)?;
+    let i = int_arg("insert", &args, 0)?;
+    let a = array_target("insert", target)?;
+    if i < 0 || i as usize > a.len() {
+        return Err(native_error("insert", format!("index out of range: {}", i)));
+    }
+    a.insert(i as usize, Box::new(args[1].clone()));
+    Ok(Variable::Int(0))
+}
+
+fn remove(_: &mut Interpreter, target: &mut Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("remove", &args, 1)?;
+    let i = int_arg("remove", &args, 0)?;
+    let a = array_target("remove", target)?;
+    if i < 0 || i as usize >= a.len() {
+        return Err(native_error("remove", format!("index out of range: {}", i)));
+    }
+    Ok(*a.remove(i as usize))
+}
+
+// sorts in ascending order, or with a comparator `cmp(a, b)` that returns true (or a
+// negative int) when a goes before b. the sort is stable
+fn sort(interpreter: &mut Interpreter, target: &mut Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    if args.len() > 1 {
+        return Err(native_error("sort", format!("expected 1 or 2 argument(s), found {}", args.len() + 1)));
+    }
+    let a = std::mem::take(array_target("sort", target)?);
+    let sorted = match args.first() {
+        Some(cmp) => merge_sort(a, &mut |x, y| {
+            match interpreter.call_value(cmp, vec![x.clone(), y.clone()])? {
+                Variable::Bool(b) => Ok(b),
+                Variable::Int(n) => Ok(n < 0),
+                v => Err(native_error("sort", format!("the comparator returned {:?}", v))),
+            }
+        })?,
+        None => merge_sort(a, &mut |x, y| match x.compare(y) {
+            Some(o) => Ok(o == Ordering::Less),
+            None => Err(native_error("sort", format!("can't compare {:?} and {:?}", x, y))),
+        })?,
+    };
+    *target = Variable::Array(sorted);
+    Ok(Variable::Int(0))
+}
+
+// the index of the first element equal to the value, or -1 if there is none
+fn index_of(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("index_of", &args, 2)?;
+    let a = array_arg("index_of", &args, 0)?;
+    Ok(match a.iter().position(|v| v.equals(&args[1])) {
+        Some(i) => Variable::Int(i as i64),
+        None => Variable::Int(-1),
+    })
+}
+
+// the elements from start up to but not including end, works on strings too
+fn slice(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("slice", &args, 3)?;
+    let start = int_arg("slice", &args, 1)?;
+    let end = int_arg("slice", &args, 2)?;
+    Ok(match &args[0] {
+        Variable::Array(_) | Variable::String(_) => args[0].access(Variable::Range(start, end))?.unwrap(),
+        v => return Err(type_error("slice", v)),
+    })
+}
+
+fn map(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("map", &args, 2)?;
+    let a = array_arg("map", &args, 0)?;
+    let mapped = a.iter().map(|v| interpreter.call_value(&args[1], vec![*v.clone()]).map(Box::new));
+    Ok(Variable::Array(mapped.collect::<Result<_, _>>()?))
+}
+
+fn filter(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("filter", &args, 2)?;
+    let a = array_arg("filter", &args, 0)?;
+    let mut kept = Vec::new();
+    for v in a.iter() {
+        if test("filter", interpreter, &args[1], v)? {
+            kept.push(v.clone());
+        }
+    }
+    Ok(Variable::Array(kept))
+}
+
+// reduce(arr, f, initial) folds the array into a single value with `f(acc, element)`
+fn reduce(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("reduce", &args, 3)?;
+    let a = array_arg("reduce", &args, 0)?;
+    a.iter().try_fold(args[2].clone(), |acc, v| interpreter.call_value(&args[1], vec![acc, *v.clone()]))
+}
+
+fn any(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("any", &args, 2)?;
+    let a = array_arg("any", &args, 0)?;
+    for v in a.iter() {
+        if test("any", interpreter, &args[1], v)? {
+            return Ok(Variable::Bool(true));
+        }
+    }
+    Ok(Variable::Bool(false))
+}
+
+fn all(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("all", &args, 2)?;
+    let a = array_arg("all", &args, 0)?;
+    for v in a.iter() {
+        if !test("all", interpreter, &args[1], v)? {
+            return Ok(Variable::Bool(false));
+        }
+    }
+    Ok(Variable::Bool(true))
+}
+
+// pairs up the elements of two arrays, stopping at the end of the shorter one
+fn zip(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("zip", &args, 2)?;
+    let a = array_arg("zip", &args, 0)?;
+    let b = array_arg("zip", &args, 1)?;
+    Ok(Variable::Array(a.iter().zip(b.iter()).map(|(x, y)| Box::new(Variable::Array(vec![x.clone(), y.clone()]))).collect()))
+}
+
+// pairs each element with its index, [[0, a], [1, b], ...]
+fn enumerate(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("enumerate", &args, 1)?;
+    let a = array_arg("enumerate", &args, 0)?;
+    Ok(Variable::Array(a.iter().enumerate()
+        .map(|(i, v)| Box::new(Variable::Array(vec![Box::new(Variable::Int(i as i64)), v.clone()])))
+        .collect()))
+}
+
+// range(end), range(start, end) or range(start, end, step) as an array of ints
+fn range(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    let (start, end, step) = match args.len() {
+        1 => (0, int_arg("range", &args, 0)?, 1),
+        2 => (int_arg("range", &args, 0)?, int_arg("range", &args, 1)?, 1),
+        3 => (int_arg("range", &args, 0)?, int_arg("range", &args, 1)?, int_arg("range", &args, 2)?),
+        n => return Err(native_error("range", format!("expected 1 to 3 argument(s), found {}", n))),
+    };
+    if step == 0 {
+        return Err(native_error("range", "the step can't be 0"));
+    }
+    let mut arr = Vec::new();
+    let mut i = start;
+    while (step > 0 && i < end) || (step < 0 && i > end) {
+        arr.push(Box::new(Variable::Int(i)));
+        i = match i.checked_add(step) {
+            Some(i) => i,
+            None => break,
+        };
+    }
+    Ok(Variable::Array(arr))
+}
+
+// calls a predicate with a value, the predicate has to return a bool
+fn test(name: &str, interpreter: &mut Interpreter, f: &Variable, v: &Variable) -> Result<bool, ScriptError> {
+    match interpreter.call_value(f, vec![v.clone()])? {
+        Variable::Bool(b) => Ok(b),
+        r => Err(native_error(name, format!("expected the function to return a bool, found {:?}", r))),
+    }
+}
+
+// stable merge sort, `before(a, b)` is true when a has to go before b
+// unlike the std sort an inconsistent comparator can't make it panic
+// the first error from `before` stops the sort
+fn merge_sort(mut items: Vec<Box<Variable>>, before: &mut dyn FnMut(&Variable, &Variable) -> Result<bool, ScriptError>)
+              -> Result<Vec<Box<Variable>>, ScriptError> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, before)?;
+    let right = merge_sort(right, before)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // only take from the right when it strictly goes first, which keeps the sort stable
+        if before(r, l)? {
+            merged.push(right.next().unwrap());
+        } else {
+            merged.push(left.next().unwrap());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
@@ -0,0 +1,48 @@
+use crate::debug::ScriptError;
+use crate::function::NativeFunction;
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::Variable;
+use super::{check_args, string_arg};
+
+// conversions from strings, unlike `as int` and `as float` parsing returns an error value
+// for invalid input
+pub(crate) struct Convert;
+
+impl NativeModule for Convert {
+    fn name(&self) -> &str {
+        "convert"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("parse_int", parse_int),
+            ("parse_float", parse_float),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("parse_int", 1),
+            ("parse_float", 1),
+        ]
+    }
+}
+
+fn parse_int(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("parse_int", &args, 1)?;
+    let s = string_arg("parse_int", &args, 0)?;
+    Ok(match s.trim().parse() {
+        Ok(n) => Variable::Int(n),
+        Err(_) => Variable::Error(format!("invalid int '{}'", s)),
+    })
+}
+
+fn parse_float(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("parse_float", &args, 1)?;
+    let s = string_arg("parse_float", &args, 0)?;
+    Ok(match s.trim().parse() {
+        Ok(n) => Variable::Float(n),
+        Err(_) => Variable::Error(format!("invalid float '{}'", s)),
+    })
+}
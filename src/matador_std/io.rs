@@ -0,0 +1,88 @@
+use better_term::{Color, read_input};
+use crate::debug::{script_error, ScriptError};
+use crate::function::NativeFunction;
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::Variable;
+use super::check_args;
+
+// printing to and reading from the console
+pub(crate) struct Io;
+
+impl NativeModule for Io {
+    fn name(&self) -> &str {
+        "io"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("print", print),
+            ("println", println),
+            ("readln", readln),
+            ("readint", readint),
+            ("readbool", readbool),
+            ("readfloat", readfloat),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("readln", 0),
+            ("readint", 0),
+            ("readbool", 0),
+            ("readfloat", 0),
+        ]
+    }
+}
+
+fn print(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    if args.len() > 1 {
+        // error
+        print!("{} Print only takes 1 argument!", Color::Red);
+    } else if args.len() == 1 {
+        print!("{}", args[0]);
+    }
+    Ok(Variable::Int(0))
+}
+
+fn println(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    if args.len() > 1 {
+        // error
+        println!("{} Print only takes 1 argument!", Color::Red);
+    } else if args.len() == 1 {
+        println!("{}", args[0]);
+    } else {
+        println!();
+    }
+    Ok(Variable::Int(0))
+}
+
+fn readln(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("readln", &args, 0)?;
+    let input = read_input!();
+    Ok(Variable::String(input.into()))
+}
+
+fn readint(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("readint", &args, 0)?;
+    let input = read_input!();
+    input.parse().map(Variable::Int).map_err(|_| {
+        script_error!("{} Invalid input, expected an integer", Color::Red)
+    })
+}
+
+fn readbool(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("readbool", &args, 0)?;
+    let input = read_input!();
+    input.parse().map(Variable::Bool).map_err(|_| {
+        script_error!("{} Invalid input, expected a boolean", Color::Red)
+    })
+}
+
+fn readfloat(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("readfloat", &args, 0)?;
+    let input = read_input!();
+    input.parse().map(Variable::Float).map_err(|_| {
+        script_error!("{} Invalid input, expected a float", Color::Red)
+    })
+}
@@ -0,0 +1,333 @@
+use std::cmp::Ordering;
+use crate::debug::ScriptError;
+use crate::function::NativeFunction;
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::{Overflow, Variable};
+use super::{native_error, check_args, int_arg};
+
+// math functions, ints stay ints where the result is exact, everything else works on floats
+pub(crate) struct Math;
+
+impl NativeModule for Math {
+    fn name(&self) -> &str {
+        "math"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("abs", abs),
+            ("min", min),
+            ("max", max),
+            ("clamp", clamp),
+            ("floor", floor),
+            ("ceil", ceil),
+            ("round", round),
+            ("sqrt", sqrt),
+            ("sin", sin),
+            ("cos", cos),
+            ("tan", tan),
+            ("atan2", atan2),
+            ("log", log),
+            ("exp", exp),
+            ("lerp", lerp),
+            ("div_euclid", div_euclid),
+            ("rem_euclid", rem_euclid),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("abs", 1),
+            ("clamp", 3),
+            ("floor", 1),
+            ("ceil", 1),
+            ("round", 1),
+            ("sqrt", 1),
+            ("sin", 1),
+            ("cos", 1),
+            ("tan", 1),
+            ("atan2", 2),
+            ("exp", 1),
+            ("lerp", 3),
+            ("div_euclid", 2),
+            ("rem_euclid", 2),
+        ]
+    }
+
+    fn constants(&self) -> Vec<(&'static str, Variable)> {
+        vec![
+            ("pi", Variable::Float(std::f64::consts::PI)),
+        ]
+    }
+}
+
+fn abs(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("abs", &args, 1)?;
+    Ok(match &args[0] {
+        Variable::Int(n) if *n < 0 => args[0].neg(interpreter.overflow())?.unwrap(),
+        Variable::Int(n) => Variable::Int(*n),
+        Variable::Float(n) => Variable::Float(n.abs()),
+        v => return Err(number_error("abs", v)),
+    })
+}
+
+// min and max take two or more numbers, or an array of numbers
+fn min(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    extreme("min", &args, Ordering::Less)
+}
+
+fn max(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    extreme("max", &args, Ordering::Greater)
+}
+
+fn clamp(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("clamp", &args, 3)?;
+    for arg in args.iter() {
+        float_arg("clamp", arg)?;
+    }
+    if args[1].compare(&args[2]) == Some(Ordering::Greater) {
+        return Err(native_error("clamp", format!("the minimum {} is greater than the maximum {}", args[1], args[2])));
+    }
+    Ok(if args[0].compare(&args[1]) == Some(Ordering::Less) {
+        args[1].clone()
+    } else if args[0].compare(&args[2]) == Some(Ordering::Greater) {
+        args[2].clone()
+    } else {
+        args[0].clone()
+    })
+}
+
+fn floor(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("floor", &args, 1)?;
+    round_with("floor", &args[0], f64::floor)
+}
+
+fn ceil(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("ceil", &args, 1)?;
+    round_with("ceil", &args[0], f64::ceil)
+}
+
+// rounds half way cases away from zero
+fn round(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("round", &args, 1)?;
+    round_with("round", &args[0], f64::round)
+}
+
+fn sqrt(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("sqrt", &args, 1)?;
+    Ok(Variable::Float(float_arg("sqrt", &args[0])?.sqrt()))
+}
+
+// angles are in radians
+fn sin(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("sin", &args, 1)?;
+    Ok(Variable::Float(float_arg("sin", &args[0])?.sin()))
+}
+
+fn cos(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("cos", &args, 1)?;
+    Ok(Variable::Float(float_arg("cos", &args[0])?.cos()))
+}
+
+fn tan(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("tan", &args, 1)?;
+    Ok(Variable::Float(float_arg("tan", &args[0])?.tan()))
+}
+
+fn atan2(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("atan2", &args, 2)?;
+    Ok(Variable::Float(float_arg("atan2", &args[0])?.atan2(float_arg("atan2", &args[1])?)))
+}
+
+// log(x) is the natural logarithm, log(x, base) uses the given base
+fn log(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    Ok(match args.len() {
+        1 => Variable::Float(float_arg("log", &args[0])?.ln()),
+        2 => {
+            let x = float_arg("log", &args[0])?;
+            // the dedicated functions are exact for powers of the base
+            Variable::Float(match float_arg("log", &args[1])? {
+                10.0 => x.log10(),
+                2.0 => x.log2(),
+                base => x.log(base),
+            })
+        }
+        n => return Err(native_error("log", format!("expected 1 or 2 argument(s), found {}", n))),
+    })
+}
+
+fn exp(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("exp", &args, 1)?;
+    Ok(Variable::Float(float_arg("exp", &args[0])?.exp()))
+}
+
+// linear interpolation, lerp(a, b, 0) is a and lerp(a, b, 1) is b
+fn lerp(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("lerp", &args, 3)?;
+    let a = float_arg("lerp", &args[0])?;
+    let b = float_arg("lerp", &args[1])?;
+    let t = float_arg("lerp", &args[2])?;
+    Ok(Variable::Float(a + (b - a) * t))
+}
+
+// euclidean division, the remainder is never negative
+fn div_euclid(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("div_euclid", &args, 2)?;
+    euclid("div_euclid", &args, interpreter.overflow(), i64::checked_div_euclid, i64::wrapping_div_euclid)
+}
+
+fn rem_euclid(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("rem_euclid", &args, 2)?;
+    euclid("rem_euclid", &args, interpreter.overflow(), i64::checked_rem_euclid, i64::wrapping_rem_euclid)
+}
+
+fn number_error(name: &str, v: &Variable) -> ScriptError {
+    native_error(name, format!("expected a number, found {:?}", v))
+}
+
+// an int or a float as a float
+fn float_arg(name: &str, v: &Variable) -> Result<f64, ScriptError> {
+    match v {
+        Variable::Int(n) => Ok(*n as f64),
+        Variable::Float(n) => Ok(*n),
+        v => Err(number_error(name, v)),
+    }
+}
+
+// the smallest or largest of the arguments, or of the elements of a single array argument
+fn extreme(name: &str, args: &[Variable], keep: Ordering) -> Result<Variable, ScriptError> {
+    let values: Vec<&Variable> = match args {
+        [Variable::Array(a)] => a.iter().map(|v| &**v).collect(),
+        [] | [_] => return Err(native_error(name, format!("expected 2 or more numbers or an array, found {} argument(s)", args.len()))),
+        _ => args.iter().collect(),
+    };
+    let mut best = *values.first().ok_or_else(|| native_error(name, "the array is empty"))?;
+    for v in values {
+        float_arg(name, v)?;
+        if v.compare(best) == Some(keep) {
+            best = v;
+        }
+    }
+    Ok(best.clone())
+}
+
+fn round_with(name: &str, v: &Variable, f: fn(f64) -> f64) -> Result<Variable, ScriptError> {
+    match v {
+        Variable::Int(n) => Ok(Variable::Int(*n)),
+        Variable::Float(n) => Ok(Variable::Float(f(*n))),
+        v => Err(number_error(name, v)),
+    }
+}
+
+fn euclid(name: &str, args: &[Variable], overflow: Overflow,
+          checked: fn(i64, i64) -> Option<i64>, wrapping: fn(i64, i64) -> i64) -> Result<Variable, ScriptError> {
+    let a = int_arg(name, args, 0)?;
+    let b = int_arg(name, args, 1)?;
+    if b == 0 {
+        return Err(native_error(name, format!("division by zero: {} by 0", a)));
+    }
+    match checked(a, b) {
+        Some(n) => Ok(Variable::Int(n)),
+        None if overflow == Overflow::Wrap => Ok(Variable::Int(wrapping(a, b))),
+        None => Err(native_error(name, format!("integer overflow: {} by {}", a, b))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::ScriptError;
+    use crate::function::NativeFunction;
+    use crate::interpreter::Interpreter;
+    use crate::variable::{Overflow, Variable};
+    use super::*;
+
+    fn call_with(overflow: Overflow, f: NativeFunction, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_overflow(overflow);
+        f(&mut interpreter, args)
+    }
+
+    fn call(f: NativeFunction, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        call_with(Overflow::Error, f, args)
+    }
+
+    fn ints(values: &[i64]) -> Vec<Variable> {
+        values.iter().map(|n| Variable::Int(*n)).collect()
+    }
+
+    #[test]
+    fn ints_stay_ints() {
+        assert_eq!(call(abs, ints(&[-3])).unwrap(), Variable::Int(3));
+        assert_eq!(call(abs, vec![Variable::Float(-2.5)]).unwrap(), Variable::Float(2.5));
+        assert_eq!(call(floor, ints(&[3])).unwrap(), Variable::Int(3));
+        assert_eq!(call(floor, vec![Variable::Float(-2.5)]).unwrap(), Variable::Float(-3.0));
+        assert_eq!(call(round, vec![Variable::Float(2.5)]).unwrap(), Variable::Float(3.0));
+        assert_eq!(call(sqrt, ints(&[4])).unwrap(), Variable::Float(2.0));
+
+        // the magnitude of the smallest int doesn't fit in an int
+        let e = call(abs, ints(&[i64::MIN])).unwrap_err();
+        assert!(e.message.contains("Integer overflow"), "{}", e.message);
+        assert_eq!(call_with(Overflow::Wrap, abs, ints(&[i64::MIN])).unwrap(), Variable::Int(i64::MIN));
+    }
+
+    #[test]
+    fn min_and_max() {
+        assert_eq!(call(min, ints(&[3, 1, 2])).unwrap(), Variable::Int(1));
+        assert_eq!(call(max, vec![Variable::Int(1), Variable::Float(2.5)]).unwrap(), Variable::Float(2.5));
+        assert_eq!(call(max, vec![Variable::Int(3), Variable::Float(2.5)]).unwrap(), Variable::Int(3));
+        // the first of equal values is kept, whatever its type
+        assert_eq!(call(min, vec![Variable::Int(1), Variable::Float(1.0)]).unwrap(), Variable::Int(1));
+
+        let array = Variable::Array(vec![Box::new(Variable::Int(3)), Box::new(Variable::Float(1.5)), Box::new(Variable::Int(2))]);
+        assert_eq!(call(min, vec![array.clone()]).unwrap(), Variable::Float(1.5));
+        assert_eq!(call(max, vec![array]).unwrap(), Variable::Int(3));
+
+        assert!(call(min, vec![Variable::Array(Vec::new())]).unwrap_err().message.contains("the array is empty"));
+        assert!(call(max, ints(&[1])).is_err());
+        assert!(call(max, vec![Variable::Int(1), Variable::String("2".into())]).is_err());
+    }
+
+    #[test]
+    fn clamp_bounds() {
+        assert_eq!(call(clamp, ints(&[5, 1, 3])).unwrap(), Variable::Int(3));
+        assert_eq!(call(clamp, ints(&[-5, 1, 3])).unwrap(), Variable::Int(1));
+        assert_eq!(call(clamp, vec![Variable::Float(2.5), Variable::Int(1), Variable::Int(3)]).unwrap(), Variable::Float(2.5));
+        assert_eq!(call(clamp, vec![Variable::Int(0), Variable::Float(1.5), Variable::Int(3)]).unwrap(), Variable::Float(1.5));
+        let e = call(clamp, ints(&[2, 3, 1])).unwrap_err();
+        assert!(e.message.contains("the minimum 3 is greater than the maximum 1"), "{}", e.message);
+    }
+
+    #[test]
+    fn logarithms() {
+        assert_eq!(call(log, vec![Variable::Float(std::f64::consts::E)]).unwrap(), Variable::Float(1.0));
+        assert_eq!(call(log, ints(&[8, 2])).unwrap(), Variable::Float(3.0));
+        assert_eq!(call(log, ints(&[1000, 10])).unwrap(), Variable::Float(3.0));
+        match call(log, ints(&[81, 3])).unwrap() {
+            Variable::Float(x) => assert!((x - 4.0).abs() < 1e-12),
+            v => panic!("{:?}", v),
+        }
+        assert!(call(log, ints(&[1, 2, 3])).is_err());
+        assert!(call(log, vec![]).is_err());
+    }
+
+    #[test]
+    fn euclidean_division() {
+        assert_eq!(call(div_euclid, ints(&[-7, 2])).unwrap(), Variable::Int(-4));
+        assert_eq!(call(rem_euclid, ints(&[-7, 2])).unwrap(), Variable::Int(1));
+        assert_eq!(call(rem_euclid, ints(&[7, -2])).unwrap(), Variable::Int(1));
+
+        // dividing by zero is an error in both overflow modes
+        for overflow in [Overflow::Error, Overflow::Wrap] {
+            for f in [div_euclid as NativeFunction, rem_euclid] {
+                let e = call_with(overflow, f, ints(&[5, 0])).unwrap_err();
+                assert!(e.message.contains("division by zero: 5 by 0"), "{}", e.message);
+            }
+        }
+
+        assert!(call(div_euclid, ints(&[i64::MIN, -1])).unwrap_err().message.contains("integer overflow"));
+        assert_eq!(call_with(Overflow::Wrap, div_euclid, ints(&[i64::MIN, -1])).unwrap(), Variable::Int(i64::MIN));
+        assert_eq!(call_with(Overflow::Wrap, rem_euclid, ints(&[i64::MIN, -1])).unwrap(), Variable::Int(0));
+        assert!(call(div_euclid, vec![Variable::Float(1.0), Variable::Int(2)]).is_err());
+    }
+}
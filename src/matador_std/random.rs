@@ -0,0 +1,77 @@
+use crate::debug::ScriptError;
+use crate::function::{InPlaceFunction, NativeFunction};
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::Variable;
+use super::{native_error, check_args, int_arg, array_arg, array_target};
+
+// the numbers come from the interpreter's generator, so a script replays identically
+// when the host sets the same state
+pub(crate) struct Random;
+
+impl NativeModule for Random {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("rand_int", rand_int),
+            ("rand_float", rand_float),
+            ("choose", choose),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("rand_int", 2),
+            ("rand_float", 0),
+            ("choose", 1),
+            ("shuffle", 1),
+        ]
+    }
+
+    fn in_place_functions(&self) -> Vec<(&'static str, InPlaceFunction)> {
+        vec![
+            ("shuffle", shuffle),
+        ]
+    }
+}
+
+// rand_int(lo, hi) includes both ends, rand_int(1, 6) rolls a die
+fn rand_int(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("rand_int", &args, 2)?;
+    let lo = int_arg("rand_int", &args, 0)?;
+    let hi = int_arg("rand_int", &args, 1)?;
+    if lo > hi {
+        return Err(native_error("rand_int", format!("the minimum {} is greater than the maximum {}", lo, hi)));
+    }
+    Ok(Variable::Int(interpreter.rng().int_between(lo, hi)))
+}
+
+// a float in [0, 1)
+fn rand_float(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("rand_float", &args, 0)?;
+    Ok(Variable::Float(interpreter.rng().next_float()))
+}
+
+fn choose(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("choose", &args, 1)?;
+    let a = array_arg("choose", &args, 0)?;
+    if a.is_empty() {
+        return Err(native_error("choose", "the array is empty"));
+    }
+    let i = interpreter.rng().below(a.len() as u64);
+    Ok(*a[i as usize].clone())
+}
+
+fn shuffle(interpreter: &mut Interpreter, target: &mut Variable, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("shuffle", &args, 0)?;
+    let a = array_target("shuffle", target)?;
+    // fisher-yates
+    for i in (1..a.len()).rev() {
+        let j = interpreter.rng().below(i as u64 + 1) as usize;
+        a.swap(i, j);
+    }
+    Ok(Variable::Int(0))
+}
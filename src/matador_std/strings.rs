@@ -0,0 +1,248 @@
+use crate::debug::ScriptError;
+use crate::function::NativeFunction;
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::Variable;
+use super::{native_error, type_error, check_args, string_arg, int_arg, pattern_arg};
+
+// string functions, indexes are in chars, like string indexing
+pub(crate) struct Strings;
+
+impl NativeModule for Strings {
+    fn name(&self) -> &str {
+        "strings"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("len", len),
+            ("upper", upper),
+            ("lower", lower),
+            ("trim", trim),
+            ("split", split),
+            ("join", join),
+            ("replace", replace),
+            ("contains", contains),
+            ("starts_with", starts_with),
+            ("ends_with", ends_with),
+            ("find", find),
+            ("substring", substring),
+            ("repeat", repeat),
+            ("reverse", reverse),
+            ("chars", chars),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("len", 1),
+            ("upper", 1),
+            ("lower", 1),
+            ("trim", 1),
+            ("split", 2),
+            ("join", 2),
+            ("replace", 3),
+            ("contains", 2),
+            ("starts_with", 2),
+            ("ends_with", 2),
+            ("find", 2),
+            ("substring", 3),
+            ("repeat", 2),
+            ("reverse", 1),
+            ("chars", 1),
+        ]
+    }
+}
+
+fn len(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("len", &args, 1)?;
+    // strings, arrays and maps
+    args[0].sizeof().ok_or_else(|| type_error("len", &args[0]))
+}
+
+fn upper(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("upper", &args, 1)?;
+    Ok(Variable::String(string_arg("upper", &args, 0)?.to_uppercase().into()))
+}
+
+fn lower(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("lower", &args, 1)?;
+    Ok(Variable::String(string_arg("lower", &args, 0)?.to_lowercase().into()))
+}
+
+fn trim(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("trim", &args, 1)?;
+    Ok(Variable::String(string_arg("trim", &args, 0)?.trim().into()))
+}
+
+fn split(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("split", &args, 2)?;
+    let s = string_arg("split", &args, 0)?;
+    let sep = pattern_arg("split", &args, 1)?;
+    // an empty separator splits the string into its chars
+    let parts: Vec<String> = if sep.is_empty() {
+        s.chars().map(|c| c.to_string()).collect()
+    } else {
+        s.split(sep.as_str()).map(|p| p.to_string()).collect()
+    };
+    Ok(Variable::Array(parts.into_iter().map(|p| Box::new(Variable::String(p.into()))).collect()))
+}
+
+fn join(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("join", &args, 2)?;
+    let sep = pattern_arg("join", &args, 1)?;
+    Ok(match &args[0] {
+        Variable::Array(a) => {
+            let parts: Vec<String> = a.iter().map(|v| format!("{}", v)).collect();
+            Variable::String(parts.join(&sep).into())
+        }
+        v => return Err(type_error("join", v)),
+    })
+}
+
+fn replace(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("replace", &args, 3)?;
+    let s = string_arg("replace", &args, 0)?;
+    let from = pattern_arg("replace", &args, 1)?;
+    let to = pattern_arg("replace", &args, 2)?;
+    Ok(Variable::String(s.replace(&from, &to).into()))
+}
+
+fn contains(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("contains", &args, 2)?;
+    if let Variable::Array(a) = &args[0] {
+        return Ok(Variable::Bool(a.iter().any(|v| v.equals(&args[1]))));
+    }
+    let s = string_arg("contains", &args, 0)?;
+    Ok(Variable::Bool(s.contains(&pattern_arg("contains", &args, 1)?)))
+}
+
+fn starts_with(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("starts_with", &args, 2)?;
+    let s = string_arg("starts_with", &args, 0)?;
+    Ok(Variable::Bool(s.starts_with(&pattern_arg("starts_with", &args, 1)?)))
+}
+
+fn ends_with(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("ends_with", &args, 2)?;
+    let s = string_arg("ends_with", &args, 0)?;
+    Ok(Variable::Bool(s.ends_with(&pattern_arg("ends_with", &args, 1)?)))
+}
+
+// the index of the first occurrence, or -1 if there is none
+fn find(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("find", &args, 2)?;
+    let s = string_arg("find", &args, 0)?;
+    Ok(match s.find(&pattern_arg("find", &args, 1)?) {
+        Some(byte) => Variable::Int(s[..byte].chars().count() as i64),
+        None => Variable::Int(-1),
+    })
+}
+
+// the chars from start up to but not including end, like slicing with a range
+fn substring(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("substring", &args, 3)?;
+    string_arg("substring", &args, 0)?;
+    let start = int_arg("substring", &args, 1)?;
+    let end = int_arg("substring", &args, 2)?;
+    args[0].access(Variable::Range(start, end))?.ok_or_else(|| type_error("substring", &args[0]))
+}
+
+fn repeat(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("repeat", &args, 2)?;
+    let s = string_arg("repeat", &args, 0)?;
+    let n = int_arg("repeat", &args, 1)?;
+    if n < 0 {
+        return Err(native_error("repeat", format!("negative count {}", n)));
+    }
+    Ok(Variable::String(s.repeat(n as usize).into()))
+}
+
+fn reverse(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("reverse", &args, 1)?;
+    if let Variable::Array(a) = &args[0] {
+        return Ok(Variable::Array(a.iter().rev().cloned().collect()));
+    }
+    Ok(Variable::String(string_arg("reverse", &args, 0)?.chars().rev().collect::<String>().into()))
+}
+
+fn chars(_: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("chars", &args, 1)?;
+    string_arg("chars", &args, 0)?;
+    Ok(args[0].to_array().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::ScriptError;
+    use crate::function::NativeFunction;
+    use crate::interpreter::Interpreter;
+    use crate::variable::Variable;
+    use super::*;
+
+    fn call(f: NativeFunction, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+        f(&mut Interpreter::new(), args)
+    }
+
+    fn s(s: &str) -> Variable {
+        Variable::String(s.into())
+    }
+
+    fn strings(parts: &[&str]) -> Variable {
+        Variable::Array(parts.iter().map(|p| Box::new(s(p))).collect())
+    }
+
+    #[test]
+    fn find_counts_chars() {
+        assert_eq!(call(find, vec![s("héllo wörld"), s("wö")]).unwrap(), Variable::Int(6));
+        assert_eq!(call(find, vec![s("😀😀x"), s("x")]).unwrap(), Variable::Int(2));
+        assert_eq!(call(find, vec![s("abc"), s("")]).unwrap(), Variable::Int(0));
+        assert_eq!(call(find, vec![s("abc"), s("d")]).unwrap(), Variable::Int(-1));
+        // a char is a pattern of one char
+        assert_eq!(call(find, vec![s("añb"), Variable::Char('b')]).unwrap(), Variable::Int(2));
+    }
+
+    #[test]
+    fn split_and_join() {
+        assert_eq!(call(split, vec![s("a,b,,c"), s(",")]).unwrap(), strings(&["a", "b", "", "c"]));
+        // an empty separator splits into chars
+        assert_eq!(call(split, vec![s("hé😀"), s("")]).unwrap(), strings(&["h", "é", "😀"]));
+        assert_eq!(call(split, vec![s(""), s("")]).unwrap(), strings(&[]));
+        assert_eq!(call(split, vec![s(""), s(",")]).unwrap(), strings(&[""]));
+        assert_eq!(call(join, vec![strings(&["a", "b"]), s(", ")]).unwrap(), s("a, b"));
+    }
+
+    #[test]
+    fn substring_and_repeat() {
+        assert_eq!(call(substring, vec![s("héllo"), Variable::Int(1), Variable::Int(3)]).unwrap(), s("él"));
+        assert_eq!(call(substring, vec![s("héllo"), Variable::Int(5), Variable::Int(5)]).unwrap(), s(""));
+        // out of range bounds are clamped to the string, like slicing
+        assert_eq!(call(substring, vec![s("héllo"), Variable::Int(2), Variable::Int(9)]).unwrap(), s("llo"));
+        assert_eq!(call(substring, vec![s("héllo"), Variable::Int(-1), Variable::Int(2)]).unwrap(), s("hé"));
+        assert_eq!(call(substring, vec![s("héllo"), Variable::Int(3), Variable::Int(1)]).unwrap(), s(""));
+        assert_eq!(call(substring, vec![s("héllo"), Variable::Int(7), Variable::Int(9)]).unwrap(), s(""));
+
+        assert_eq!(call(repeat, vec![s("é"), Variable::Int(3)]).unwrap(), s("ééé"));
+        assert_eq!(call(repeat, vec![s("ab"), Variable::Int(0)]).unwrap(), s(""));
+        let e = call(repeat, vec![s("ab"), Variable::Int(-1)]).unwrap_err();
+        assert!(e.message.contains("negative count -1"), "{}", e.message);
+    }
+
+    #[test]
+    fn type_errors() {
+        for (f, args) in [
+            (upper as NativeFunction, vec![Variable::Int(1)]),
+            (split, vec![s("a"), Variable::Int(1)]),
+            (join, vec![s("a"), s(",")]),
+            (find, vec![Variable::Bool(true), s("a")]),
+            (substring, vec![s("abc"), Variable::Float(1.0), Variable::Int(2)]),
+            (repeat, vec![s("a"), s("2")]),
+            (len, vec![Variable::Int(5)]),
+        ] {
+            let e = call(f, args).unwrap_err();
+            assert!(e.message.contains("invalid argument"), "{}", e.message);
+        }
+        let e = call(upper, vec![]).unwrap_err();
+        assert!(e.message.contains("expected 1 argument(s), found 0"), "{}", e.message);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::debug::ScriptError;
+use crate::function::NativeFunction;
+use crate::interpreter::Interpreter;
+use crate::module::NativeModule;
+use crate::variable::Variable;
+use super::{native_error, check_args, int_arg};
+
+// time functions, times are in milliseconds on the interpreter's clock, which the host can replace
+pub(crate) struct Time;
+
+impl NativeModule for Time {
+    fn name(&self) -> &str {
+        "time"
+    }
+
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)> {
+        vec![
+            ("now_ms", now_ms),
+            ("elapsed", elapsed),
+            ("sleep", sleep),
+        ]
+    }
+
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("now_ms", 0),
+            ("elapsed", 0),
+            ("sleep", 1),
+        ]
+    }
+}
+
+fn now_ms(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("now_ms", &args, 0)?;
+    Ok(Variable::Int(interpreter.clock().now_ms() as i64))
+}
+
+// time since the script started
+fn elapsed(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("elapsed", &args, 0)?;
+    let start = interpreter.start_ms();
+    Ok(Variable::Int(interpreter.clock().now_ms().saturating_sub(start) as i64))
+}
+
+// returns how long it actually slept
+fn sleep(interpreter: &mut Interpreter, args: Vec<Variable>) -> Result<Variable, ScriptError> {
+    check_args("sleep", &args, 1)?;
+    let ms = int_arg("sleep", &args, 0)?;
+    if ms < 0 {
+        return Err(native_error("sleep", format!("negative time {}", ms)));
+    }
+    Ok(Variable::Int(interpreter.clock().sleep(ms as u64) as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::Clock;
+    use crate::debug::ScriptError;
+    use crate::interpreter::Interpreter;
+    use crate::lexer::Lexer;
+    use crate::matador_std;
+    use crate::node::Node;
+    use crate::parser::Parser;
+    use crate::variable::Variable;
+
+    // a clock that only moves when the script sleeps
+    struct StubClock(u64);
+
+    impl Clock for StubClock {
+        fn now_ms(&self) -> u64 {
+            self.0
+        }
+
+        fn sleep(&mut self, ms: u64) -> u64 {
+            self.0 += ms;
+            ms
+        }
+    }
+
+    // the value of the last statement
+    fn run(code: &str) -> Result<Variable, ScriptError> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_clock(Box::new(StubClock(1_000)));
+        matador_std::attach_std(&mut interpreter);
+        let mut nodes = Parser::new(Lexer::new(code).lex()?).parse()?;
+        nodes.retain(|node| *node != Node::EOF);
+        Ok(interpreter.try_interpret(nodes)?.pop().unwrap())
+    }
+
+    #[test]
+    fn time_follows_the_clock() {
+        let code = "let start = now_ms()\nlet slept = sleep(250)\ntime.sleep(50)\n[start, slept, elapsed(), now_ms()]";
+        assert_eq!(format!("{}", run(code).unwrap()), "[1000, 250, 300, 1300]");
+        assert_eq!(run("elapsed()").unwrap(), Variable::Int(0));
+        assert!(run("sleep(-1)").unwrap_err().message.starts_with("Error in sleep: negative time -1"));
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+use crate::function::{InPlaceFunction, NativeFunction};
+use crate::variable::Variable;
+
+/// Finds and loads the source of imported modules, a host can implement this to serve
+/// modules from an asset pack instead of the filesystem
+pub trait ModuleResolver {
+    /// Turns the path of an import into a key that is unique for each module, imports that
+    /// resolve to the same key share one module. `from` is the key of the importing module,
+    /// or None when importing from the main program
+    fn resolve(&self, path: &str, from: Option<&str>) -> Result<String, String>;
+
+    /// Loads the source code of a resolved module
+    fn load(&self, key: &str) -> Result<String, String>;
+}
+
+/// Loads modules from files, relative to the importing module or to the root directory
+/// for the main program. `.mtdr` is added to paths without an extension
+#[derive(Debug, Clone)]
+pub struct FileResolver {
+    root: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FileResolver { root: root.into() }
+    }
+}
+
+impl Default for FileResolver {
+    fn default() -> Self {
+        FileResolver::new(".")
+    }
+}
+
+impl ModuleResolver for FileResolver {
+    fn resolve(&self, path: &str, from: Option<&str>) -> Result<String, String> {
+        let dir = match from {
+            Some(key) => Path::new(key).parent().unwrap_or(Path::new(".")),
+            None => self.root.as_path(),
+        };
+        let mut file = dir.join(path);
+        if file.extension().is_none() {
+            file.set_extension("mtdr");
+        }
+        // the canonical path, so different paths to the same file share a module
+        file.canonicalize()
+            .map(|p| p.to_string_lossy().into_owned())
+            .map_err(|e| format!("{}: {}", file.display(), e))
+    }
+
+    fn load(&self, key: &str) -> Result<String, String> {
+        std::fs::read_to_string(key).map_err(|e| format!("{}: {}", key, e))
+    }
+}
+
+/// A library of native functions and constants, registered under a namespace so scripts use
+/// its members as `name.member`, i.e. `math.sqrt(2)` or `math.pi`
+pub trait NativeModule {
+    /// The namespace of the module
+    fn name(&self) -> &str;
+
+    /// The functions of the module
+    fn functions(&self) -> Vec<(&'static str, NativeFunction)>;
+
+    /// The functions of the module that change their first argument
+    fn in_place_functions(&self) -> Vec<(&'static str, InPlaceFunction)> {
+        Vec::new()
+    }
+
+    /// The number of arguments of the functions that always take the same number, in-place
+    /// functions count their first argument. Tools like the linter check calls against it
+    fn arities(&self) -> Vec<(&'static str, usize)> {
+        Vec::new()
+    }
+
+    /// The constants of the module
+    fn constants(&self) -> Vec<(&'static str, Variable)> {
+        Vec::new()
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::Display;
 use crate::variable::{Variable, VariableType};
-use crate::postfix::ShuntedStack;
+use crate::postfix::{ShuntedStack, ShuntedStackItem};
+use crate::format::FormatSpec;
 
 // AST Nodes
 #[derive(Debug, PartialEq, Clone)]
@@ -17,12 +18,13 @@ pub(crate) enum Node {
     VarDecl(String, Option<Box<Node>>),
     VarAssign(String, Box<Node>),
 
+    // interpolated strings, the parts are joined into one string
+    Concat(Vec<Node>),
+    Format(Box<Node>, FormatSpec),
+
     // array
     Array(Vec<Box<Node>>),
 
-    // map
-    Map(Vec<(Box<Node>, Box<Variable>)>),
-
     ArrayMapAccess(String, Box<Node>),
     ArrayMapAssign(String, Box<Node>, Box<Node>),
 
@@ -46,10 +48,39 @@ pub(crate) enum Node {
     Sizeof(Box<Node>),
     Drop(String),
     Exit,
+    // import path as name
+    Import(String, String),
     EOF,
 }
 
 impl Node {
+    // the nodes directly inside this one, in source order
+    pub(crate) fn children(&self) -> Vec<&Node> {
+        match self {
+            Node::Block(nodes) | Node::Concat(nodes) => nodes.iter().collect(),
+            Node::ShuntedStack(stack) => stack.items().iter().filter_map(|item| match item {
+                ShuntedStackItem::Operand(node) => Some(node),
+                _ => None,
+            }).collect(),
+            Node::VarDecl(_, value) | Node::Return(value) => value.iter().map(|v| v.as_ref()).collect(),
+            Node::VarAssign(_, node) | Node::Format(node, _) | Node::TypeCast(node, _)
+            | Node::TypeCheck(node, _) | Node::Sizeof(node) | Node::Loop(node)
+            | Node::FunctionDecl(_, _, node) | Node::ArrayMapAccess(_, node) => vec![node],
+            Node::Array(nodes) | Node::FunctionCall(_, nodes) => nodes.iter().map(|n| n.as_ref()).collect(),
+            Node::ArrayMapAssign(_, index, value) => vec![index, value],
+            Node::While(cond, body) => vec![cond, body],
+            Node::If(cond, then, els) => {
+                let mut children = vec![cond.as_ref()];
+                children.extend(then.iter().map(|n| n.as_ref()));
+                children.extend(els.iter().map(|n| n.as_ref()));
+                children
+            }
+            Node::Variable(_) | Node::Ident(_) | Node::Continue | Node::Break | Node::Expression
+            | Node::Negative | Node::Not | Node::Drop(_) | Node::Exit | Node::Import(_, _)
+            | Node::EOF => Vec::new(),
+        }
+    }
+
     fn to_display_string(&self, depth: &u32) -> String {
         let spaces = "  ".repeat(*depth as usize);
         format!("{}{}", spaces, match self {
@@ -63,7 +94,7 @@ impl Node {
                 let mut s = "Block:\n".to_string();
                 for node in nodes {
                     s.push_str(&node.to_display_string(&(*depth + 1)));
-                    s.push_str("\n");
+                    s.push('\n');
                 }
                 s
             }
@@ -71,7 +102,7 @@ impl Node {
                 let mut s = format!("FunctionDecl: {}(", ident);
                 for arg in args {
                     s.push_str(&arg.to_string());
-                    s.push_str(",");
+                    s.push(',');
                 }
                 s.push_str(")\n");
                 s.push_str(&block.to_display_string(&(*depth + 1)));
@@ -81,7 +112,7 @@ impl Node {
                 let mut s = format!("FunctionCall: {}\n", ident);
                 for arg in args {
                     s.push_str(&arg.to_string());
-                    s.push_str("\n");
+                    s.push('\n');
                 }
                 s
             }
@@ -89,12 +120,12 @@ impl Node {
                 let mut s = format!("{}If: {}\n",depth, cond);
                 if let Some(then) = then {
                     s.push_str(&then.to_display_string(&(*depth + 1)));
-                    s.push_str("\n");
+                    s.push('\n');
                 }
                 if let Some(els) = els {
                     s.push_str(format!("{}{}Else:\n", spaces, depth).as_str());
                     s.push_str(&els.to_display_string(&(*depth + 1)));
-                    s.push_str("\n");
+                    s.push('\n');
                 }
                 s
             }
@@ -121,32 +152,32 @@ impl Node {
                 }
             }
             Node::VarAssign(ident, value) => { format!("VarAssign: {} to {}", ident, value) }
+            Node::Concat(nodes) => {
+                let mut s = "Concat:\n".to_string();
+                for node in nodes {
+                    s.push_str(&node.to_display_string(&(*depth + 1)));
+                    s.push('\n');
+                }
+                s
+            }
+            Node::Format(node, spec) => { format!("Format: {} with {:?}", node, spec) }
             Node::Array(nodes) => {
                 let mut s = "Array:\n".to_string();
                 for node in nodes {
                     s.push_str(&node.to_display_string(&(*depth + 1)));
-                    s.push_str("\n");
+                    s.push('\n');
                 }
                 s
             }
             Node::ArrayMapAccess(ident, index) => { format!("ArrayMapAccess: {} at {}", ident, index) }
             Node::ArrayMapAssign(ident, index, value) => { format!("ArrayMapAssign: {} at {} to {}", ident, index, value) }
-            Node::Map(nodes) => {
-                let mut s = "Map:\n".to_string();
-                for (key, value) in nodes {
-                    s.push_str(&key.to_display_string(&(*depth + 1)));
-                    s.push_str(&value.to_string());
-                    s.push_str("\n");
-                }
-                s
-            }
             Node::TypeCast(node, typ) => { format!("TypeCast: {} to {}", node, typ) }
             Node::TypeCheck(node, typ) => { format!("TypeCheck: {} is {}", node, typ) }
             Node::Sizeof(ident) => { format!("Sizeof: {}", ident) }
             Node::Drop(node) => { format!("Drop: {}", node) }
             Node::Exit => { "Exit".to_string() }
+            Node::Import(path, name) => { format!("Import: {} as {}", path, name) }
             Node::EOF => { "EOF".to_string() }
-            _ => { "???".to_string() }
         })
     }
 
@@ -231,6 +262,14 @@ impl Display for Node {
                 }
             }
             Node::VarAssign(ident, value) => write!(f, "ASSIGN '{}' TO {}", ident, value),
+            Node::Concat(nodes) => {
+                write!(f, "CONCAT(")?;
+                for node in nodes {
+                    write!(f, "{} ", node)?;
+                }
+                write!(f, ")")
+            }
+            Node::Format(node, spec) => write!(f, "FORMAT {} WITH {:?}", node, spec),
             Node::Array(nodes) => {
                 write!(f, "ARRAY[")?;
                 for node in nodes {
@@ -240,18 +279,12 @@ impl Display for Node {
             }
             Node::ArrayMapAccess(ident, index) => write!(f, "ACCESS ARRAY/MAP '{}' AT {}", ident, index),
             Node::ArrayMapAssign(ident, index, value) => write!(f, "ASSIGN ARRAY/MAP '{}' AT {} TO {}", ident, index, value),
-            Node::Map(nodes) => {
-                write!(f, "MAP[")?;
-                for (key, value) in nodes {
-                    write!(f, "{}: {} ", key, value)?;
-                }
-                write!(f, "]")
-            }
             Node::TypeCast(node, typ) => write!(f, "CAST {} TO {}", node, typ),
             Node::TypeCheck(node, typ) => write!(f, "CHECK {} IS {}", node, typ),
             Node::Sizeof(ident) => write!(f, "SIZEOF {}", ident),
             Node::Drop(node) => write!(f, "DROP {}", node),
             Node::Exit => write!(f, "EXIT"),
+            Node::Import(path, name) => write!(f, "IMPORT {:?} AS {}", path, name),
             Node::EOF => write!(f, "EOF"),
         }
     }
@@ -1,5 +1,6 @@
 use std::fmt::Display;
-use crate::variable::Variable;
+use crate::debug::ScriptError;
+use crate::variable::{Overflow, Variable};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) enum Operator {
@@ -12,6 +13,7 @@ pub(crate) enum Operator {
     Pow,    // **
     Inc,    // ++
     Dec,    // --
+    Neg,    // - (unary, decided by the parser)
 
     // misc
     Range,   // ..
@@ -42,40 +44,39 @@ impl Operator {
         match self {
             Operator::And | Operator::Or | Operator::Not => Some(0),
             Operator::Eq | Operator::Neq | Operator::Gt | Operator::Lt |
-            Operator::Gte | Operator::Lte => Some(1),
+            Operator::Gte | Operator::Lte | Operator::Range => Some(1),
             Operator::Inc | Operator::Dec => Some(2),
             Operator::Plus | Operator::Minus => Some(3),
             Operator::Mul | Operator::Div | Operator::Mod => Some(4),
             Operator::BitAnd | Operator::BitOr | Operator::Xor
             | Operator::LShift | Operator::RShift => Some(5),
-            Operator::Pow => Some(6),
+            Operator::Neg => Some(6),
+            Operator::Pow => Some(7),
             _ => None,
         }
     }
 
     pub(crate) fn can_apply(&self) -> bool {
-        match self {
-            Operator::Range  => false,
-            _ => true,
-        }
+        !matches!(self, Operator::LParen | Operator::RParen)
     }
 
-    pub(crate) fn apply_binary(&self, left: Variable, right: Variable) -> Option<Variable> {
-        match self {
+    pub(crate) fn apply_binary(&self, left: Variable, right: Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
             // standard
-            Operator::Plus => left.add(&right),
-            Operator::Minus => left.sub(&right),
-            Operator::Mul => left.mul(&right),
-            Operator::Div => left.div(&right),
-            Operator::Mod => left.rem(&right),
-            Operator::Pow => left.pow(&right),
+            Operator::Plus => left.add(&right, overflow)?,
+            Operator::Minus => left.sub(&right, overflow)?,
+            Operator::Mul => left.mul(&right, overflow)?,
+            Operator::Div => left.div(&right, overflow)?,
+            Operator::Mod => left.rem(&right, overflow)?,
+            Operator::Pow => left.pow(&right, overflow)?,
+            Operator::Range => left.range(&right),
 
             // bitwise
             Operator::BitAnd => left.bitand(&right),
             Operator::BitOr => left.bitor(&right),
             Operator::Xor => left.xor(&right),
-            Operator::LShift => left.shl(&right),
-            Operator::RShift => left.shr(&right),
+            Operator::LShift => left.shl(&right, overflow)?,
+            Operator::RShift => left.shr(&right, overflow)?,
 
             // comparison
             Operator::Eq => left.eq(&right),
@@ -89,24 +90,21 @@ impl Operator {
             Operator::Or => left.or(&right),
 
             _ => None,
-        }
+        })
     }
 
     pub(crate) fn is_unary(&self) -> bool {
-        match self {
-            Operator::Inc | Operator::Dec | Operator::Minus | Operator::Not => true,
-            _ => false,
-        }
+        matches!(self, Operator::Inc | Operator::Dec | Operator::Minus | Operator::Neg | Operator::Not)
     }
 
-    pub(crate) fn apply_unary(&self, left: Variable) -> Option<Variable> {
-        match self {
-            Operator::Inc => left.inc(),
-            Operator::Dec => left.dec(),
-            Operator::Minus => left.neg(),
+    pub(crate) fn apply_unary(&self, left: Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
+            Operator::Inc => left.inc(overflow)?,
+            Operator::Dec => left.dec(overflow)?,
+            Operator::Minus | Operator::Neg => left.neg(overflow)?,
             Operator::Not => left.not(),
             _ => None,
-        }
+        })
     }
 }
 
@@ -121,6 +119,7 @@ impl Display for Operator {
             Operator::Pow => "**",
             Operator::Inc => "++",
             Operator::Dec => "--",
+            Operator::Neg => "-",
             Operator::LParen => "(",
             Operator::RParen => ")",
             Operator::Range => "..",
@@ -1,10 +1,12 @@
-use better_term::{Color, flush_styles};
-use crate::debug_print;
-use crate::lexer::{Token, TokenType};
+use better_term::Color;
+use crate::debug::{script_error, ScriptError};
+use crate::logging::log;
+use crate::lexer::{Lexer, StringPart, Token, TokenType};
 use crate::operator::Operator;
 use crate::variable::Variable;
 use crate::node::Node;
 use crate::postfix::{ShuntedStack, ShuntedStackItem};
+use crate::sexpr::ToSexpr;
 
 // Parser
 pub struct Parser {
@@ -17,89 +19,121 @@ impl Parser {
         Parser { tokens, pos: 0 }
     }
 
-    pub fn parse(&mut self) -> Vec<Node> {
+    pub fn parse(&mut self) -> Result<Vec<Node>, ScriptError> {
         let mut nodes = Vec::new();
         while self.pos < self.tokens.len() {
-            nodes.push(self.next());
-            debug_print!("{}Parsed: {}", Color::BrightGreen, nodes.last().unwrap());
-            flush_styles()
+            nodes.push(self.next()?);
+            log!(Parser, Debug, "parsed {}", nodes.last().unwrap().to_sexpr());
         }
-        nodes
+        Ok(nodes)
     }
 
-    fn next(&mut self) -> Node {
-        let token = &self.tokens[self.pos];
+    fn next(&mut self) -> Result<Node, ScriptError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(self.unexpected_eof());
+        };
         self.pos += 1;
-        match &token.token_type {
+        Ok(match &token.token_type {
             TokenType::LBrace => { // {
-                let block = self.parse_block();
+                let block = self.parse_block()?;
                 self.pos += 1;
                 // if there is a trailing operator, treat as an operand in the shunting yard
                 if self.should_shunt_from_lit() {
-                    self.shunting_yard(block)
+                    self.shunting_yard(block)?
                 } else {
                     block
                 }
             }
 
             TokenType::Let => {
-                let ident = self.consume_ident();
+                let ident = self.consume_ident()?;
                 if self.peek().token_type == TokenType::Assign {
                     self.pos += 1;
-                    let expr = self.next();
+                    let expr = self.next()?;
                     Node::VarDecl(ident, Some(Box::new(expr)))
                 } else {
                     Node::VarDecl(ident, None)
                 }
             }
             TokenType::Sizeof => {
-                let node = self.next();
+                let node = self.next()?;
                 Node::Sizeof(Box::new(node))
             }
             TokenType::Drop => {
-                let ident = self.consume_ident();
+                let ident = self.consume_ident()?;
                 Node::Drop(ident)
             }
             TokenType::Exit => {
                 Node::Exit
             }
+            TokenType::Import => {
+                // import "path/to/lib.mtdr" or import lib, the name defaults to the file name
+                let (path, name) = match self.peek().token_type.clone() {
+                    TokenType::String(path) => {
+                        let name = module_name(&path);
+                        (path, name)
+                    }
+                    TokenType::Ident(name) => (name.clone(), Some(name)),
+                    _ => {
+                        return Err(script_error!(at self.peek().pos, "{}Invalid import, expected a path or a name, found: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos));
+                    }
+                };
+                self.pos += 1;
+                let name = if self.peek().token_type == TokenType::As {
+                    self.pos += 1;
+                    Some(self.consume_ident()?)
+                } else {
+                    name
+                };
+                let Some(name) = name else {
+                    return Err(script_error!("{}Import needs a name (import \"...\" as name): {}{:?}", Color::BrightRed, Color::Red, path));
+                };
+                Node::Import(path, name)
+            }
             TokenType::Ident(ident) => {
-                let node = self.parse_ident(ident.clone());
+                let node = self.parse_ident(ident.clone())?;
                 // if there is a trailing operator, treat as an operand in the shunting yard
                 if self.should_shunt_from_lit() {
-                    self.shunting_yard(node)
+                    self.shunting_yard(node)?
+                } else {
+                    node
+                }
+            },
+            // type names double as the names of std functions, e.g. `map(a, f)` or `range(10)`
+            TokenType::VariableType(typ) if self.peek().token_type == TokenType::Op(Operator::LParen) => {
+                let node = self.parse_ident(typ.to_string())?;
+                if self.should_shunt_from_lit() {
+                    self.shunting_yard(node)?
                 } else {
                     node
                 }
             },
 
             TokenType::Fn => {
-                let ident = self.consume_ident();
+                let ident = self.consume_ident()?;
                 // consume left paren
                 if self.peek().token_type != TokenType::Op(Operator::LParen) {
-                    println!("{}Missing Left Parenthesis ('('), found: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos);
-                    flush_styles();
-                    std::process::exit(0);
+                    return Err(script_error!(at self.peek().pos, "{}Missing Left Parenthesis ('('), found: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos));
                 }
                 self.pos += 1; // consume left paren
 
                 // get the parameter ident list
-                let params = self.parse_ident_params(TokenType::Op(Operator::RParen));
+                let params = self.parse_ident_params(TokenType::Op(Operator::RParen))?;
 
-                let block = Box::new(self.next());
+                let block = Box::new(self.next()?);
 
                 Node::FunctionDecl(ident, params, block)
             }
 
             TokenType::If => {
                 // get the condition
-                let cond = self.next();
+                let cond = self.next()?;
                 // get the then block
-                let then = Some(Box::new(self.next()));
+                let then = Some(Box::new(self.next()?));
                 // get the else block if it exists
                 let els = if self.peek().token_type == TokenType::Else {
                     self.pos += 1; // skip the else token
-                    Some(Box::new(self.next()))
+                    Some(Box::new(self.next()?))
                 } else {
                     None
                 };
@@ -108,18 +142,18 @@ impl Parser {
 
             TokenType::While => {
                 // get the condition
-                let cond = Box::new(self.next());
+                let cond = Box::new(self.next()?);
                 // get the block
-                let block = Box::new(self.next());
+                let block = Box::new(self.next()?);
                 Node::While(cond, block)
             }
             TokenType::Loop => {
                 // get the block
-                let block = Box::new(self.next());
+                let block = Box::new(self.next()?);
                 Node::Loop(block)
             }
             TokenType::For => {
-                todo!()
+                return Err(script_error!(at token.pos, "{}For loops are not supported yet @ {:?}", Color::BrightRed, token.pos));
             }
             TokenType::Break => {
                 Node::Break
@@ -128,16 +162,16 @@ impl Parser {
                 Node::Continue
             }
             TokenType::Return => {
-                let expr = self.next();
+                let expr = self.next()?;
                 Node::Return(Some(Box::new(expr)))
             }
 
             // arrays and maps
             TokenType::LBracket => {
                 // array = [1, 2, 3]
-                let arr = self.parse_array();
+                let arr = self.parse_array()?;
                 if self.should_shunt_from_lit() {
-                    self.shunting_yard(arr)
+                    self.shunting_yard(arr)?
                 } else {
                     arr
                 }
@@ -145,85 +179,96 @@ impl Parser {
 
             TokenType::Op(Operator::Minus) => {
                 // negative sign, get the next token
-                self.shunting_yard(Node::Negative)
+                self.shunting_yard(Node::Negative)?
             },
 
             TokenType::Op(Operator::LParen) => {
-                self.shunting_yard(Node::Expression)
+                self.shunting_yard(Node::Expression)?
             }
 
             TokenType::Op(Operator::Not) => {
-                self.shunting_yard(Node::Not)
+                self.shunting_yard(Node::Not)?
             }
 
-            // todo: this does not support `<literal> as <type>` statements
-            TokenType::Int(n) => self.shunting_yard(Node::Variable(Variable::Int(*n))),
-            TokenType::Float(n) => self.shunting_yard(Node::Variable(Variable::Float(*n))),
-            TokenType::String(s) => self.shunting_yard(Node::Variable(Variable::String(s.clone()))),
-            TokenType::Bool(b) => self.shunting_yard(Node::Variable(Variable::Bool(*b))),
+            TokenType::Int(i64::MIN) => return Err(int_out_of_range(token)),
+            TokenType::Int(n) => self.shunting_yard(Node::Variable(Variable::Int(*n)))?,
+            TokenType::Float(n) => self.shunting_yard(Node::Variable(Variable::Float(*n)))?,
+            TokenType::String(s) => self.shunting_yard(Node::Variable(Variable::String(s.as_str().into())))?,
+            TokenType::Char(c) => self.shunting_yard(Node::Variable(Variable::Char(*c)))?,
+            TokenType::InterpolatedString(parts) => {
+                let node = self.parse_interpolation(parts)?;
+                self.shunting_yard(node)?
+            }
+            TokenType::Bool(b) => self.shunting_yard(Node::Variable(Variable::Bool(*b)))?,
 
             TokenType::EOF => Node::EOF,
             _ => {
-                // invalid token, dump info and exit
-                println!("{}Invalid token: {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos);
-                flush_styles();
-                std::process::exit(0);
+                // invalid token
+                return Err(script_error!(at token.pos, "{}Invalid token: {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos));
             }
-        }
+        })
     }
 
     pub(crate) fn should_shunt_from_lit(&self) -> bool {
         if self.pos < self.tokens.len() {
-            match self.peek().token_type {
-                TokenType::Op(_) => true,
-                TokenType::RBrace => true,
-                _ => false,
-            }
+            matches!(self.peek().token_type, TokenType::Op(_) | TokenType::RBrace | TokenType::As | TokenType::Is)
         } else {
             false
         }
     }
 
-    pub(crate) fn parse_block(&mut self) -> Node {
+    pub(crate) fn parse_block(&mut self) -> Result<Node, ScriptError> {
         let mut nodes = Vec::new();
         while self.peek().token_type != TokenType::RBrace {
-            nodes.push(self.next());
+            if self.peek().token_type == TokenType::EOF {
+                return Err(missing("Right Brace ('}')", self.peek()));
+            }
+            nodes.push(self.next()?);
         }
-        Node::Block(nodes)
+        Ok(Node::Block(nodes))
     }
 
-    pub(crate) fn parse_ident(&mut self, ident: String) -> Node {
-        let token = &self.tokens[self.pos];
-        let ident = ident.clone();
+    pub(crate) fn parse_ident(&mut self, ident: String) -> Result<Node, ScriptError> {
+        let mut ident = ident.clone();
         if self.pos >= self.tokens.len() {
-            return Node::Ident(ident);
+            return Ok(Node::Ident(ident));
         }
-        match self.peek().token_type {
+        let token = &self.tokens[self.pos];
+        // qualified names of module members, i.e. `lib.func`
+        while self.peek().token_type == TokenType::Decimal {
+            let member = match self.tokens.get(self.pos + 1).map(|t| &t.token_type) {
+                Some(TokenType::Ident(member)) => member.clone(),
+                // members may be named after types, i.e. `arrays.map`
+                Some(TokenType::VariableType(typ)) => typ.to_string(),
+                _ => break,
+            };
+            ident = format!("{}.{}", ident, member);
+            self.pos += 2;
+        }
+        Ok(match self.peek().token_type {
             TokenType::Assign => {
                 self.pos += 1;
-                let expr = self.next();
+                let expr = self.next()?;
                 Node::VarAssign(ident, Box::new(expr))
             }
             // array / map access and assignment
             TokenType::LBracket => { // [
                 self.pos += 1;
-                let index = self.next();
+                let index = self.next()?;
                 // check for closing bracket
                 if self.peek().token_type != TokenType::RBracket {
-                    println!("{}Missing Right Bracket (']'), found: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos);
-                    flush_styles();
-                    std::process::exit(0);
+                    return Err(script_error!(at self.peek().pos, "{}Missing Right Bracket (']'), found: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos));
                 } else {
                     self.pos += 1;
                 }
                 if self.pos >= self.tokens.len() {
-                    return Node::ArrayMapAccess(ident, Box::new(index));
+                    return Ok(Node::ArrayMapAccess(ident, Box::new(index)));
                 }
                 // handle assignment or access
                 match self.peek().clone().token_type {
                     TokenType::Assign => {
                         self.pos += 1;
-                        let expr = self.next();
+                        let expr = self.next()?;
                         Node::ArrayMapAssign(ident, Box::new(index), Box::new(expr))
                     }
                     _ => Node::ArrayMapAccess(ident, Box::new(index))
@@ -231,15 +276,15 @@ impl Parser {
             }
             TokenType::Op(Operator::LParen) => { // (
                 self.pos += 1;
-                let params = self.parse_params(TokenType::Op(Operator::RParen));
+                let params = self.parse_params(TokenType::Op(Operator::RParen))?;
                 // peak future for As, Is, LBracket and In
                 if self.pos >= self.tokens.len() {
-                    return Node::FunctionCall(ident, params);
+                    return Ok(Node::FunctionCall(ident, params));
                 }
                 Node::FunctionCall(ident, params)
             }
             TokenType::In => {
-                todo!()
+                return Err(script_error!(at self.peek().pos, "{}In is not supported yet @ {:?}", Color::BrightRed, self.peek().pos));
             }
             TokenType::As => {
                 self.pos += 1;
@@ -249,10 +294,8 @@ impl Parser {
                         Node::TypeCast(Box::new(Node::Ident(ident)), typ.clone())
                     }
                     _ => {
-                        // invalid token, dump info and exit
-                        println!("{}Invalid token (as): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos);
-                        flush_styles();
-                        std::process::exit(0);
+                        // invalid token
+                        return Err(script_error!(at token.pos, "{}Invalid token (as): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos));
                     }
                 }
             }
@@ -264,67 +307,86 @@ impl Parser {
                         Node::TypeCheck(Box::new(Node::Ident(ident)), typ.clone())
                     }
                     _ => {
-                        // invalid token, dump info and exit
-                        println!("{}Invalid token (is): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos);
-                        flush_styles();
-                        std::process::exit(0);
+                        // invalid token
+                        return Err(script_error!(at token.pos, "{}Invalid token (is): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos));
                     }
                 }
             }
             _ => Node::Ident(ident)
+        })
+    }
+
+    // parses the embedded expressions of an interpolated string into a concatenation
+    fn parse_interpolation(&self, parts: &[StringPart]) -> Result<Node, ScriptError> {
+        let mut nodes = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Literal(s) => nodes.push(Node::Variable(Variable::String(s.as_str().into()))),
+                StringPart::Expr(source, pos, spec) => {
+                    let tokens = Lexer::new_at(source, *pos).lex()?;
+                    let mut parser = Parser::new(tokens);
+                    let expr = parser.next()?;
+                    if parser.peek().token_type != TokenType::EOF {
+                        return Err(script_error!(at *pos, "{}Invalid interpolated expression: {}{:?} @ {:?}", Color::BrightRed, Color::Red, source, pos));
+                    }
+                    nodes.push(match spec {
+                        Some(spec) => Node::Format(Box::new(expr), spec.clone()),
+                        None => expr,
+                    });
+                }
+            }
         }
+        Ok(Node::Concat(nodes))
     }
 
-    fn parse_array(&mut self) -> Node {
-        match self.peek().token_type {
+    fn parse_array(&mut self) -> Result<Node, ScriptError> {
+        Ok(match self.peek().token_type {
             TokenType::RBracket => {
                 self.pos += 1;
                 Node::Variable(Variable::Array(Vec::new()))
             }
             _ => {
                 // array with elements
-                let elements = self.parse_params(TokenType::RBracket);
+                let elements = self.parse_params(TokenType::RBracket)?;
                 Node::Array(elements)
             }
-        }
+        })
     }
 
-    fn consume_ident(&mut self) -> String {
-        let token = &self.tokens[self.pos];
+    fn consume_ident(&mut self) -> Result<String, ScriptError> {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Err(self.unexpected_eof());
+        };
         self.pos += 1;
         match &token.token_type {
-            TokenType::Ident(ident) => ident.clone(),
+            TokenType::Ident(ident) => Ok(ident.clone()),
             _ => {
-                // invalid token, dump info and exit
-                println!("{}Invalid token (ci): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos);
-                flush_styles();
-                std::process::exit(0);
+                // invalid token
+                Err(script_error!(at token.pos, "{}Invalid token (ci): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos))
             }
         }
     }
 
-    fn parse_ident_params(&mut self, end: TokenType) -> Vec<String> {
+    fn parse_ident_params(&mut self, end: TokenType) -> Result<Vec<String>, ScriptError> {
         let mut params = Vec::new();
         // if the next token is the end token, then there are no parameters
         if self.peek().token_type == end {
             self.pos += 1;
-            return params;
+            return Ok(params);
         }
         loop {
-            params.push(self.consume_ident());
+            params.push(self.consume_ident()?);
             if self.peek().token_type == end {
                 self.pos += 1;
                 break;
             }
             if self.peek().token_type != TokenType::Comma {
-                // invalid token, dump info and exit
-                println!("{}Invalid parameter: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos);
-                flush_styles();
-                std::process::exit(0);
+                // invalid token
+                return Err(script_error!(at self.peek().pos, "{}Invalid parameter: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos));
             }
             self.pos += 1;
         }
-        params
+        Ok(params)
     }
 
     // parse parameters separated by commas
@@ -332,38 +394,48 @@ impl Parser {
     // 1. a, b, ...)
     // 2. a)
     // 3. )
-    fn parse_params(&mut self, end: TokenType) -> Vec<Box<Node>> {
+    fn parse_params(&mut self, end: TokenType) -> Result<Vec<Box<Node>>, ScriptError> {
         let mut params = Vec::new();
         // if the next token is the end token, then there are no parameters
         if self.peek().token_type == end {
             self.pos += 1;
-            return params;
+            return Ok(params);
         }
+        let what = match end {
+            TokenType::RBracket => "Right Bracket (']')",
+            _ => "Right Parenthesis (')')",
+        };
         loop {
-            params.push(Box::new(self.next()));
+            if self.peek().token_type == TokenType::EOF {
+                return Err(missing(what, self.peek()));
+            }
+            params.push(Box::new(self.next()?));
             if self.peek().token_type == end {
                 self.pos += 1;
                 break;
             }
+            if self.peek().token_type == TokenType::EOF {
+                return Err(missing(what, self.peek()));
+            }
             if self.peek().token_type != TokenType::Comma {
-                // invalid token, dump info and exit
-                println!("{}Invalid parameter: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos);
-                flush_styles();
-                std::process::exit(0);
+                // invalid token
+                return Err(script_error!(at self.peek().pos, "{}Invalid parameter: {}{:?} @ {:?}", Color::BrightRed, Color::Red, self.peek().token_type, self.peek().pos));
             }
             self.pos += 1;
         }
-        params
+        Ok(params)
     }
 
     // todo: trailing ++ or -- can cause issues
-    fn shunting_yard(&mut self, lhs: Node) -> Node {
+    fn shunting_yard(&mut self, lhs: Node) -> Result<Node, ScriptError> {
         let mut postfix = ShuntedStack::new();
         let mut op_stack = Vec::new();
 
-        let mut last_op: Option<Operator> = None;
-        let mut negative = false;
         let mut last_was_lit = false;
+        // where the parenthesized expressions that are open start in the postfix stack, and
+        // where the last one that was closed starts and ends
+        let mut groups = Vec::new();
+        let mut last_group = None;
 
         match lhs {
             Node::Variable(_) | Node::Ident(_) => {
@@ -371,15 +443,14 @@ impl Parser {
                 last_was_lit = true;
             }
             Node::Negative => {
-                negative = true;
+                op_stack.push(Operator::Neg);
             }
             Node::Not => {
                 op_stack.push(Operator::Not);
-                last_op = Some(Operator::Not);
             }
             Node::Expression => {
                 op_stack.push(Operator::LParen);
-                last_op = Some(Operator::LParen);
+                groups.push(0);
             }
             // add all others to the stack, assume parser knows best
             _ => {
@@ -396,18 +467,14 @@ impl Parser {
                     match op {
                         Operator::LParen => {
                             op_stack.push(op.clone());
+                            groups.push(postfix.items().len());
                             if last_was_lit {
                                 // error: missing operator
-                                println!("{}Invalid token (aslp): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos);
-                                flush_styles();
-                                std::process::exit(0);
+                                return Err(script_error!(at token.pos, "{}Invalid token (aslp): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos));
                             }
-                            last_op = None;
                             last_was_lit = false;
-                            negative = false;
                         }
                         Operator::RParen => {
-                            last_was_lit = false;
                             let mut found = false;
                             while let Some(op) = op_stack.pop() {
                                 if op == Operator::LParen {
@@ -417,39 +484,27 @@ impl Parser {
                                 postfix.push(ShuntedStackItem::Operator(op));
                             }
 
+                            last_group = groups.pop().map(|start| (start, postfix.items().len()));
                             if !found {
                                 // error: missing left parenthesis
                                 // println!("{}Invalid token (asrp): {}{:?} @ {:?}", Color::BrightRed, Color::Red, token.token_type, token.pos);
                                 // flush_styles();
-                                // std::process::exit(0);
+                                // exit_with_error();
                                 break; // this is to fix functions
                             }
 
-                            // last_op = Some(op.clone());
                             last_was_lit = true;
-                            last_op = None;
                         }
-                        Operator::Minus => {
-                            if last_op.is_some() {
-                                // if the last token was an operator, then this is a negative sign
-                                negative = true;
-                            } else {
-                                // if the last token was not an operator, then this is a subtraction sign
-                                op_stack.push(op.clone());
-                            }
-                            last_op = Some(op.clone());
-                            last_was_lit = false;
+                        Operator::Minus if !last_was_lit => {
+                            // a minus that doesn't follow an operand is a negative sign
+                            op_stack.push(Operator::Neg);
                         }
                         Operator::Not => {
                             op_stack.push(op.clone());
-                            last_op = Some(op.clone());
                             last_was_lit = false;
-                            negative = false;
                         }
                         _ => {
-                            last_op = Some(op.clone());
                             last_was_lit = false;
-                            negative = false;
                             while let Some(op2) = op_stack.pop() {
                                 if op2 == Operator::LParen {
                                     op_stack.push(op2);
@@ -461,19 +516,51 @@ impl Parser {
                                 }
                                 postfix.push(ShuntedStackItem::Operator(op2));
                             }
+                            // the left operand is complete, mark where && and || may skip the right one
+                            if matches!(op, Operator::And | Operator::Or) {
+                                postfix.push(ShuntedStackItem::ShortCircuit(op.clone(), 0));
+                            }
                             op_stack.push(op.clone());
                         }
                     }
                 }
+                // a cast or type check of the operand before it, i.e. `'a' as int` or `(a + b) as float`,
+                // it binds tighter than the binary operators
+                TokenType::As | TokenType::Is if last_was_lit => {
+                    let is_cast = token.token_type == TokenType::As;
+                    let Some(TokenType::VariableType(typ)) = self.tokens.get(self.pos + 1).map(|t| &t.token_type) else {
+                        return Err(script_error!(at token.pos, "{}Invalid token ({}): {}{:?} @ {:?}", Color::BrightRed, if is_cast { "as" } else { "is" }, Color::Red, token.token_type, token.pos));
+                    };
+                    let start = match last_group {
+                        Some((start, end)) if end == postfix.items().len() => start,
+                        _ => postfix.items().len() - 1,
+                    };
+                    // signs before the operand apply first, so `-1 as float` is -1.0
+                    while let Some(op) = op_stack.pop_if(|op| matches!(op, Operator::Neg | Operator::Not)) {
+                        postfix.push(ShuntedStackItem::Operator(op));
+                    }
+                    let operand = postfix.split_off(start);
+                    let operand = match operand.single_operand() {
+                        Some(node) => node.clone(),
+                        None => Node::ShuntedStack(operand),
+                    };
+                    let node = if is_cast {
+                        Node::TypeCast(Box::new(operand), typ.clone())
+                    } else {
+                        Node::TypeCheck(Box::new(operand), typ.clone())
+                    };
+                    postfix.push(ShuntedStackItem::Operand(node));
+                    // the loop moves past the type
+                    self.pos += 1;
+                }
                 TokenType::LBrace => { // {
                     // parse blocks
                     if last_was_lit {
                         break;
                     }
                     self.pos += 1;
-                    let block = self.parse_block();
+                    let block = self.parse_block()?;
                     postfix.push(ShuntedStackItem::Operand(block));
-                    last_op = None;
                     last_was_lit = true;
                 }
                 TokenType::Sizeof => {
@@ -481,18 +568,23 @@ impl Parser {
                         break;
                     }
                     self.pos += 1;
-                    let next = self.next();
+                    let next = self.next()?;
                     self.pos -= 1;
                     postfix.push(ShuntedStackItem::Operand(Node::Sizeof(Box::new(next))));
-                    last_op = None;
                     last_was_lit = true;
                 }
                 TokenType::Int(n) => {
                     if last_was_lit {
                         break;
                     }
+                    if *n == i64::MIN {
+                        // `-9223372036854775808` is i64::MIN, the literal can't be negated
+                        if op_stack.last() != Some(&Operator::Neg) || self.tokens[self.pos - 1].token_type != TokenType::Op(Operator::Minus) {
+                            return Err(int_out_of_range(token));
+                        }
+                        op_stack.pop();
+                    }
                     postfix.push(ShuntedStackItem::Operand(Node::Variable(Variable::Int(*n))));
-                    last_op = None;
                     last_was_lit = true;
                 }
                 TokenType::Float(n) => {
@@ -500,7 +592,6 @@ impl Parser {
                         break;
                     }
                     postfix.push(ShuntedStackItem::Operand(Node::Variable(Variable::Float(*n))));
-                    last_op = None;
                     last_was_lit = true;
                 }
                 TokenType::Ident(ident) => {
@@ -509,11 +600,23 @@ impl Parser {
                         break;
                     }
                     self.pos += 1;
-                    let ident = self.parse_ident(ident.clone());
+                    let ident = self.parse_ident(ident.clone())?;
                     self.pos -= 1; // because peak looks at current, not future
                     postfix.push(ShuntedStackItem::Operand(ident.clone()));
 
-                    last_op = None;
+                    last_was_lit = true;
+                }
+                TokenType::VariableType(typ) if self.tokens.get(self.pos + 1)
+                    .is_some_and(|t| t.token_type == TokenType::Op(Operator::LParen)) => {
+                    // a std function named after a type
+                    if last_was_lit {
+                        break;
+                    }
+                    self.pos += 1;
+                    let call = self.parse_ident(typ.to_string())?;
+                    self.pos -= 1;
+                    postfix.push(ShuntedStackItem::Operand(call));
+
                     last_was_lit = true;
                 }
                 TokenType::Bool(b) => {
@@ -521,15 +624,28 @@ impl Parser {
                         break;
                     }
                     postfix.push(ShuntedStackItem::Operand(Node::Variable(Variable::Bool(*b))));
-                    last_op = None;
                     last_was_lit = true;
                 }
                 TokenType::String(s) => {
                     if last_was_lit {
                         break;
                     }
-                    postfix.push(ShuntedStackItem::Operand(Node::Variable(Variable::String(s.clone()))));
-                    last_op = None;
+                    postfix.push(ShuntedStackItem::Operand(Node::Variable(Variable::String(s.as_str().into()))));
+                    last_was_lit = true;
+                }
+                TokenType::InterpolatedString(parts) => {
+                    if last_was_lit {
+                        break;
+                    }
+                    let node = self.parse_interpolation(parts)?;
+                    postfix.push(ShuntedStackItem::Operand(node));
+                    last_was_lit = true;
+                }
+                TokenType::Char(c) => {
+                    if last_was_lit {
+                        break;
+                    }
+                    postfix.push(ShuntedStackItem::Operand(Node::Variable(Variable::Char(*c))));
                     last_was_lit = true;
                 }
                 // has to be after ident
@@ -539,9 +655,9 @@ impl Parser {
                         break;
                     }
                     self.pos += 1;
-                    let arr = self.parse_array();
+                    let arr = self.parse_array()?;
+                    self.pos -= 1; // parse_array consumes the closing bracket
                     postfix.push(ShuntedStackItem::Operand(arr));
-                    last_op = None;
                     last_was_lit = true;
                 }
                 _ => break, // exit the loop
@@ -554,15 +670,37 @@ impl Parser {
             postfix.push(ShuntedStackItem::Operator(op));
         }
 
-        if negative {
-            postfix.push(ShuntedStackItem::Operator(Operator::Minus));
-        }
-
-        Node::ShuntedStack(postfix)
+        Ok(Node::ShuntedStack(postfix))
     }
 
+    // past the end this is the EOF token, so loops looking for a closing token stop there
     fn peek(&self) -> &Token {
-        &self.tokens[self.pos]
+        self.tokens.get(self.pos).or(self.tokens.last()).expect("the lexer ends the tokens with EOF")
     }
 
-}
\ No newline at end of file
+    // the error for running out of tokens in the middle of a statement
+    fn unexpected_eof(&self) -> ScriptError {
+        let pos = self.tokens.last().map_or((1, 1), |t| t.pos);
+        script_error!(at pos, "{}Unexpected end of file @ {:?}", Color::BrightRed, pos)
+    }
+
+}
+
+// the error for a closing token that never came, i.e. `Missing Right Brace ('}')`
+fn missing(what: &str, found: &Token) -> ScriptError {
+    script_error!(at found.pos, "{}Missing {}, found: {}{:?} @ {:?}", Color::BrightRed, what, Color::Red, found.token_type, found.pos)
+}
+
+// the lexer lets the magnitude of i64::MIN through for the parser to pair with a minus
+fn int_out_of_range(token: &Token) -> ScriptError {
+    script_error!(at token.pos, "{}Integer literal out of range '9223372036854775808', ints must fit in 64 bits @ {:?}", Color::BrightRed, token.pos)
+}
+
+// the name a module is imported as when there is no `as`, the file name without its extension
+pub(crate) fn module_name(path: &str) -> Option<String> {
+    let stem = std::path::Path::new(path).file_stem()?.to_str()?;
+    let mut chars = stem.chars();
+    let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_');
+    valid.then(|| stem.to_string())
+}
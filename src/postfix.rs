@@ -6,6 +6,9 @@ use crate::operator::Operator;
 pub(crate) enum ShuntedStackItem {
     Operator(Operator),
     Operand(Node),
+    // placed after the left operand of && and ||, if the left operand decides the
+    // result the evaluation jumps past the operator at the given index
+    ShortCircuit(Operator, usize),
 }
 
 impl Display for ShuntedStackItem {
@@ -13,6 +16,7 @@ impl Display for ShuntedStackItem {
         match self {
             ShuntedStackItem::Operator(op) => write!(f, "{}", op),
             ShuntedStackItem::Operand(node) => write!(f, "{}", node),
+            ShuntedStackItem::ShortCircuit(op, end) => write!(f, "{}?{}", op, end),
         }
     }
 }
@@ -21,19 +25,64 @@ impl Display for ShuntedStackItem {
 pub(crate) struct ShuntedStack {
     items: Vec<ShuntedStackItem>,
     current_iter: usize,
+    // short circuits waiting for the index of their operator
+    open_jumps: Vec<usize>,
 }
 
 impl ShuntedStack {
     pub(crate) fn new() -> Self {
         Self {
             items: Vec::new(),
-            current_iter: 0
+            current_iter: 0,
+            open_jumps: Vec::new(),
         }
     }
 
     pub(crate) fn push(&mut self, item: ShuntedStackItem) {
+        match &item {
+            ShuntedStackItem::ShortCircuit(_, _) => self.open_jumps.push(self.items.len()),
+            // operators leave the operator stack in reverse order, so the
+            // most recent open short circuit belongs to this operator
+            ShuntedStackItem::Operator(Operator::And | Operator::Or) => {
+                if let Some(i) = self.open_jumps.pop() {
+                    let end = self.items.len();
+                    if let ShuntedStackItem::ShortCircuit(_, target) = &mut self.items[i] {
+                        *target = end;
+                    }
+                }
+            }
+            _ => {}
+        }
         self.items.push(item);
     }
+
+    // the operand of a stack that holds nothing else, i.e. the stack of a bare identifier
+    pub(crate) fn single_operand(&self) -> Option<&Node> {
+        match self.items.as_slice() {
+            [ShuntedStackItem::Operand(node)] => Some(node),
+            _ => None,
+        }
+    }
+
+    // moves the items from `at` on into a stack of their own, they have to be a complete
+    // expression, i.e. a parenthesized one
+    pub(crate) fn split_off(&mut self, at: usize) -> ShuntedStack {
+        let mut rest = ShuntedStack::new();
+        // pushing again points the short circuits at the indices of the new stack
+        for item in self.items.split_off(at) {
+            rest.push(item);
+        }
+        rest
+    }
+
+    pub(crate) fn items(&self) -> &[ShuntedStackItem] {
+        &self.items
+    }
+
+    // continue iterating from the given index
+    pub(crate) fn jump(&mut self, to: usize) {
+        self.current_iter = to;
+    }
 }
 
 impl Iterator for ShuntedStack {
@@ -0,0 +1,95 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// a small deterministic random number generator (splitmix64)
+// the whole state is a single u64, so it's easy to save and restore, and the same state
+// always produces the same sequence of numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(state: u64) -> Self {
+        Rng { state }
+    }
+
+    // seeded from the system clock, for when the host doesn't need replays
+    pub(crate) fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+        Rng::new(nanos as u64)
+    }
+
+    pub(crate) fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // a float in [0, 1)
+    pub(crate) fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // an int in [0, n), without modulo bias
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        // values under the threshold would make the low results more likely
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return x % n;
+            }
+        }
+    }
+
+    // an int between lo and hi, both included
+    pub(crate) fn int_between(&mut self, lo: i64, hi: i64) -> i64 {
+        let span = (hi as u64).wrapping_sub(lo as u64).wrapping_add(1);
+        if span == 0 {
+            // lo..=hi covers every int
+            return self.next_u64() as i64;
+        }
+        lo.wrapping_add(self.below(span) as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_sequence() {
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220a8397b1dcdaf);
+        assert_eq!(rng.next_u64(), 0x6e789e6aa1b965f4);
+    }
+
+    #[test]
+    fn same_state_same_numbers() {
+        let mut a = Rng::new(42);
+        a.next_u64();
+        let mut b = Rng::new(a.state());
+        for _ in 0..100 {
+            assert_eq!(a.int_between(-5, 5), b.int_between(-5, 5));
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let n = rng.int_between(1, 6);
+            assert!((1..=6).contains(&n));
+            let f = rng.next_float();
+            assert!((0.0..1.0).contains(&f));
+        }
+        assert_eq!(rng.int_between(3, 3), 3);
+        rng.int_between(i64::MIN, i64::MAX);
+    }
+}
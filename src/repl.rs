@@ -0,0 +1,370 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use better_term::{Color, flush_styles, Style};
+use crate::debug::ScriptError;
+use crate::function::Function;
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::logging::Logger;
+use crate::matador_std;
+use crate::node::Node;
+use crate::parser::Parser;
+
+const HELP: &str = "\
+:vars          list the variables
+:funcs         list the functions
+:ast [code]    print the parsed code, or the previous entry when no code is given
+:history       list the previous entries, also those of earlier sessions
+:reset         forget all variables and functions
+:help          print this message
+:quit          leave the repl
+
+Lines are read as typed, there is no line editing or recalling entries with the arrow keys.";
+
+// an interactive session, one interpreter runs every entry so variables and functions
+// stay around between them
+pub struct Repl {
+    interpreter: Interpreter,
+    args: Vec<String>,
+    history: Vec<String>,
+    // entries are appended to this file, None keeps them in memory only
+    history_file: Option<PathBuf>,
+}
+
+impl Repl {
+    // the history is kept in ~/.matador_history
+    pub fn new(args: Vec<String>) -> Self {
+        let file = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".matador_history"));
+        Self::with_history_file(args, file)
+    }
+
+    pub fn with_history_file(args: Vec<String>, history_file: Option<PathBuf>) -> Self {
+        let history = history_file.as_deref().map_or(Vec::new(), load_history);
+        Repl {
+            interpreter: new_interpreter(&args),
+            args,
+            history,
+            history_file,
+        }
+    }
+
+    // logs of the entries go to the logger
+    pub fn set_logger(&mut self, logger: Logger) {
+        self.interpreter.set_logger(logger);
+    }
+
+    pub fn run(&mut self) {
+        println!("{}Matador {}v0.1ALPHA {}repl, type :help for commands",
+                 Style::new().fg(Color::Cyan).bold(),
+                 Style::new().overwrite().fg(Color::BrightCyan),
+                 Style::new().overwrite().fg(Color::BrightBlack));
+        flush_styles();
+        while let Some(entry) = read_entry() {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            if let Some(command) = entry.strip_prefix(':') {
+                if !self.command(command) {
+                    break;
+                }
+            } else {
+                self.add_history(entry);
+                self.eval(entry);
+                if self.interpreter.exited() {
+                    break;
+                }
+            }
+        }
+    }
+
+    // runs a meta command, returns false when the repl should stop
+    fn command(&mut self, command: &str) -> bool {
+        let (name, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        match name {
+            "vars" => {
+                for (name, value) in self.interpreter.variables() {
+                    println!("{} = {}", name, value);
+                }
+            }
+            "funcs" => {
+                let (declared, natives) = self.interpreter.functions();
+                for (name, function) in declared {
+                    match function {
+                        Function::Local(params, _) => println!("fn {}({})", name, params.join(", ")),
+                        _ => println!("fn {}", name),
+                    }
+                }
+                // one line per module, functions without a module first
+                let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+                for native in natives.iter() {
+                    let (module, name) = native.split_once('.').unwrap_or(("native", native));
+                    match groups.iter_mut().find(|(m, _)| *m == module) {
+                        Some((_, names)) => names.push(name),
+                        None => groups.push((module, vec![name])),
+                    }
+                }
+                groups.sort_by_key(|(module, _)| *module != "native");
+                for (module, names) in groups {
+                    println!("{}{}: {}{}", Color::BrightBlack, module, Color::White, names.join(", "));
+                }
+                flush_styles();
+            }
+            "ast" => {
+                let code = match rest.trim() {
+                    "" => self.history.last().map(|s| s.as_str()).unwrap_or(""),
+                    code => code,
+                };
+                match parse(code) {
+                    Ok(nodes) => Node::prgm_display(&nodes),
+                    Err(e) => print_error(&e),
+                }
+            }
+            "history" => {
+                for (i, entry) in self.history.iter().enumerate() {
+                    println!("{}{:>4} {}{}", Color::BrightBlack, i + 1, Color::White, entry);
+                }
+                flush_styles();
+            }
+            "reset" => {
+                let logger = self.interpreter.take_logger();
+                self.interpreter = new_interpreter(&self.args);
+                if let Some(logger) = logger {
+                    self.interpreter.set_logger(logger);
+                }
+            }
+            "help" => println!("{}", HELP),
+            "quit" | "q" | "exit" => return false,
+            _ => {
+                println!("{}Unknown command: {}:{}", Color::BrightRed, Color::Red, name);
+                flush_styles();
+            }
+        }
+        true
+    }
+
+    fn add_history(&mut self, entry: &str) {
+        self.history.push(entry.to_string());
+        if let Some(file) = &self.history_file {
+            // the history is a convenience, the session goes on if it can't be saved
+            let saved = std::fs::OpenOptions::new().create(true).append(true).open(file)
+                .and_then(|mut f| writeln!(f, "{}", escape_entry(entry)));
+            if saved.is_err() {
+                self.history_file = None;
+            }
+        }
+    }
+
+    // runs an entry and prints the values of its expressions
+    fn eval(&mut self, code: &str) {
+        let result = self.interpreter.with_logger(|interpreter| {
+            let nodes = parse(code)?;
+            let shown: Vec<bool> = nodes.iter().map(shows_value).collect();
+            Ok((interpreter.try_interpret(nodes)?, shown))
+        });
+        let (values, shown) = match result {
+            Ok(result) => result,
+            Err(e) => return print_error(&e),
+        };
+        for (value, shown) in values.iter().zip(shown) {
+            if shown {
+                println!("{}{}", Color::BrightBlue, value);
+            }
+        }
+        flush_styles();
+    }
+}
+
+fn new_interpreter(args: &[String]) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_args(args);
+    matador_std::attach_std(&mut interpreter);
+    interpreter
+}
+
+// one entry per line, the newlines of entries over several lines are escaped
+fn load_history(file: &Path) -> Vec<String> {
+    std::fs::read_to_string(file).map_or(Vec::new(), |history| history.lines().map(unescape_entry).collect())
+}
+
+fn escape_entry(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape_entry(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                entry.push('\n');
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                entry.push('\\');
+            }
+            _ => entry.push(c),
+        }
+    }
+    entry
+}
+
+fn print_error(error: &ScriptError) {
+    println!("{}{}", Color::BrightRed, error);
+    flush_styles();
+}
+
+fn parse(code: &str) -> Result<Vec<Node>, ScriptError> {
+    let tokens = Lexer::new(code).lex()?;
+    Parser::new(tokens).parse()
+}
+
+// whether the value of a statement is printed, statements that only do something
+// like declarations, loops and printing have no value worth showing
+fn shows_value(node: &Node) -> bool {
+    match node {
+        Node::FunctionCall(name, _) => {
+            let name = name.strip_prefix("io.").unwrap_or(name);
+            name != "print" && name != "println"
+        }
+        Node::Variable(_) | Node::Ident(_) | Node::ShuntedStack(_) | Node::Concat(_)
+        | Node::Format(_, _) | Node::Array(_) | Node::ArrayMapAccess(_, _)
+        | Node::TypeCast(_, _) | Node::TypeCheck(_, _) | Node::Sizeof(_) => true,
+        _ => false,
+    }
+}
+
+// reads lines until the open braces, brackets and parentheses are closed, None at the
+// end of input
+fn read_entry() -> Option<String> {
+    let mut entry = String::new();
+    loop {
+        print!("{}{} {}", Color::BrightBlack, if entry.is_empty() { ">>" } else { ".." }, Color::White);
+        flush_styles();
+        std::io::stdout().flush().ok();
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) if entry.is_empty() => {
+                println!();
+                return None;
+            }
+            // the input ended in the middle of an entry, run what there is
+            Ok(0) | Err(_) => return Some(entry),
+            Ok(_) => entry.push_str(&line),
+        }
+        if !is_incomplete(&entry) {
+            return Some(entry);
+        }
+    }
+}
+
+// whether code has unclosed braces, brackets, parentheses or block comments
+fn is_incomplete(code: &str) -> bool {
+    let mut depth = 0i32;
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth -= 1,
+            '"' | '\'' => {
+                // skip the literal, an unterminated one is left for the lexer to report
+                while let Some(s) = chars.next() {
+                    if s == '\\' {
+                        chars.next();
+                    } else if s == c {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|&s| s != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(s) = chars.next() {
+                    if s == '*' && chars.next_if_eq(&'/').is_some() {
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::variable::Variable;
+    use super::{escape_entry, is_incomplete, unescape_entry, Repl};
+
+    #[test]
+    fn incomplete_input() {
+        assert!(is_incomplete("fn add(a, b) {"));
+        assert!(is_incomplete("let a = [1, 2,"));
+        assert!(is_incomplete("println((1 + 2)"));
+        assert!(is_incomplete("/* a comment"));
+        assert!(!is_incomplete("fn add(a, b) { return a + b }"));
+        assert!(!is_incomplete("println(\"{ not a brace\")"));
+        assert!(!is_incomplete("let c = '('"));
+        assert!(!is_incomplete("let a = 1 // {"));
+        assert!(!is_incomplete("}"));
+    }
+
+    #[test]
+    fn incomplete_strings_and_comments() {
+        assert!(is_incomplete("let s = \"}\" + {"));
+        assert!(is_incomplete("let a = [\n1,\n2"));
+        assert!(!is_incomplete("/* { */ let a = 1"));
+        assert!(!is_incomplete("let c = '\\''"));
+    }
+
+    #[test]
+    fn commands() {
+        let mut repl = Repl::with_history_file(Vec::new(), None);
+        repl.eval("let a = 1");
+        repl.eval("fn f(x) { return x }");
+        assert_eq!(repl.interpreter.variables().len(), 1);
+        assert_eq!(repl.interpreter.functions().0.len(), 1);
+
+        // unknown commands and commands that only print keep the session going
+        for command in ["vars", "funcs", "ast", "ast 1 + 2", "history", "help", "nope"] {
+            assert!(repl.command(command), "{}", command);
+        }
+        assert!(repl.command("reset"));
+        assert!(repl.interpreter.variables().is_empty());
+        assert!(repl.interpreter.functions().0.is_empty());
+        assert!(!repl.command("quit"));
+        assert!(!repl.command("q"));
+    }
+
+    #[test]
+    fn errors_keep_the_session() {
+        let mut repl = Repl::with_history_file(Vec::new(), None);
+        repl.eval("let a = 1");
+        repl.eval("a = 2\nprintln(1 / 0)");
+        repl.eval("{ let b = 3\nprintln(b / 0) }");
+        // changes before the error stay, the scope of the failed block is gone
+        assert_eq!(repl.interpreter.variables(), vec![(&"a".to_string(), &Variable::Int(2))]);
+        assert_eq!(repl.interpreter.try_interpret(Vec::new()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn history_file() {
+        let file = std::env::temp_dir().join(format!("matador_history_{}", std::process::id()));
+        std::fs::remove_file(&file).ok();
+        let mut repl = Repl::with_history_file(Vec::new(), Some(file.clone()));
+        repl.add_history("let a = 1");
+        repl.add_history("fn f() {\n    return \"\\n\"\n}");
+        let repl = Repl::with_history_file(Vec::new(), Some(file.clone()));
+        std::fs::remove_file(&file).ok();
+        assert_eq!(repl.history, vec!["let a = 1", "fn f() {\n    return \"\\n\"\n}"]);
+        assert_eq!(unescape_entry(&escape_entry("a\\nb\nc")), "a\\nb\nc");
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
-use better_term::{Color, flush_styles};
+use better_term::Color;
+use crate::debug::{script_error, ScriptError};
 use crate::function::Function;
+use crate::logging::log;
 use crate::variable::Variable;
 
 #[derive(Debug, Clone)]
@@ -21,10 +23,6 @@ impl Scope {
         self.functions.insert(ident, function);
     }
 
-    fn function_exists(&self, ident: String) -> bool {
-        self.functions.contains_key(&ident)
-    }
-
     fn get_function(&self, ident: String) -> Option<&Function> {
         self.functions.get(&ident)
     }
@@ -41,12 +39,8 @@ impl Scope {
         self.variables.get(ident)
     }
 
-    fn get_or_else(&self, ident: &str) -> Variable {
-        self.variables.get(ident).unwrap_or_else(|| {
-            println!("{}Undefined variable: {}{}", Color::BrightRed, Color::Red, ident);
-            flush_styles();
-            std::process::exit(0);
-        }).clone()
+    fn get_mut(&mut self, ident: &str) -> Option<&mut Variable> {
+        self.variables.get_mut(ident)
     }
 
     fn remove(&mut self, ident: &str) {
@@ -68,13 +62,16 @@ impl ScopeHandler {
 
     pub(crate) fn push_scope(&mut self) {
         self.scopes.push(Scope::new());
+        log!(Scope, Trace, "push scope {}", self.scopes.len());
     }
 
     pub(crate) fn pop_scope(&mut self) {
+        log!(Scope, Trace, "pop scope {}", self.scopes.len());
         self.scopes.pop();
     }
 
     pub(crate) fn set(&mut self, ident: &str, value: Variable) {
+        log!(Scope, Trace, "set {} to {}", ident, value);
         // set a variable in one of the scopes
         for scope in self.scopes.iter_mut().rev() {
             if scope.get(ident).is_some() {
@@ -96,15 +93,22 @@ impl ScopeHandler {
         None
     }
 
-    pub(crate) fn get_or_else(&self, ident: &str) -> Variable {
+    pub(crate) fn get_mut(&mut self, ident: &str) -> Option<&mut Variable> {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(value) = scope.get_mut(ident) {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn get_or_else(&self, ident: &str) -> Result<Variable, ScriptError> {
         for scope in self.scopes.iter().rev() {
             if let Some(value) = scope.get(ident) {
-                return value.clone();
+                return Ok(value.clone());
             }
         }
-        println!("{}Undefined variable: {}{}", Color::BrightRed, Color::Red, ident);
-        flush_styles();
-        std::process::exit(0);
+        Err(script_error!("{}Undefined variable: {}{}", Color::BrightRed, Color::Red, ident))
     }
 
     pub(crate) fn get_function(&self, ident: String) -> Option<&Function> {
@@ -116,15 +120,6 @@ impl ScopeHandler {
         None
     }
 
-    pub(crate) fn function_exists(&self, ident: String) -> bool {
-        for scope in self.scopes.iter().rev() {
-            if scope.function_exists(ident.clone()) {
-                return true;
-            }
-        }
-        false
-    }
-
     pub(crate) fn variable_exists(&self, ident: &str) -> bool {
         for scope in self.scopes.iter().rev() {
             if scope.variable_exists(ident) {
@@ -135,6 +130,7 @@ impl ScopeHandler {
     }
 
     pub(crate) fn push_function(&mut self, ident: String, function: Function) {
+        log!(Scope, Trace, "declare fn {}", ident);
         self.scopes.last_mut().unwrap().push_function(ident, function);
     }
 
@@ -146,4 +142,26 @@ impl ScopeHandler {
             }
         }
     }
+
+    // the variables visible from the current scope, sorted by name
+    pub(crate) fn variables(&self) -> Vec<(&String, &Variable)> {
+        let mut variables: HashMap<&String, &Variable> = HashMap::new();
+        for scope in self.scopes.iter() {
+            variables.extend(scope.variables.iter());
+        }
+        let mut variables: Vec<_> = variables.into_iter().collect();
+        variables.sort_by(|a, b| a.0.cmp(b.0));
+        variables
+    }
+
+    // the functions visible from the current scope, sorted by name
+    pub(crate) fn functions(&self) -> Vec<(&String, &Function)> {
+        let mut functions: HashMap<&String, &Function> = HashMap::new();
+        for scope in self.scopes.iter() {
+            functions.extend(scope.functions.iter());
+        }
+        let mut functions: Vec<_> = functions.into_iter().collect();
+        functions.sort_by(|a, b| a.0.cmp(b.0));
+        functions
+    }
 }
@@ -0,0 +1,481 @@
+use std::fmt::{Display, Formatter, Write};
+use crate::lexer::{StringPart, Token, TokenType};
+use crate::node::Node;
+use crate::operator::Operator;
+use crate::postfix::{ShuntedStack, ShuntedStackItem};
+use crate::variable::Variable;
+
+// s-expressions used to dump tokens and nodes in a stable format that other tools can read
+// and that can be diffed between versions, i.e. `(let "a" (expr 1 2 (op plus)))`
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Sexpr {
+    Atom(String),
+    Str(String),
+    List(Vec<Sexpr>),
+}
+
+impl Sexpr {
+    fn atom<S: Display>(s: S) -> Sexpr {
+        Sexpr::Atom(s.to_string())
+    }
+
+    fn str<S: Into<String>>(s: S) -> Sexpr {
+        Sexpr::Str(s.into())
+    }
+
+    fn str_of<D: Display>(d: &D) -> Sexpr {
+        Sexpr::Str(d.to_string())
+    }
+
+    // a list starting with an atom naming what it holds
+    fn tagged<I: IntoIterator<Item = Sexpr>>(tag: &str, items: I) -> Sexpr {
+        let mut list = vec![Sexpr::atom(tag)];
+        list.extend(items);
+        Sexpr::List(list)
+    }
+
+    // lists that don't fit in `width` columns are broken into one child per line, the atoms
+    // at the start of a list stay on its first line
+    pub(crate) fn pretty(&self, width: usize) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0, width);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize, width: usize) {
+        let compact = self.to_string();
+        let items = match self {
+            Sexpr::List(items) if indent + compact.len() > width => items,
+            _ => {
+                out.push_str(&compact);
+                return;
+            }
+        };
+        out.push('(');
+        let head = items.iter().take_while(|item| !matches!(item, Sexpr::List(_))).count();
+        for (i, item) in items[..head].iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let _ = write!(out, "{}", item);
+        }
+        for (i, item) in items[head..].iter().enumerate() {
+            if head == 0 && i == 0 {
+                item.write_pretty(out, indent + 1, width);
+                continue;
+            }
+            out.push('\n');
+            out.push_str(&" ".repeat(indent + 2));
+            item.write_pretty(out, indent + 2, width);
+        }
+        out.push(')');
+    }
+}
+
+impl Display for Sexpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sexpr::Atom(a) => write!(f, "{}", a),
+            Sexpr::Str(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Sexpr::List(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+pub(crate) trait ToSexpr {
+    fn to_sexpr(&self) -> Sexpr;
+}
+
+impl<T: ToSexpr> ToSexpr for Box<T> {
+    fn to_sexpr(&self) -> Sexpr {
+        self.as_ref().to_sexpr()
+    }
+}
+
+// missing optional parts are written as nil so every form has the same number of items
+impl<T: ToSexpr> ToSexpr for Option<T> {
+    fn to_sexpr(&self) -> Sexpr {
+        match self {
+            Some(t) => t.to_sexpr(),
+            None => Sexpr::atom("nil"),
+        }
+    }
+}
+
+fn list<T: ToSexpr>(items: &[T]) -> impl Iterator<Item = Sexpr> + '_ {
+    items.iter().map(|item| item.to_sexpr())
+}
+
+// (kind line char value...)
+impl ToSexpr for Token {
+    fn to_sexpr(&self) -> Sexpr {
+        let (line, char) = self.pos;
+        let (kind, values): (&str, Vec<Sexpr>) = match &self.token_type {
+            TokenType::Let => ("let", vec![]),
+            TokenType::Fn => ("fn", vec![]),
+            TokenType::Return => ("return", vec![]),
+            TokenType::If => ("if", vec![]),
+            TokenType::Else => ("else", vec![]),
+            TokenType::While => ("while", vec![]),
+            TokenType::Loop => ("loop", vec![]),
+            TokenType::For => ("for", vec![]),
+            TokenType::Break => ("break", vec![]),
+            TokenType::Continue => ("continue", vec![]),
+            TokenType::In => ("in", vec![]),
+            TokenType::As => ("as", vec![]),
+            TokenType::Is => ("is", vec![]),
+            TokenType::Assign => ("assign", vec![]),
+            TokenType::Op(op) => ("op", vec![Sexpr::atom(operator_name(op))]),
+            TokenType::Decimal => ("decimal", vec![]),
+            TokenType::LBracket => ("lbracket", vec![]),
+            TokenType::RBracket => ("rbracket", vec![]),
+            TokenType::Colon => ("colon", vec![]),
+            TokenType::Comma => ("comma", vec![]),
+            TokenType::Sizeof => ("sizeof", vec![]),
+            TokenType::Drop => ("drop", vec![]),
+            TokenType::Exit => ("exit", vec![]),
+            TokenType::Import => ("import", vec![]),
+            TokenType::LBrace => ("lbrace", vec![]),
+            TokenType::RBrace => ("rbrace", vec![]),
+            TokenType::Ident(ident) => ("ident", vec![Sexpr::str(ident)]),
+            TokenType::VariableType(typ) => ("type", vec![Sexpr::atom(typ)]),
+            TokenType::Int(n) => ("int", vec![Sexpr::atom(n)]),
+            TokenType::Float(n) => ("float", vec![Sexpr::atom(format!("{:?}", n))]),
+            TokenType::Bool(b) => ("bool", vec![Sexpr::atom(b)]),
+            TokenType::String(s) => ("string", vec![Sexpr::str(s)]),
+            TokenType::Char(c) => ("char", vec![Sexpr::str(c.to_string())]),
+            TokenType::InterpolatedString(parts) => ("interpolated", list(parts).collect()),
+            TokenType::EOF => ("eof", vec![]),
+        };
+        let mut items = vec![Sexpr::atom(line), Sexpr::atom(char)];
+        items.extend(values);
+        Sexpr::tagged(kind, items)
+    }
+}
+
+impl ToSexpr for StringPart {
+    fn to_sexpr(&self) -> Sexpr {
+        match self {
+            StringPart::Literal(s) => Sexpr::tagged("literal", [Sexpr::str(s)]),
+            StringPart::Expr(source, (line, char), spec) => Sexpr::tagged("expr", [
+                Sexpr::str(source),
+                Sexpr::atom(line),
+                Sexpr::atom(char),
+                spec.as_ref().map_or(Sexpr::atom("nil"), Sexpr::str_of),
+            ]),
+        }
+    }
+}
+
+impl ToSexpr for Variable {
+    fn to_sexpr(&self) -> Sexpr {
+        match self {
+            Variable::Int(n) => Sexpr::tagged("int", [Sexpr::atom(n)]),
+            Variable::Float(n) => Sexpr::tagged("float", [Sexpr::atom(format!("{:?}", n))]),
+            Variable::String(s) => Sexpr::tagged("string", [Sexpr::str(s.as_str())]),
+            Variable::Char(c) => Sexpr::tagged("char", [Sexpr::str(c.to_string())]),
+            Variable::Bool(b) => Sexpr::tagged("bool", [Sexpr::atom(b)]),
+            Variable::Range(start, end) => Sexpr::tagged("range", [Sexpr::atom(start), Sexpr::atom(end)]),
+            Variable::Array(items) => Sexpr::tagged("array", list(items)),
+            Variable::Map(entries) => Sexpr::tagged("map", entries.iter().map(|(k, v)| {
+                Sexpr::tagged("entry", [k.to_sexpr(), v.to_sexpr()])
+            })),
+            Variable::Error(msg) => Sexpr::tagged("error", [Sexpr::str(msg)]),
+            Variable::Function(name, _) => Sexpr::tagged("function", [Sexpr::str(name)]),
+            Variable::Module(key) => Sexpr::tagged("module", [Sexpr::str(key)]),
+        }
+    }
+}
+
+impl ToSexpr for ShuntedStack {
+    fn to_sexpr(&self) -> Sexpr {
+        Sexpr::tagged("expr", self.items().iter().map(|item| match item {
+            ShuntedStackItem::Operand(node) => node.to_sexpr(),
+            ShuntedStackItem::Operator(op) => Sexpr::tagged("op", [Sexpr::atom(operator_name(op))]),
+            ShuntedStackItem::ShortCircuit(op, end) => {
+                Sexpr::tagged("short-circuit", [Sexpr::atom(operator_name(op)), Sexpr::atom(end)])
+            }
+        }))
+    }
+}
+
+impl ToSexpr for Node {
+    fn to_sexpr(&self) -> Sexpr {
+        match self {
+            // literals are written as their value
+            Node::Variable(v) => v.to_sexpr(),
+            Node::Ident(ident) => Sexpr::tagged("ident", [Sexpr::str(ident)]),
+            Node::Block(nodes) => Sexpr::tagged("block", list(nodes)),
+            Node::ShuntedStack(stack) => stack.to_sexpr(),
+            Node::VarDecl(ident, value) => Sexpr::tagged("let", [Sexpr::str(ident), value.to_sexpr()]),
+            Node::VarAssign(ident, value) => Sexpr::tagged("assign", [Sexpr::str(ident), value.to_sexpr()]),
+            Node::Concat(nodes) => Sexpr::tagged("concat", list(nodes)),
+            Node::Format(node, spec) => Sexpr::tagged("format", [node.to_sexpr(), Sexpr::str_of(spec)]),
+            Node::Array(nodes) => Sexpr::tagged("array", list(nodes)),
+            Node::ArrayMapAccess(ident, index) => Sexpr::tagged("index", [Sexpr::str(ident), index.to_sexpr()]),
+            Node::ArrayMapAssign(ident, index, value) => {
+                Sexpr::tagged("index-assign", [Sexpr::str(ident), index.to_sexpr(), value.to_sexpr()])
+            }
+            Node::TypeCast(node, typ) => Sexpr::tagged("as", [node.to_sexpr(), Sexpr::atom(typ)]),
+            Node::TypeCheck(node, typ) => Sexpr::tagged("is", [node.to_sexpr(), Sexpr::atom(typ)]),
+            Node::FunctionDecl(ident, params, body) => Sexpr::tagged("fn", [
+                Sexpr::str(ident),
+                Sexpr::List(params.iter().map(Sexpr::str_of).collect()),
+                body.to_sexpr(),
+            ]),
+            Node::FunctionCall(ident, args) => {
+                Sexpr::tagged("call", std::iter::once(Sexpr::str(ident)).chain(list(args)))
+            }
+            Node::If(cond, then, els) => Sexpr::tagged("if", [cond.to_sexpr(), then.to_sexpr(), els.to_sexpr()]),
+            Node::While(cond, body) => Sexpr::tagged("while", [cond.to_sexpr(), body.to_sexpr()]),
+            Node::Loop(body) => Sexpr::tagged("loop", [body.to_sexpr()]),
+            Node::Continue => Sexpr::tagged("continue", []),
+            Node::Break => Sexpr::tagged("break", []),
+            Node::Return(value) => Sexpr::tagged("return", [value.to_sexpr()]),
+            Node::Expression => Sexpr::tagged("expression", []),
+            Node::Negative => Sexpr::tagged("negative", []),
+            Node::Not => Sexpr::tagged("not", []),
+            Node::Sizeof(node) => Sexpr::tagged("sizeof", [node.to_sexpr()]),
+            Node::Drop(ident) => Sexpr::tagged("drop", [Sexpr::str(ident)]),
+            Node::Exit => Sexpr::tagged("exit", []),
+            Node::Import(path, name) => Sexpr::tagged("import", [Sexpr::str(path), Sexpr::str(name)]),
+            Node::EOF => Sexpr::tagged("eof", []),
+        }
+    }
+}
+
+fn operator_name(op: &Operator) -> &'static str {
+    match op {
+        Operator::Plus => "plus",
+        Operator::Minus => "minus",
+        Operator::Mul => "mul",
+        Operator::Div => "div",
+        Operator::Mod => "mod",
+        Operator::Pow => "pow",
+        Operator::Inc => "inc",
+        Operator::Dec => "dec",
+        Operator::Neg => "neg",
+        Operator::Range => "range",
+        Operator::LParen => "lparen",
+        Operator::RParen => "rparen",
+        Operator::BitAnd => "bit-and",
+        Operator::BitOr => "bit-or",
+        Operator::Xor => "xor",
+        Operator::Not => "not",
+        Operator::LShift => "lshift",
+        Operator::RShift => "rshift",
+        Operator::Eq => "eq",
+        Operator::Neq => "neq",
+        Operator::Gt => "gt",
+        Operator::Lt => "lt",
+        Operator::Gte => "gte",
+        Operator::Lte => "lte",
+        Operator::And => "and",
+        Operator::Or => "or",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::Lexer;
+    use crate::node::Node;
+    use crate::parser::Parser;
+    use crate::variable::Variable;
+    use super::{Sexpr, ToSexpr};
+
+    // the dumps are read by other tools, so the form of every token and node is pinned here
+    const TOKENS_SOURCE: &str = r#"let fn return if else while loop for break continue in as is
+= . [ ] : , ( ) + - * / % ** .. & | ^ ! << >> && || ++ -- == != > < >= <=
+sizeof drop exit import { } name int 1 2.5 true "s\n" 'c' "a{b:>3}c"
+"#;
+
+    const TOKENS: &str = r#"(let 1 1)
+(fn 1 5)
+(return 1 8)
+(if 1 15)
+(else 1 18)
+(while 1 23)
+(loop 1 29)
+(for 1 34)
+(break 1 38)
+(continue 1 44)
+(in 1 53)
+(as 1 56)
+(is 1 59)
+(assign 2 1)
+(decimal 2 3)
+(lbracket 2 5)
+(rbracket 2 7)
+(colon 2 9)
+(comma 2 11)
+(op 2 13 lparen)
+(op 2 15 rparen)
+(op 2 17 plus)
+(op 2 19 minus)
+(op 2 21 mul)
+(op 2 23 div)
+(op 2 25 mod)
+(op 2 27 pow)
+(op 2 30 range)
+(op 2 33 bit-and)
+(op 2 35 bit-or)
+(op 2 37 xor)
+(op 2 39 not)
+(op 2 41 lshift)
+(op 2 44 rshift)
+(op 2 47 and)
+(op 2 50 or)
+(op 2 53 inc)
+(op 2 56 dec)
+(op 2 59 eq)
+(op 2 62 neq)
+(op 2 65 gt)
+(op 2 67 lt)
+(op 2 69 gte)
+(op 2 72 lte)
+(sizeof 3 1)
+(drop 3 8)
+(exit 3 13)
+(import 3 18)
+(lbrace 3 25)
+(rbrace 3 27)
+(ident 3 29 "name")
+(type 3 34 int)
+(int 3 38 1)
+(float 3 40 2.5)
+(bool 3 44 true)
+(string 3 49 "s\n")
+(char 3 55 "c")
+(interpolated 3 59 (literal "a") (expr "b" 3 61 ">3") (literal "c"))
+(eof 4 1)"#;
+
+    const AST_SOURCE: &str = r#"import "lib" as l
+let a = [1, 2.5, "s", 'c', true]
+let m = {}
+a[0] = -a[1] + 2 * (3 - 1)
+m = a[0] as float
+fn f(x, y) {
+    if x is int && !y {
+        return x
+    } else {
+        drop m
+    }
+    while x < 3 {
+        x = x + 1
+        continue
+    }
+    loop {
+        break
+    }
+}
+println("v: {f(1, false):>4}", sizeof a, l.g(1..3))
+exit
+if true {
+}
+"#;
+
+    const AST: &str = r#"(import "lib" "l")
+(let "a"
+  (array (expr (int 1)) (expr (float 2.5)) (expr (string "s")) (expr (char "c")) (expr (bool true))))
+(let "m" (block))
+(index-assign "a"
+  (expr (int 0))
+  (expr (index "a" (expr (int 1))) (op neg) (int 2) (int 3) (int 1) (op minus) (op mul) (op plus)))
+(assign "m" (expr (as (index "a" (expr (int 0))) float)))
+(fn "f"
+  ("x" "y")
+  (block
+    (if
+      (expr (is (ident "x") int) (short-circuit and 4) (ident "y") (op not) (op and))
+      (block (return (expr (ident "x"))))
+      (block (drop "m")))
+    (while
+      (expr (ident "x") (int 3) (op lt))
+      (block (assign "x" (expr (ident "x") (int 1) (op plus))) (continue)))
+    (loop (expr (block (break))))))
+(call "println"
+  (expr (concat (string "v: ") (format (call "f" (expr (int 1)) (expr (bool false))) ">4")))
+  (sizeof (ident "a"))
+  (expr (call "l.g" (expr (int 1) (int 3) (op range)))))
+(exit)
+(if (expr (bool true)) (block) nil)
+(eof)"#;
+
+    fn dump<T: ToSexpr>(items: &[T]) -> String {
+        items.iter().map(|item| item.to_sexpr().pretty(100)).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn escapes_strings() {
+        let s = Sexpr::List(vec![Sexpr::atom("string"), Sexpr::str("a \"b\"\n\\")]);
+        assert_eq!(s.to_string(), r#"(string "a \"b\"\n\\")"#);
+    }
+
+    #[test]
+    fn pretty_breaks_long_lists() {
+        let call = Sexpr::tagged("call", [
+            Sexpr::str("add"),
+            Sexpr::tagged("int", [Sexpr::atom(1)]),
+            Sexpr::tagged("int", [Sexpr::atom(2)]),
+        ]);
+        assert_eq!(call.pretty(80), r#"(call "add" (int 1) (int 2))"#);
+        assert_eq!(call.pretty(10), "(call \"add\"\n  (int 1)\n  (int 2))");
+    }
+
+    #[test]
+    fn tokens_golden() {
+        let tokens = Lexer::new(TOKENS_SOURCE).lex().unwrap();
+        assert_eq!(dump(&tokens), TOKENS);
+    }
+
+    #[test]
+    fn nodes_golden() {
+        let nodes = Parser::new(Lexer::new(AST_SOURCE).lex().unwrap()).parse().unwrap();
+        assert_eq!(dump(&nodes), AST);
+
+        // values and nodes the parser doesn't make
+        let nodes = [
+            Node::Variable(Variable::Range(1, 3)),
+            Node::Variable(Variable::Map(vec![(Box::new(Variable::Int(1)), Box::new(Variable::Float(-0.5)))])),
+            Node::Variable(Variable::Error("no".to_string())),
+            Node::Variable(Variable::Function("f".to_string(), Some("lib".to_string()))),
+            Node::Variable(Variable::Module("lib".to_string())),
+            Node::Return(None),
+            Node::Expression,
+            Node::Negative,
+            Node::Not,
+        ];
+        assert_eq!(dump(&nodes), r#"(range 1 3)
+(map (entry (int 1) (float -0.5)))
+(error "no")
+(function "f")
+(module "lib")
+(return nil)
+(expression)
+(negative)
+(not)"#);
+    }
+}
@@ -0,0 +1,183 @@
+use std::ops::Range;
+use crate::debug::ScriptError;
+use crate::lexer::{Lexer, Token, TokenType};
+use crate::operator::Operator;
+
+/// What a token is, for syntax highlighting
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenCategory {
+    Keyword,
+    /// type names such as `int` or `array`
+    Type,
+    Operator,
+    /// braces, brackets, parentheses, commas, colons and dots
+    Punctuation,
+    Literal,
+    Identifier,
+    Comment,
+    Whitespace,
+    /// text that could not be lexed, such as an unterminated string
+    Error,
+}
+
+/// A token of matador source code
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceToken {
+    pub category: TokenCategory,
+    /// the bytes of the source the token covers
+    pub range: Range<usize>,
+    /// line of the first character, starting at 1
+    pub line: usize,
+    /// char of the first character in its line, starting at 1
+    pub column: usize,
+}
+
+impl SourceToken {
+    /// The text of the token in the source it was lexed from
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.range.clone()]
+    }
+}
+
+/// Splits source code into tokens. In lossless mode whitespace and comments are kept, so
+/// the tokens cover every byte of the source and joining their text gives the source back.
+/// Lexing never fails, text that can't be lexed becomes an `Error` token
+pub fn tokenize(source: &str, lossless: bool) -> Vec<SourceToken> {
+    lex_tolerant(source).into_iter().filter_map(|lexed| {
+        let (category, range, (line, column)) = match lexed {
+            Lexed::Trivia(comment, range, pos) if lossless => {
+                let category = if comment { TokenCategory::Comment } else { TokenCategory::Whitespace };
+                (category, range, pos)
+            }
+            Lexed::Trivia(..) => return None,
+            Lexed::Token(token) => (category(&token.token_type), token.range, token.pos),
+            Lexed::Error(_, range, pos) => (TokenCategory::Error, range, pos),
+        };
+        Some(SourceToken { category, range, line, column })
+    }).collect()
+}
+
+// a piece of source lexed by `lex_tolerant`, with its bytes and position
+pub(crate) enum Lexed {
+    // whitespace, or a comment when true
+    Trivia(bool, Range<usize>, (usize, usize)),
+    Token(Token),
+    Error(ScriptError, Range<usize>, (usize, usize)),
+}
+
+// lexes all of the source, going on after errors, the end of file token is left out
+pub(crate) fn lex_tolerant(source: &str) -> Vec<Lexed> {
+    let mut lexer = Lexer::new(source);
+    let mut lexed = Vec::new();
+    loop {
+        let (start, pos) = lexer.offset();
+        match lexer.next_trivia() {
+            Ok(Some(comment)) => {
+                lexed.push(Lexed::Trivia(comment, start..lexer.offset().0, pos));
+                continue;
+            }
+            Ok(None) => {}
+            // an unterminated block comment runs to the end
+            Err(e) => {
+                lexed.push(Lexed::Error(e, start..lexer.offset().0, pos));
+                break;
+            }
+        }
+
+        match lexer.next_token() {
+            Ok(token) if token.token_type == TokenType::EOF => break,
+            Ok(token) => lexed.push(Lexed::Token(token)),
+            Err(e) => {
+                // the lexer stops where the error was found, skip at least one character
+                if lexer.offset().0 == start {
+                    lexer.skip_char();
+                }
+                lexed.push(Lexed::Error(e, start..lexer.offset().0, pos));
+            }
+        }
+    }
+    lexed
+}
+
+fn category(token_type: &TokenType) -> TokenCategory {
+    match token_type {
+        TokenType::Let | TokenType::Fn | TokenType::Return | TokenType::If | TokenType::Else
+        | TokenType::While | TokenType::Loop | TokenType::For | TokenType::Break
+        | TokenType::Continue | TokenType::In | TokenType::As | TokenType::Is
+        | TokenType::Sizeof | TokenType::Drop | TokenType::Exit | TokenType::Import => TokenCategory::Keyword,
+        TokenType::VariableType(_) => TokenCategory::Type,
+        TokenType::Op(Operator::LParen | Operator::RParen) => TokenCategory::Punctuation,
+        TokenType::Op(_) | TokenType::Assign => TokenCategory::Operator,
+        TokenType::Decimal | TokenType::LBracket | TokenType::RBracket | TokenType::Colon
+        | TokenType::Comma | TokenType::LBrace | TokenType::RBrace => TokenCategory::Punctuation,
+        TokenType::Int(_) | TokenType::Float(_) | TokenType::Bool(_) | TokenType::String(_)
+        | TokenType::Char(_) | TokenType::InterpolatedString(_) => TokenCategory::Literal,
+        TokenType::Ident(_) => TokenCategory::Identifier,
+        // the end of the source has no text
+        TokenType::EOF => TokenCategory::Whitespace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, TokenCategory};
+
+    fn categories(source: &str, lossless: bool) -> Vec<(TokenCategory, &str)> {
+        tokenize(source, lossless).iter().map(|t| (t.category, t.text(source))).collect()
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let source = "let x = 5 // five\n/* block */ if x > 3 { println(\"big\") }\n";
+        let text: String = tokenize(source, true).iter().map(|t| t.text(source)).collect();
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn token_categories() {
+        assert_eq!(categories("let a: int = f(1) // c", true), vec![
+            (TokenCategory::Keyword, "let"),
+            (TokenCategory::Whitespace, " "),
+            (TokenCategory::Identifier, "a"),
+            (TokenCategory::Punctuation, ":"),
+            (TokenCategory::Whitespace, " "),
+            (TokenCategory::Type, "int"),
+            (TokenCategory::Whitespace, " "),
+            (TokenCategory::Operator, "="),
+            (TokenCategory::Whitespace, " "),
+            (TokenCategory::Identifier, "f"),
+            (TokenCategory::Punctuation, "("),
+            (TokenCategory::Literal, "1"),
+            (TokenCategory::Punctuation, ")"),
+            (TokenCategory::Whitespace, " "),
+            (TokenCategory::Comment, "// c"),
+        ]);
+        assert_eq!(categories("a /* b */ c", false), vec![
+            (TokenCategory::Identifier, "a"),
+            (TokenCategory::Identifier, "c"),
+        ]);
+    }
+
+    #[test]
+    fn errors_become_tokens() {
+        assert_eq!(categories("a $ b", false), vec![
+            (TokenCategory::Identifier, "a"),
+            (TokenCategory::Error, "$"),
+            (TokenCategory::Identifier, "b"),
+        ]);
+        assert_eq!(categories("x = \"open\ny", false), vec![
+            (TokenCategory::Identifier, "x"),
+            (TokenCategory::Operator, "="),
+            (TokenCategory::Error, "\"open"),
+            (TokenCategory::Identifier, "y"),
+        ]);
+        assert_eq!(categories("a /* open", true).last().unwrap(), &(TokenCategory::Error, "/* open"));
+    }
+
+    #[test]
+    fn positions() {
+        let tokens = tokenize("a\n  é b", false);
+        assert_eq!((tokens[1].line, tokens[1].column, tokens[1].range.clone()), (2, 3, 4..6));
+        assert_eq!((tokens[2].line, tokens[2].column, tokens[2].range.clone()), (2, 5, 7..8));
+    }
+}
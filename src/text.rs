@@ -0,0 +1,180 @@
+use std::cell::OnceCell;
+use std::fmt::{Debug, Display};
+use std::ops::Deref;
+
+// the string type of matador values
+// strings are sequences of unicode scalar values (chars), so indexes, lengths and slices
+// count chars rather than bytes. the byte offset of each char is computed the first time
+// it is needed and cached, which makes indexing O(1) afterwards
+#[derive(Clone, Default)]
+pub struct Text {
+    s: String,
+    // None when the string is ascii, in which case byte offsets are char offsets
+    offsets: OnceCell<Option<Box<[usize]>>>,
+}
+
+impl Text {
+    pub fn new(s: String) -> Self {
+        Text { s, offsets: OnceCell::new() }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.s
+    }
+
+    pub fn into_string(self) -> String {
+        self.s
+    }
+
+    fn offsets(&self) -> Option<&[usize]> {
+        self.offsets.get_or_init(|| {
+            if self.s.is_ascii() {
+                None
+            } else {
+                Some(self.s.char_indices().map(|(i, _)| i).collect())
+            }
+        }).as_deref()
+    }
+
+    // byte offset of the char at index i, i may be the length of the string
+    fn byte_offset(&self, i: usize) -> Option<usize> {
+        match self.offsets() {
+            None => (i <= self.s.len()).then_some(i),
+            Some(offsets) if i == offsets.len() => Some(self.s.len()),
+            Some(offsets) => offsets.get(i).copied(),
+        }
+    }
+
+    // number of chars in the string
+    pub fn char_len(&self) -> usize {
+        match self.offsets() {
+            None => self.s.len(),
+            Some(offsets) => offsets.len(),
+        }
+    }
+
+    pub fn char_at(&self, i: usize) -> Option<char> {
+        if i >= self.char_len() {
+            return None;
+        }
+        self.s[self.byte_offset(i)?..].chars().next()
+    }
+
+    // the chars from start up to but not including end
+    pub fn slice(&self, start: usize, end: usize) -> Option<&str> {
+        if start > end {
+            return None;
+        }
+        Some(&self.s[self.byte_offset(start)?..self.byte_offset(end)?])
+    }
+
+    // replaces the char at index i with a string
+    pub(crate) fn replace_char(&mut self, i: usize, with: &str) -> Option<()> {
+        let start = self.byte_offset(i)?;
+        let end = self.byte_offset(i + 1)?;
+        self.s.replace_range(start..end, with);
+        self.offsets = OnceCell::new();
+        Some(())
+    }
+}
+
+impl Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.s
+    }
+}
+
+impl From<String> for Text {
+    fn from(s: String) -> Self {
+        Text::new(s)
+    }
+}
+
+impl From<&str> for Text {
+    fn from(s: &str) -> Self {
+        Text::new(s.to_string())
+    }
+}
+
+impl PartialEq for Text {
+    fn eq(&self, other: &Self) -> bool {
+        self.s == other.s
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.s, f)
+    }
+}
+
+impl Debug for Text {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.s, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Text;
+
+    #[test]
+    fn char_offsets() {
+        // 1, 2, 3 and 4 byte chars
+        let text = Text::from("aé€😀b");
+        assert_eq!(text.len(), 11);
+        assert_eq!(text.char_len(), 5);
+        let chars: Vec<char> = (0..5).map(|i| text.char_at(i).unwrap()).collect();
+        assert_eq!(chars, vec!['a', 'é', '€', '😀', 'b']);
+        assert_eq!(text.char_at(5), None);
+        assert_eq!(text.char_at(usize::MAX), None);
+
+        let ascii = Text::from("abc");
+        assert_eq!(ascii.char_len(), 3);
+        assert_eq!(ascii.char_at(2), Some('c'));
+        assert_eq!(ascii.char_at(3), None);
+        assert_eq!(Text::default().char_at(0), None);
+    }
+
+    #[test]
+    fn slices() {
+        let text = Text::from("aé€😀b");
+        assert_eq!(text.slice(1, 4), Some("é€😀"));
+        // ranges may end at the length, and empty ranges are empty strings
+        assert_eq!(text.slice(3, 5), Some("😀b"));
+        assert_eq!(text.slice(0, 5), Some("aé€😀b"));
+        assert_eq!(text.slice(5, 5), Some(""));
+        assert_eq!(text.slice(2, 2), Some(""));
+        // but not past it or backwards
+        assert_eq!(text.slice(3, 6), None);
+        assert_eq!(text.slice(6, 6), None);
+        assert_eq!(text.slice(3, 2), None);
+
+        let ascii = Text::from("hello");
+        assert_eq!(ascii.slice(1, 5), Some("ello"));
+        assert_eq!(ascii.slice(0, 6), None);
+    }
+
+    #[test]
+    fn replace_char() {
+        let mut text = Text::from("aé€");
+        assert_eq!(text.replace_char(1, "xyz"), Some(()));
+        assert_eq!(text.as_str(), "axyz€");
+        // the cached offsets follow the new string
+        assert_eq!(text.char_len(), 5);
+        assert_eq!(text.char_at(4), Some('€'));
+        assert_eq!(text.replace_char(4, ""), Some(()));
+        assert_eq!(text.as_str(), "axyz");
+        assert_eq!(text.replace_char(4, "!"), None);
+
+        // an ascii string that stops being ascii
+        let mut ascii = Text::from("abc");
+        assert_eq!(ascii.char_len(), 3);
+        assert_eq!(ascii.replace_char(0, "😀"), Some(()));
+        assert_eq!(ascii.char_at(1), Some('b'));
+        assert_eq!(ascii.slice(0, 2), Some("😀b"));
+        assert_eq!(ascii.replace_char(3, "d"), None);
+    }
+}
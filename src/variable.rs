@@ -1,15 +1,20 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
-use better_term::{Color, flush_styles};
+use better_term::Color;
+use crate::debug::{script_error, ScriptError};
+use crate::text::Text;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) enum VariableType {
     Int,
     Float,
     String,
+    Char,
     Bool,
     Range,
     Array,
     Map,
+    Error,
 }
 
 impl Display for VariableType {
@@ -18,38 +23,62 @@ impl Display for VariableType {
             VariableType::Int => "int",
             VariableType::Float => "float",
             VariableType::String => "string",
+            VariableType::Char => "char",
             VariableType::Bool => "bool",
             VariableType::Range => "range",
             VariableType::Array => "array",
             VariableType::Map => "map",
+            VariableType::Error => "error",
         };
         write!(f, "{}", typ)
     }
 
 }
 
+/// How integer arithmetic behaves when a result does not fit in an `int`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Overflow {
+    /// stop the program with an integer overflow error
+    #[default]
+    Error,
+    /// wrap around at the boundary of the type (two's complement)
+    Wrap,
+}
+
+/// A matador value. Native functions build and match values through the variants, the
+/// public methods cover the comparisons and conversions the std natives share
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) enum Variable {
+pub enum Variable {
     // static types
     Int(i64),
     Float(f64),
-    String(String),
+    String(Text),
+    Char(char),
     Bool(bool),
 
     // dynamic types
+    /// a half open range, includes the start but not the end
     Range(i64, i64),
     Array(Vec<Box<Variable>>),
     Map(Vec<(Box<Variable>, Box<Variable>)>),
+
+    /// returned by functions that can fail, holds the error message
+    Error(String),
+    /// a reference to a function by name and the key of the module it is declared in, None for
+    /// the main program, used to pass functions to other functions
+    Function(String, Option<String>),
+    /// an imported module, by the key it was resolved to
+    Module(String),
 }
 
 impl Variable {
 
-    pub(crate) fn add(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a + b)),
+    pub(crate) fn add(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, "+", overflow, i64::checked_add, i64::wrapping_add)?),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Float(a + b)),
-            (Variable::String(a), b) => Some(Variable::String(format!("{}{}", a, b))), // can append anything to a string
-            (a, Variable::String(b)) => Some(Variable::String(format!("{}{}", a, b))),
+            (Variable::String(a), b) => Some(Variable::String(format!("{}{}", a, b).into())), // can append anything to a string
+            (a, Variable::String(b)) => Some(Variable::String(format!("{}{}", a, b).into())),
 
             // adding arrays
             (Variable::Array(a), Variable::Array(b)) => {
@@ -74,91 +103,83 @@ impl Variable {
                 Some(Variable::Map(map))
             }
 
-            _ => None,
-        }
+            _ => promote(self, other).map(|(a, b)| Variable::Float(a + b)),
+        })
     }
 
-    pub(crate) fn sub(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a - b)),
+    pub(crate) fn sub(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, "-", overflow, i64::checked_sub, i64::wrapping_sub)?),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Float(a - b)),
-            _ => None,
-        }
+            _ => promote(self, other).map(|(a, b)| Variable::Float(a - b)),
+        })
     }
 
-    pub(crate) fn mul(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a * b)),
+    pub(crate) fn mul(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, "*", overflow, i64::checked_mul, i64::wrapping_mul)?),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Float(a * b)),
-            _ => None,
-        }
+            _ => promote(self, other).map(|(a, b)| Variable::Float(a * b)),
+        })
     }
 
-    pub(crate) fn div(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a / b)),
+    // integer division by zero is always an error, float division follows IEEE 754 (inf / NaN)
+    pub(crate) fn div(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(0)) => return Err(division_by_zero(*a, "/")),
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, "/", overflow, i64::checked_div, i64::wrapping_div)?),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Float(a / b)),
-            _ => None,
-        }
+            _ => promote(self, other).map(|(a, b)| Variable::Float(a / b)),
+        })
     }
 
-    pub(crate) fn rem(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a % b)),
+    pub(crate) fn rem(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(0)) => return Err(division_by_zero(*a, "%")),
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, "%", overflow, i64::checked_rem, i64::wrapping_rem)?),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Float(a % b)),
-            _ => None,
-        }
+            _ => promote(self, other).map(|(a, b)| Variable::Float(a % b)),
+        })
     }
 
-    pub(crate) fn pow(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a.pow(*b as u32))),
+    pub(crate) fn pow(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(int_pow(*a, *b, overflow)?),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Float(a.powf(*b))),
-            _ => None,
-        }
+            _ => promote(self, other).map(|(a, b)| Variable::Float(a.powf(b))),
+        })
     }
 
-    pub(crate) fn inc(&self) -> Option<Variable> {
-        match self {
-            Variable::Int(a) => Some(Variable::Int(a + 1)),
+    pub(crate) fn inc(&self, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
+            Variable::Int(a) => Some(int_op(*a, 1, "+", overflow, i64::checked_add, i64::wrapping_add)?),
             Variable::Float(a) => Some(Variable::Float(a + 1.0)),
             _ => None,
-        }
+        })
     }
 
-    pub(crate) fn dec(&self) -> Option<Variable> {
-        match self {
-            Variable::Int(a) => Some(Variable::Int(a - 1)),
+    pub(crate) fn dec(&self, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
+            Variable::Int(a) => Some(int_op(*a, 1, "-", overflow, i64::checked_sub, i64::wrapping_sub)?),
             Variable::Float(a) => Some(Variable::Float(a - 1.0)),
             _ => None,
-        }
+        })
     }
 
     pub(crate) fn eq(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Bool(a == b)),
-            (Variable::Float(a), Variable::Float(b)) => Some(Variable::Bool(a == b)),
-            (Variable::String(a), Variable::String(b)) => Some(Variable::Bool(a == b)),
-            (Variable::Bool(a), Variable::Bool(b)) => Some(Variable::Bool(a == b)),
-            _ => None,
-        }
+        Some(Variable::Bool(self.equals(other)))
     }
 
     pub(crate) fn neq(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Bool(a != b)),
-            (Variable::Float(a), Variable::Float(b)) => Some(Variable::Bool(a != b)),
-            (Variable::String(a), Variable::String(b)) => Some(Variable::Bool(a != b)),
-            (Variable::Bool(a), Variable::Bool(b)) => Some(Variable::Bool(a != b)),
-            _ => None,
-        }
+        Some(Variable::Bool(!self.equals(other)))
     }
 
     pub(crate) fn gt(&self, other: &Variable) -> Option<Variable> {
         match (self, other) {
             (Variable::Int(a), Variable::Int(b)) => Some(Variable::Bool(a > b)),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Bool(a > b)),
-            _ => None,
+            _ => mixed_compare(self, other).map(|o| Variable::Bool(o.is_some_and(Ordering::is_gt)))
+                .or_else(|| self.compare(other).map(|o| Variable::Bool(o.is_gt()))),
         }
     }
 
@@ -166,7 +187,8 @@ impl Variable {
         match (self, other) {
             (Variable::Int(a), Variable::Int(b)) => Some(Variable::Bool(a < b)),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Bool(a < b)),
-            _ => None,
+            _ => mixed_compare(self, other).map(|o| Variable::Bool(o.is_some_and(Ordering::is_lt)))
+                .or_else(|| self.compare(other).map(|o| Variable::Bool(o.is_lt()))),
         }
     }
 
@@ -174,7 +196,8 @@ impl Variable {
         match (self, other) {
             (Variable::Int(a), Variable::Int(b)) => Some(Variable::Bool(a >= b)),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Bool(a >= b)),
-            _ => None,
+            _ => mixed_compare(self, other).map(|o| Variable::Bool(o.is_some_and(Ordering::is_ge)))
+                .or_else(|| self.compare(other).map(|o| Variable::Bool(o.is_ge()))),
         }
     }
 
@@ -182,6 +205,64 @@ impl Variable {
         match (self, other) {
             (Variable::Int(a), Variable::Int(b)) => Some(Variable::Bool(a <= b)),
             (Variable::Float(a), Variable::Float(b)) => Some(Variable::Bool(a <= b)),
+            _ => mixed_compare(self, other).map(|o| Variable::Bool(o.is_some_and(Ordering::is_le)))
+                .or_else(|| self.compare(other).map(|o| Variable::Bool(o.is_le()))),
+        }
+    }
+
+    /// Structural equality: arrays compare element by element, maps compare their entries
+    /// regardless of order, and values of unrelated types are never equal
+    pub fn equals(&self, other: &Variable) -> bool {
+        match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => a == b,
+            (Variable::Float(a), Variable::Float(b)) => a == b,
+            (Variable::String(a), Variable::String(b)) => a == b,
+            (Variable::Char(a), Variable::Char(b)) => a == b,
+            (Variable::Bool(a), Variable::Bool(b)) => a == b,
+            (Variable::Range(a1, b1), Variable::Range(a2, b2)) => a1 == a2 && b1 == b2,
+            (Variable::Error(a), Variable::Error(b)) => a == b,
+            (Variable::Function(a, m), Variable::Function(b, n)) => a == b && m == n,
+            (Variable::Module(a), Variable::Module(b)) => a == b,
+            (Variable::Array(a), Variable::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.equals(y))
+            }
+            (Variable::Map(a), Variable::Map(b)) => {
+                fn contains_all(a: &[(Box<Variable>, Box<Variable>)], b: &[(Box<Variable>, Box<Variable>)]) -> bool {
+                    a.iter().all(|(k, v)| {
+                        b.iter().find(|(k2, _)| k.equals(k2)).is_some_and(|(_, v2)| v.equals(v2))
+                    })
+                }
+                contains_all(a, b) && contains_all(b, a)
+            }
+            _ => mixed_compare(self, other) == Some(Some(Ordering::Equal)),
+        }
+    }
+
+    /// Ordering: numbers compare by value, strings and arrays lexicographically.
+    /// Returns None for values that have no ordering
+    pub fn compare(&self, other: &Variable) -> Option<Ordering> {
+        match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(a.cmp(b)),
+            (Variable::Float(a), Variable::Float(b)) => a.partial_cmp(b),
+            (Variable::String(a), Variable::String(b)) => Some(a.as_str().cmp(b.as_str())),
+            (Variable::Char(a), Variable::Char(b)) => Some(a.cmp(b)),
+            (Variable::Array(a), Variable::Array(b)) => {
+                for (x, y) in a.iter().zip(b.iter()) {
+                    match x.compare(y)? {
+                        Ordering::Equal => continue,
+                        o => return Some(o),
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            _ => mixed_compare(self, other).flatten(),
+        }
+    }
+
+    // a half open range, `a..b` includes a but not b
+    pub(crate) fn range(&self, other: &Variable) -> Option<Variable> {
+        match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Range(*a, *b)),
             _ => None,
         }
     }
@@ -207,18 +288,25 @@ impl Variable {
         }
     }
 
-    pub(crate) fn shl(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a << b)),
+    // shifting by a negative amount or by 64 or more bits counts as an overflow
+    // shifting by 64 or more, or by a negative amount, is an overflow. When wrapping every bit
+    // is shifted out instead, `<<` gives 0 and `>>` fills with the sign bit
+    pub(crate) fn shl(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, "<<", overflow,
+                |a, b| u32::try_from(b).ok().and_then(|b| a.checked_shl(b)),
+                |a, b| if (0..64).contains(&b) { a << b } else { 0 })?),
             _ => None,
-        }
+        })
     }
 
-    pub(crate) fn shr(&self, other: &Variable) -> Option<Variable> {
-        match (self, other) {
-            (Variable::Int(a), Variable::Int(b)) => Some(Variable::Int(a >> b)),
+    pub(crate) fn shr(&self, other: &Variable, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, other) {
+            (Variable::Int(a), Variable::Int(b)) => Some(int_op(*a, *b, ">>", overflow,
+                |a, b| u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+                |a, b| if (0..64).contains(&b) { a >> b } else { a >> 63 })?),
             _ => None,
-        }
+        })
     }
 
     pub(crate) fn not(&self) -> Option<Variable> {
@@ -243,49 +331,63 @@ impl Variable {
         }
     }
 
-    pub(crate) fn neg(&self) -> Option<Variable> {
-        match self {
-            Variable::Int(a) => Some(Variable::Int(-*a)),
+    pub(crate) fn neg(&self, overflow: Overflow) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
+            Variable::Int(a) => Some(int_op(0, *a, "-", overflow, i64::checked_sub, i64::wrapping_sub)?),
             Variable::Float(a) => Some(Variable::Float(-*a)),
             _ => None,
-        }
+        })
     }
 
-    pub(crate) fn to_int(&self) -> Option<Variable> {
-        match self {
+    pub(crate) fn to_int(&self) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
             Variable::Int(a) => Some(Variable::Int(*a)),
             Variable::Float(a) => Some(Variable::Int(*a as i64)),
-            Variable::String(a) => Some(Variable::Int(a.parse().unwrap_or_else(|_| {
-                println!("{}Invalid input: {}{}", Color::BrightRed, Color::Red, a);
-                flush_styles();
-                std::process::exit(0);
-            }))),
+            Variable::String(a) => Some(Variable::Int(a.parse().map_err(|_| {
+                script_error!("{}Invalid input: {}{}", Color::BrightRed, Color::Red, a)
+            })?)),
+            Variable::Char(a) => Some(Variable::Int(*a as i64)),
             Variable::Bool(a) => Some(Variable::Int(*a as i64)),
 
             _ => None,
-        }
+        })
     }
 
-    pub(crate) fn to_float(&self) -> Option<Variable> {
-        match self {
+    pub(crate) fn to_float(&self) -> Result<Option<Variable>, ScriptError> {
+        Ok(match self {
             Variable::Int(a) => Some(Variable::Float(*a as f64)),
             Variable::Float(a) => Some(Variable::Float(*a)),
-            Variable::String(a) => Some(Variable::Float(a.parse().unwrap_or_else(|_| {
-                println!("{}Invalid input: {}{}", Color::BrightRed, Color::Red, a);
-                flush_styles();
-                std::process::exit(0);
-            }))),
+            Variable::String(a) => Some(Variable::Float(a.parse().map_err(|_| {
+                script_error!("{}Invalid input: {}{}", Color::BrightRed, Color::Red, a)
+            })?)),
             Variable::Bool(a) => Some(Variable::Float(*a as i64 as f64)),
             _ => None,
-        }
+        })
     }
 
     pub(crate) fn to_string(&self) -> Option<Variable> {
         match self {
-            Variable::Int(a) => Some(Variable::String(a.to_string())),
-            Variable::Float(a) => Some(Variable::String(a.to_string())),
+            Variable::Int(a) => Some(Variable::String(a.to_string().into())),
+            Variable::Float(a) => Some(Variable::String(a.to_string().into())),
             Variable::String(a) => Some(Variable::String(a.clone())),
-            Variable::Bool(a) => Some(Variable::String(a.to_string())),
+            Variable::Char(a) => Some(Variable::String(a.to_string().into())),
+            Variable::Bool(a) => Some(Variable::String(a.to_string().into())),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn to_char(&self) -> Option<Variable> {
+        match self {
+            // ints are converted from their unicode code point
+            Variable::Int(a) => u32::try_from(*a).ok().and_then(char::from_u32).map(Variable::Char),
+            Variable::Char(a) => Some(Variable::Char(*a)),
+            Variable::String(a) => {
+                let mut chars = a.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(Variable::Char(c)),
+                    _ => None,
+                }
+            }
             _ => None,
         }
     }
@@ -300,11 +402,13 @@ impl Variable {
         }
     }
 
-    pub(crate) fn to_array(&self) -> Option<Variable> {
+    /// Converts the value like `as array`, strings become arrays of chars and scalars
+    /// become arrays of one element. Returns None for values that do not convert
+    pub fn to_array(&self) -> Option<Variable> {
         match self {
             Variable::Int(a) => Some(Variable::Array(vec![Box::new(Variable::Int(*a))])),
             Variable::Float(a) => Some(Variable::Array(vec![Box::new(Variable::Float(*a))])),
-            Variable::String(a) => Some(Variable::Array(a.chars().map(|c| Box::new(Variable::String(c.to_string()))).collect())),
+            Variable::String(a) => Some(Variable::Array(a.chars().map(|c| Box::new(Variable::Char(c))).collect())),
             Variable::Bool(a) => Some(Variable::Array(vec![Box::new(Variable::Bool(*a))])),
             Variable::Array(a) => Some(Variable::Array(a.clone())),
             _ => None,
@@ -325,90 +429,89 @@ impl Variable {
         }
     }
 
-    pub(crate) fn access(&self, i: Variable) -> Option<Variable> {
-        match (self, i) {
+    pub(crate) fn to_error(&self) -> Option<Variable> {
+        match self {
+            Variable::Error(e) => Some(Variable::Error(e.clone())),
+            _ => None,
+        }
+    }
+
+    // strings are indexed by char, so every index, slice and length counts unicode scalar values
+    pub(crate) fn access(&self, i: Variable) -> Result<Option<Variable>, ScriptError> {
+        Ok(match (self, i) {
             (Variable::String(s), Variable::Int(i)) => {
-                if i < 0 || i as usize >= s.len() {
-                    println!("{}Index out of range: {}{}", Color::BrightRed, Color::Red, i);
-                    flush_styles();
-                    std::process::exit(0);
-                }
-                Some(Variable::String(s.chars().nth(i as usize).unwrap().to_string()))
+                let c = usize::try_from(i).ok().and_then(|i| s.char_at(i))
+                    .ok_or_else(|| index_out_of_range(i))?;
+                Some(Variable::Char(c))
+            }
+            (Variable::String(s), Variable::Range(a, b)) => {
+                let (a, b) = slice_bounds(a, b, s.char_len());
+                s.slice(a, b).map(|s| Variable::String(s.into()))
             }
             (Variable::Array(a), Variable::Int(i)) => {
                 if i < 0 || i as usize >= a.len() {
-                    println!("{}Index out of range: {}{}", Color::BrightRed, Color::Red, i);
-                    flush_styles();
-                    std::process::exit(0);
+                    return Err(index_out_of_range(i));
                 }
                 Some(*a[i as usize].clone())
             }
+            (Variable::Array(arr), Variable::Range(a, b)) => {
+                let (a, b) = slice_bounds(a, b, arr.len());
+                Some(Variable::Array(arr[a..b].to_vec()))
+            }
             (Variable::Map(a), i) => {
                 for (k, v) in a.iter() {
-                    if k.eq(&Box::new(i.clone())) {
-                        return Some(*v.clone());
+                    if k.equals(&i) {
+                        return Ok(Some(*v.clone()));
                     }
                 }
-                println!("{}Key not found: {}{}", Color::BrightRed, Color::Red, i);
-                flush_styles();
-                std::process::exit(0);
+                return Err(script_error!("{}Key not found: {}{}", Color::BrightRed, Color::Red, i));
             }
             _ => None,
-        }
+        })
     }
 
-    pub(crate) fn assign(&mut self, i: Variable, v: Variable) -> Option<Variable> {
-        match self {
-            Variable::String(s) => {
-                // insert a string at a specific index
-                if let Variable::Int(i) = i {
-                    if i < 0 || i as usize >= s.len() {
-                        println!("{}Index out of range: {}{}", Color::BrightRed, Color::Red, i);
-                        flush_styles();
-                        std::process::exit(0);
-                    }
-                    let mut s = s.clone();
-                    let Variable::String(v) = v else {
-                        println!("{}Invalid assignment: {}{}", Color::BrightRed, Color::Red, v);
-                        flush_styles();
-                        std::process::exit(0);
-                    };
-                    s.insert_str(i as usize, &v);
-                    Some(Variable::String(s))
-                } else {
-                    None
+    // assigns in place, returns None if the value can't be indexed by `i`
+    pub(crate) fn assign(&mut self, i: Variable, v: Variable) -> Result<Option<()>, ScriptError> {
+        Ok(match (self, i) {
+            (Variable::String(s), Variable::Int(i)) => {
+                // replace the char at a specific index
+                if i < 0 || i as usize >= s.char_len() {
+                    return Err(index_out_of_range(i));
                 }
-            }
-            Variable::Array(a) => {
-                if let Variable::Int(i) = i {
-                    if i < 0 || i as usize >= a.len() {
-                        println!("{}Index out of range: {}{}", Color::BrightRed, Color::Red, i);
-                        flush_styles();
-                        std::process::exit(0);
+                let v = match v {
+                    Variable::String(v) => v.into_string(),
+                    Variable::Char(c) => c.to_string(),
+                    _ => {
+                        return Err(script_error!("{}Invalid assignment: {}{}", Color::BrightRed, Color::Red, v));
                     }
-                    a[i as usize] = Box::new(v);
-                    Some(Variable::Array(a.clone()))
-                } else {
-                    None
+                };
+                s.replace_char(i as usize, &v)
+            }
+            (Variable::Array(a), Variable::Int(i)) => {
+                if i < 0 || i as usize >= a.len() {
+                    return Err(index_out_of_range(i));
                 }
+                *a[i as usize] = v;
+                Some(())
             }
-            Variable::Map(a) => {
-                for (k, v) in a.iter_mut() {
-                    if (*k).eq(&Box::new(i.clone())) {
-                        *v = v.clone();
-                        return Some(Variable::Map(a.clone()));
+            (Variable::Map(a), i) => {
+                for (k, value) in a.iter_mut() {
+                    if k.equals(&i) {
+                        **value = v;
+                        return Ok(Some(()));
                     }
                 }
                 a.push((Box::new(i), Box::new(v)));
-                Some(Variable::Map(a.clone()))
+                Some(())
             }
             _ => None,
-        }
+        })
     }
 
-    pub(crate) fn sizeof(&self) -> Option<Variable> {
+    /// The number of chars of a string or elements of an array or map, None for other values
+    pub fn sizeof(&self) -> Option<Variable> {
         match self {
-            Variable::String(s) => Some(Variable::Int(s.len() as i64)),
+            Variable::String(s) => Some(Variable::Int(s.char_len() as i64)),
             Variable::Array(a) => Some(Variable::Int(a.len() as i64)),
             Variable::Map(a) => Some(Variable::Int(a.len() as i64)),
             _ => None,
@@ -416,14 +519,117 @@ impl Variable {
     }
 }
 
+// promotes a mixed int / float pair of operands to floats
+fn promote(a: &Variable, b: &Variable) -> Option<(f64, f64)> {
+    match (a, b) {
+        (Variable::Int(a), Variable::Float(b)) => Some((*a as f64, *b)),
+        (Variable::Float(a), Variable::Int(b)) => Some((*a, *b as f64)),
+        _ => None,
+    }
+}
+
+// compares a mixed int / float pair exactly, converting the int to a float could round it,
+// so i64::MAX would equal 2^63. None when the pair isn't mixed, Some(None) when the float is NaN
+fn mixed_compare(a: &Variable, b: &Variable) -> Option<Option<Ordering>> {
+    match (a, b) {
+        (Variable::Int(a), Variable::Float(b)) => Some(int_float_cmp(*a, *b)),
+        (Variable::Float(a), Variable::Int(b)) => Some(int_float_cmp(*b, *a).map(Ordering::reverse)),
+        _ => None,
+    }
+}
+
+fn int_float_cmp(a: i64, b: f64) -> Option<Ordering> {
+    // 2^63, the first float past i64::MAX, -2^63 is i64::MIN
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if b.is_nan() {
+        return None;
+    }
+    if b >= LIMIT {
+        return Some(Ordering::Less);
+    }
+    if b < -LIMIT {
+        return Some(Ordering::Greater);
+    }
+    // the whole part of b fits in an i64, the fraction decides ties
+    let whole = b.trunc();
+    Some(a.cmp(&(whole as i64)).then_with(|| 0.0.partial_cmp(&(b - whole)).unwrap()))
+}
+
+// applies an integer operation, handling overflow according to `overflow`
+fn int_op(a: i64, b: i64, op: &str, overflow: Overflow,
+          checked: fn(i64, i64) -> Option<i64>, wrapping: fn(i64, i64) -> i64) -> Result<Variable, ScriptError> {
+    match checked(a, b) {
+        Some(n) => Ok(Variable::Int(n)),
+        None if overflow == Overflow::Wrap => Ok(Variable::Int(wrapping(a, b))),
+        None => Err(overflow_error(a, op, b)),
+    }
+}
+
+// integer exponentiation
+// a negative exponent is treated as `1 / (a ** -b)` using integer division, so it truncates
+// to 0 unless `a` is 1 or -1, and `0 ** -b` is a division by zero
+fn int_pow(a: i64, b: i64, overflow: Overflow) -> Result<Variable, ScriptError> {
+    if b < 0 {
+        return match a {
+            0 => Err(division_by_zero(1, "/")),
+            1 => Ok(Variable::Int(1)),
+            -1 => Ok(Variable::Int(if b % 2 == 0 { 1 } else { -1 })),
+            _ => Ok(Variable::Int(0)),
+        };
+    }
+
+    let mul = |x: i64, y: i64| match overflow {
+        Overflow::Error => x.checked_mul(y),
+        Overflow::Wrap => Some(x.wrapping_mul(y)),
+    };
+
+    // square and multiply, so exponents that don't fit in a u32 still work
+    let mut result = 1i64;
+    let mut base = a;
+    let mut exp = b as u64;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base).ok_or_else(|| overflow_error(a, "**", b))?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mul(base, base).ok_or_else(|| overflow_error(a, "**", b))?;
+        }
+    }
+    Ok(Variable::Int(result))
+}
+
+// clamps the bounds of a slice to a sequence of length `len`
+fn slice_bounds(a: i64, b: i64, len: usize) -> (usize, usize) {
+    let clamp = |n: i64| n.clamp(0, len as i64) as usize;
+    let (a, b) = (clamp(a), clamp(b));
+    (a, b.max(a))
+}
+
+fn index_out_of_range(i: i64) -> ScriptError {
+    script_error!("{}Index out of range: {}{}", Color::BrightRed, Color::Red, i)
+}
+
+fn overflow_error(a: i64, op: &str, b: i64) -> ScriptError {
+    script_error!("{}Integer overflow: {}{} {} {}", Color::BrightRed, Color::Red, a, op, b)
+}
+
+fn division_by_zero(a: i64, op: &str) -> ScriptError {
+    script_error!("{}Division by zero: {}{} {} 0", Color::BrightRed, Color::Red, a, op)
+}
+
 impl Display for Variable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Variable::Int(n) => write!(f, "{}", n),
             Variable::Float(n) => write!(f, "{}", n),
             Variable::String(s) => write!(f, "{}", s),
+            Variable::Char(c) => write!(f, "{}", c),
             Variable::Bool(b) => write!(f, "{}", b),
             Variable::Range(a, b) => write!(f, "{}..{}", a, b),
+            Variable::Error(e) => write!(f, "error: {}", e),
+            Variable::Function(name, _) => write!(f, "fn {}", name),
+            Variable::Module(key) => write!(f, "module {}", key),
             Variable::Array(arr) => {
                 write!(f, "[")?;
                 for (i, v) in arr.iter().enumerate() {
@@ -446,4 +652,123 @@ impl Display for Variable {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use super::{Overflow, Variable};
+
+    const ERROR: Overflow = Overflow::Error;
+    const WRAP: Overflow = Overflow::Wrap;
+
+    fn int(n: i64) -> Variable {
+        Variable::Int(n)
+    }
+
+    fn float(n: f64) -> Variable {
+        Variable::Float(n)
+    }
+
+    fn array(items: Vec<Variable>) -> Variable {
+        Variable::Array(items.into_iter().map(Box::new).collect())
+    }
+
+    #[test]
+    fn promotion() {
+        assert_eq!(int(1).add(&float(0.5), ERROR).unwrap(), Some(float(1.5)));
+        assert_eq!(float(3.0).mul(&int(2), ERROR).unwrap(), Some(float(6.0)));
+        assert_eq!(int(2).pow(&float(0.5), ERROR).unwrap(), Some(float(2f64.sqrt())));
+        // ints stay ints
+        assert_eq!(int(7).div(&int(2), ERROR).unwrap(), Some(int(3)));
+        assert_eq!(int(-7).rem(&int(2), ERROR).unwrap(), Some(int(-1)));
+        assert_eq!(int(1).add(&Variable::Bool(true), ERROR).unwrap(), None);
+    }
+
+    #[test]
+    fn overflow_and_wrap() {
+        let max = int(i64::MAX);
+        let min = int(i64::MIN);
+        assert_eq!(max.add(&int(1), ERROR).unwrap_err().message, "Integer overflow: 9223372036854775807 + 1");
+        assert_eq!(max.add(&int(1), WRAP).unwrap(), Some(min.clone()));
+        assert_eq!(min.sub(&int(1), WRAP).unwrap(), Some(max.clone()));
+        assert!(max.mul(&int(2), ERROR).is_err());
+        assert_eq!(max.mul(&int(2), WRAP).unwrap(), Some(int(-2)));
+        assert!(min.neg(ERROR).is_err());
+        assert_eq!(min.neg(WRAP).unwrap(), Some(min.clone()));
+        assert!(min.div(&int(-1), ERROR).is_err());
+        assert_eq!(min.div(&int(-1), WRAP).unwrap(), Some(min.clone()));
+        assert!(max.inc(ERROR).is_err());
+        assert_eq!(int(2).pow(&int(62), ERROR).unwrap(), Some(int(1 << 62)));
+        assert_eq!(int(2).pow(&int(63), ERROR).unwrap_err().message, "Integer overflow: 2 ** 63");
+        assert_eq!(int(2).pow(&int(64), WRAP).unwrap(), Some(int(0)));
+        // shifts move bits out of the int without an error, the shift amount can overflow
+        assert_eq!(int(1).shl(&int(63), ERROR).unwrap(), Some(min.clone()));
+        assert_eq!(int(3).shl(&int(63), ERROR).unwrap(), Some(min.clone()));
+        assert_eq!(int(1).shl(&int(64), ERROR).unwrap_err().message, "Integer overflow: 1 << 64");
+        assert_eq!(int(1).shr(&int(-1), ERROR).unwrap_err().message, "Integer overflow: 1 >> -1");
+        // when wrapping, a shift of 64 or more shifts every bit out instead of masking the amount
+        assert_eq!(int(1).shl(&int(65), WRAP).unwrap(), Some(int(0)));
+        assert_eq!(int(-1).shl(&int(64), WRAP).unwrap(), Some(int(0)));
+        assert_eq!(int(1).shl(&int(-1), WRAP).unwrap(), Some(int(0)));
+        assert_eq!(int(5).shr(&int(65), WRAP).unwrap(), Some(int(0)));
+        assert_eq!(min.shr(&int(64), WRAP).unwrap(), Some(int(-1)));
+        assert_eq!(int(-8).shr(&int(i64::MAX), WRAP).unwrap(), Some(int(-1)));
+        assert_eq!(int(-8).shr(&int(2), WRAP).unwrap(), Some(int(-2)));
+        // floats have no overflow
+        assert_eq!(float(f64::MAX).mul(&float(2.0), ERROR).unwrap(), Some(float(f64::INFINITY)));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(int(1).div(&int(0), ERROR).unwrap_err().message, "Division by zero: 1 / 0");
+        assert_eq!(int(5).rem(&int(0), WRAP).unwrap_err().message, "Division by zero: 5 % 0");
+        assert_eq!(int(1).div(&float(0.0), ERROR).unwrap(), Some(float(f64::INFINITY)));
+        assert!(matches!(float(0.0).div(&float(0.0), ERROR).unwrap(), Some(Variable::Float(n)) if n.is_nan()));
+    }
+
+    #[test]
+    fn negative_exponents() {
+        assert_eq!(int(2).pow(&int(-1), ERROR).unwrap(), Some(int(0)));
+        assert_eq!(int(1).pow(&int(-5), ERROR).unwrap(), Some(int(1)));
+        assert_eq!(int(-1).pow(&int(-3), ERROR).unwrap(), Some(int(-1)));
+        assert_eq!(int(-1).pow(&int(-4), ERROR).unwrap(), Some(int(1)));
+        assert_eq!(int(0).pow(&int(-1), ERROR).unwrap_err().message, "Division by zero: 1 / 0");
+        assert_eq!(int(2).pow(&float(-1.0), ERROR).unwrap(), Some(float(0.5)));
+    }
+
+    #[test]
+    fn mixed_comparisons_are_exact() {
+        let max = int(i64::MAX);
+        let two_63 = float(9223372036854775808.0);
+        assert!(!max.equals(&two_63));
+        assert_eq!(max.compare(&two_63), Some(Ordering::Less));
+        assert_eq!(max.lt(&two_63), Some(Variable::Bool(true)));
+        assert!(int(i64::MIN).equals(&float(-9223372036854775808.0)));
+        assert!(int(1).equals(&float(1.0)));
+        assert_eq!(int(1).compare(&float(1.5)), Some(Ordering::Less));
+        assert_eq!(float(-1.5).compare(&int(-1)), Some(Ordering::Less));
+        assert_eq!(int(-1).compare(&float(-1.5)), Some(Ordering::Greater));
+        assert_eq!(int(3).compare(&float(f64::NEG_INFINITY)), Some(Ordering::Greater));
+        // NaN is unordered and unequal
+        assert_eq!(int(1).compare(&float(f64::NAN)), None);
+        assert_eq!(int(1).gte(&float(f64::NAN)), Some(Variable::Bool(false)));
+        assert!(!int(0).equals(&float(f64::NAN)));
+    }
+
+    #[test]
+    fn structural_equality() {
+        assert!(array(vec![int(1), array(vec![float(2.0)])]).equals(&array(vec![float(1.0), array(vec![int(2)])])));
+        assert!(!array(vec![int(1)]).equals(&array(vec![int(1), int(1)])));
+        let map = |entries: Vec<(Variable, Variable)>| {
+            Variable::Map(entries.into_iter().map(|(k, v)| (Box::new(k), Box::new(v))).collect())
+        };
+        let a = map(vec![(Variable::String("a".into()), int(1)), (Variable::String("b".into()), int(2))]);
+        let b = map(vec![(Variable::String("b".into()), int(2)), (Variable::String("a".into()), int(1))]);
+        assert!(a.equals(&b));
+        assert!(!a.equals(&map(vec![(Variable::String("a".into()), int(1))])));
+        // unrelated types are never equal
+        assert!(!int(1).equals(&Variable::Bool(true)));
+        assert!(!Variable::Char('1').equals(&Variable::String("1".into())));
+        assert_eq!(int(1).compare(&Variable::String("1".into())), None);
+    }
+}